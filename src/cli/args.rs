@@ -1,3 +1,5 @@
+use crate::commands::GraphFormat;
+use crate::common::ConfigKind;
 use clap::{Parser, Subcommand};
 use std::path::PathBuf;
 
@@ -6,6 +8,10 @@ use std::path::PathBuf;
 #[command(about = "A tool for managing multiple ephemeral environments in Terraform with layered dependencies and resource sharing")]
 #[command(version)]
 pub struct Cli {
+    /// Run as if envie was started in this directory
+    #[arg(short = 'C', long = "chdir", global = true)]
+    pub chdir: Option<PathBuf>,
+
     #[command(subcommand)]
     pub command: Commands,
 }
@@ -36,9 +42,9 @@ pub enum Commands {
         #[arg(short = 'S', long)]
         service: Option<String>,
         
-        /// The ID of the environment to deploy (e.g., MR number, feature branch, etc.)
+        /// The ID of the environment to deploy (optional - inferred from CI variables or the git branch)
         #[arg(long)]
-        merge_request: String,
+        merge_request: Option<String>,
         
         /// Override environment for specific dependencies (format: service:environment)
         /// Example: -E database:stable.sandbox -E networking:ephemeral.456
@@ -53,24 +59,122 @@ pub enum Commands {
         #[arg(long)]
         no_prompt: bool,
         
+        /// Write the full Terraform output of every module to .envie/logs/<run-id>/
+        #[arg(long)]
+        log: bool,
+        
+        /// Maximum number of modules to run at the same time
+        #[arg(long, default_value_t = 1)]
+        parallelism: usize,
+        
+        /// Keep running modules that don't depend on a failed module instead of stopping at the first failure
+        #[arg(long)]
+        continue_on_error: bool,
+        
+        /// Print detailed output during execution
+        #[arg(long)]
+        verbose: bool,
+    },
+    /// Plan a service and its ephemeral dependencies, saving one plan file per module
+    Plan {
+        /// The name of the service to plan (optional - will auto-discover from current directory)
+        #[arg(short = 'S', long)]
+        service: Option<String>,
+        
+        /// The ID of the environment to plan (optional - inferred from CI variables or the git branch)
+        #[arg(long)]
+        merge_request: Option<String>,
+        
+        /// Override environment for specific dependencies (format: service:environment)
+        #[arg(short = 'E', long, action = clap::ArgAction::Append)]
+        environment: Vec<String>,
+        
+        /// Print the plan summary as JSON
+        #[arg(long)]
+        json: bool,
+        
+        /// Write the full Terraform output of every module to .envie/logs/<run-id>/
+        #[arg(long)]
+        log: bool,
+        
+        /// Maximum number of modules to run at the same time
+        #[arg(long, default_value_t = 1)]
+        parallelism: usize,
+        
+        /// Keep running modules that don't depend on a failed module instead of stopping at the first failure
+        #[arg(long)]
+        continue_on_error: bool,
+        
+        /// Print detailed output during execution
+        #[arg(long)]
+        verbose: bool,
+    },
+    /// Apply the plans saved by `envie plan`, in dependency order
+    Apply {
+        /// The service the plans were made for (optional - checked against the saved plans)
+        #[arg(short = 'S', long)]
+        service: Option<String>,
+        
+        /// The ID of the environment to apply (optional - inferred from CI variables or the git branch)
+        #[arg(long)]
+        merge_request: Option<String>,
+        
+        /// Apply the saved plan files instead of planning again
+        #[arg(long)]
+        from_plans: bool,
+        
+        /// Write the full Terraform output of every module to .envie/logs/<run-id>/
+        #[arg(long)]
+        log: bool,
+        
         /// Print detailed output during execution
         #[arg(long)]
         verbose: bool,
     },
-    /// Destroy the environment for a specific service or component
+    /// Destroy the ephemeral environment of a service, dependents first
     Destroy {
-        /// The ID of the merge request to base the destruction on
+        /// The name of the service to destroy (optional - will auto-discover from current directory)
+        #[arg(short = 'S', long)]
+        service: Option<String>,
+        
+        /// The ID of the environment to destroy (optional - inferred from CI variables or the git branch)
         #[arg(long)]
         merge_request: Option<String>,
         
-        /// Simulate the destruction process without making changes
+        /// Override environment for specific dependencies, as passed to deploy (format: service:environment)
+        #[arg(short = 'E', long, action = clap::ArgAction::Append)]
+        environment: Vec<String>,
+        
+        /// Print the destroy order without making changes
         #[arg(short = 'D', long)]
         dry_run: bool,
         
+        /// Write the full Terraform output of every module to .envie/logs/<run-id>/
+        #[arg(long)]
+        log: bool,
+        
+        /// Maximum number of modules to run at the same time
+        #[arg(long, default_value_t = 1)]
+        parallelism: usize,
+        
+        /// Keep running modules that don't depend on a failed module instead of stopping at the first failure
+        #[arg(long)]
+        continue_on_error: bool,
+        
         /// Print detailed output during execution
         #[arg(long)]
         verbose: bool,
     },
+    /// Show what is deployed to an ephemeral environment, from its deployment manifest
+    Status {
+        /// The ID of the environment (optional if only one is deployed)
+        #[arg(long)]
+        merge_request: Option<String>,
+        
+        /// Print the manifest as JSON
+        #[arg(long)]
+        json: bool,
+    },
     /// Manage ephemeral development environments
     Env {
         #[command(subcommand)]
@@ -85,6 +189,14 @@ pub enum Commands {
         /// Path to the Terraform output file (instead of calling envie output)
         #[arg(long)]
         file: Option<PathBuf>,
+        
+        /// The service whose outputs are used (optional - defaults to the only deployed service)
+        #[arg(short = 'S', long)]
+        service: Option<String>,
+        
+        /// The ID of the environment to read outputs from (optional if only one is deployed)
+        #[arg(long)]
+        merge_request: Option<String>,
     },
     /// List all available development environments
     List,
@@ -94,6 +206,14 @@ pub enum Commands {
         #[arg(short = 'f', long)]
         file: Option<PathBuf>,
         
+        /// The service whose outputs are combined (optional - defaults to the only deployed service)
+        #[arg(short = 'S', long)]
+        service: Option<String>,
+        
+        /// The ID of the environment to read outputs from (optional if only one is deployed)
+        #[arg(long)]
+        merge_request: Option<String>,
+        
         /// Print detailed output during execution
         #[arg(long)]
         verbose: bool,
@@ -126,18 +246,73 @@ pub enum Commands {
         #[arg(long)]
         dependencies: bool,
         
+        /// Show one module, as service/module or a module name (defaults to the current directory with --vars)
+        #[arg(long)]
+        module: Option<String>,
+        
+        /// Show the module's effective Terraform variables and where each was set
+        #[arg(long)]
+        vars: bool,
+        
+        /// The environment to resolve variables for (ephemeral, stable.sandbox, ...)
+        #[arg(long, default_value = "ephemeral")]
+        environment: String,
+        
         /// Print detailed output during execution
         #[arg(long)]
         verbose: bool,
     },
+    /// Print the resolved module dependency graph as DOT, Mermaid or JSON
+    Graph {
+        /// Limit the graph to the modules a deployment of this service involves
+        #[arg(short = 'S', long)]
+        service: Option<String>,
+        
+        /// The ID of the environment to resolve dependencies for (optional - inferred from CI variables or the git branch)
+        #[arg(long)]
+        merge_request: Option<String>,
+        
+        /// Override environment for specific dependencies, as passed to deploy (format: service:environment)
+        #[arg(short = 'E', long, action = clap::ArgAction::Append)]
+        environment: Vec<String>,
+        
+        /// Output format
+        #[arg(long, value_enum, default_value_t = GraphFormat::Dot)]
+        format: GraphFormat,
+    },
+    /// List the modules that depend on a module and the outputs each of them reads
+    Dependents {
+        /// The module, as service/module or a module name
+        module: String,
+        
+        /// The service to look the module name up in
+        #[arg(short = 'S', long)]
+        service: Option<String>,
+        
+        /// Also list the modules that depend on the dependents, and so on
+        #[arg(long)]
+        transitive: bool,
+        
+        /// Print the dependents as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Check every workspace, service and module config and report all problems
+    Validate,
+    /// Print the JSON Schema of a config file, for editor completion and validation
+    Schema {
+        /// The config file to describe
+        #[arg(value_enum, default_value_t = ConfigKind::Workspace)]
+        kind: ConfigKind,
+    },
 }
 
 #[derive(Subcommand)]
 pub enum EnvCommands {
     /// Start a new ephemeral dev environment
     Start {
-        /// The ID of the merge request
-        merge_request_id: String,
+        /// The ID of the environment (optional - inferred from CI variables or the git branch)
+        merge_request_id: Option<String>,
         
         /// Run commands silently without displaying output
         #[arg(long)]
@@ -145,7 +320,7 @@ pub enum EnvCommands {
     },
     /// Destroy the specified or current active development environment
     Destroy {
-        /// The ID of the merge request (optional)
+        /// The ID of the environment (optional - inferred from CI variables or the git branch)
         merge_request_id: Option<String>,
         
         /// Run commands silently without displaying output
@@ -2,17 +2,36 @@ use crate::cli::args::*;
 use crate::commands::*;
 use crate::common::*;
 use std::collections::HashMap;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 pub struct CommandHandler {
+    /// Project root: the nearest directory with a workspace.envie or .envie.yaml
     working_directory: PathBuf,
+    /// Directory envie was invoked from, used to infer the service
+    current_directory: PathBuf,
 }
 
 impl CommandHandler {
     pub fn new() -> Self {
-        Self {
-            working_directory: std::env::current_dir().unwrap_or_else(|_| PathBuf::from(".")),
-        }
+        let current_directory = std::env::current_dir().unwrap_or_else(|_| PathBuf::from("."));
+        Self::for_directory(&current_directory).unwrap_or_else(|_| Self {
+            working_directory: current_directory.clone(),
+            current_directory,
+        })
+    }
+
+    /// Handler for a run started in `directory`, working on the project that contains it
+    pub fn for_directory(directory: &Path) -> Result<Self> {
+        let current_directory = directory.canonicalize().map_err(|e| EnvieError::FileSystemError(
+            format!("Cannot change to directory {}: {}", directory.display(), e)
+        ))?;
+        let working_directory = ServiceRegistry::find_project_root(&current_directory)
+            .unwrap_or_else(|| current_directory.clone());
+
+        Ok(Self {
+            working_directory,
+            current_directory,
+        })
     }
 
     pub async fn handle_command(&self, command: Commands) -> Result<()> {
@@ -30,7 +49,8 @@ impl CommandHandler {
                     verbose,
                 };
 
-                let init_command = InitCommand::new(self.working_directory.clone());
+                // A new project starts where envie is run, not in an enclosing project
+                let init_command = InitCommand::new(self.current_directory.clone());
                 init_command.execute(options).await
             }
             Commands::Deploy {
@@ -39,6 +59,9 @@ impl CommandHandler {
                 environment,
                 dry_run,
                 no_prompt: _no_prompt,
+                log,
+                parallelism,
+                continue_on_error,
                 verbose,
             } => {
                 let environments = self.parse_environments(environment)?;
@@ -49,21 +72,81 @@ impl CommandHandler {
                     environment_overrides: environments,
                     dry_run,
                     no_prompt: false,
+                    log,
+                    execution: ExecutionOptions::new(parallelism, continue_on_error),
                     verbose,
+                    current_directory: self.current_directory.clone(),
                 };
 
                 let deployer = DeployV2Command::new(self.working_directory.clone());
                 deployer.execute(options).await
             }
+            Commands::Plan {
+                service,
+                merge_request,
+                environment,
+                json,
+                log,
+                parallelism,
+                continue_on_error,
+                verbose,
+            } => {
+                let environments = self.parse_environments(environment)?;
+                
+                let options = PlanOptions {
+                    service_name: service,
+                    merge_request,
+                    environment_overrides: environments,
+                    json,
+                    log,
+                    execution: ExecutionOptions::new(parallelism, continue_on_error),
+                    verbose,
+                    current_directory: self.current_directory.clone(),
+                };
+
+                let planner = PlanCommand::new(self.working_directory.clone());
+                planner.execute(options).await
+            }
+            Commands::Apply {
+                service,
+                merge_request,
+                from_plans,
+                log,
+                verbose,
+            } => {
+                let options = ApplyOptions {
+                    service_name: service,
+                    merge_request,
+                    from_plans,
+                    log,
+                    verbose,
+                    current_directory: self.current_directory.clone(),
+                };
+
+                let applier = ApplyCommand::new(self.working_directory.clone());
+                applier.execute(options).await
+            }
             Commands::Destroy {
+                service,
                 merge_request,
+                environment,
                 dry_run,
+                log,
+                parallelism,
+                continue_on_error,
                 verbose,
             } => {
+                let environments = self.parse_environments(environment)?;
+                
                 let options = DestroyOptions {
+                    service_name: service,
                     merge_request,
+                    environment_overrides: environments,
                     dry_run,
+                    log,
+                    execution: ExecutionOptions::new(parallelism, continue_on_error),
                     verbose,
+                    current_directory: self.current_directory.clone(),
                 };
 
                 let destroyer = DestroyCommand::new(self.working_directory.clone());
@@ -72,12 +155,24 @@ impl CommandHandler {
             Commands::Env { command } => {
                 self.handle_env_command(command).await
             }
-            Commands::Generate { env_file, file } => {
+            Commands::Status { merge_request, json } => {
+                let options = StatusOptions {
+                    merge_request,
+                    json,
+                };
+
+                let status = StatusCommand::new(self.working_directory.clone());
+                status.execute(options).await
+            }
+            Commands::Generate { env_file, file, service, merge_request } => {
                 let use_envie_output = file.is_none();
                 let options = GenerateOptions {
                     env_file,
                     output_file: file,
                     use_envie_output,
+                    service_name: service,
+                    merge_request,
+                    current_directory: self.current_directory.clone(),
                 };
 
                 let generator = GenerateCommand::new(self.working_directory.clone());
@@ -87,10 +182,13 @@ impl CommandHandler {
                 let lister = ListCommand::new(self.working_directory.clone());
                 lister.list()
             }
-            Commands::Output { file, verbose } => {
+            Commands::Output { file, service, merge_request, verbose } => {
                 let options = OutputOptions {
+                    service_name: service,
+                    merge_request,
                     output_file: file.map(|p| p.to_string_lossy().to_string()),
                     verbose,
+                    current_directory: self.current_directory.clone(),
                 };
 
                 let output = OutputCommand::new(self.working_directory.clone());
@@ -114,18 +212,69 @@ impl CommandHandler {
                 service,
                 modules,
                 dependencies,
+                module,
+                vars,
+                environment,
                 verbose,
             } => {
                 let options = ShowOptions {
                     service,
                     modules,
                     dependencies,
+                    module,
+                    vars,
+                    environment,
                     verbose,
+                    current_directory: self.current_directory.clone(),
                 };
 
                 let shower = ShowCommand::new(self.working_directory.clone());
                 shower.execute(options)
             }
+            Commands::Graph {
+                service,
+                merge_request,
+                environment,
+                format,
+            } => {
+                let environments = self.parse_environments(environment)?;
+                
+                let options = GraphOptions {
+                    service_name: service,
+                    merge_request,
+                    environment_overrides: environments,
+                    format,
+                };
+
+                let grapher = GraphCommand::new(self.working_directory.clone());
+                grapher.execute(options)
+            }
+            Commands::Dependents {
+                module,
+                service,
+                transitive,
+                json,
+            } => {
+                let options = DependentsOptions {
+                    module,
+                    service,
+                    transitive,
+                    json,
+                };
+
+                let dependents = DependentsCommand::new(self.working_directory.clone());
+                dependents.execute(options)
+            }
+            Commands::Validate => {
+                let validator = ValidateCommand::new(self.working_directory.clone());
+                validator.execute()
+            }
+            Commands::Schema { kind } => {
+                let options = SchemaOptions { kind };
+
+                let schema = SchemaCommand::new();
+                schema.execute(options)
+            }
         }
     }
 
@@ -148,7 +297,7 @@ impl CommandHandler {
                 quiet,
             } => {
                 let options = EnvOptions {
-                    merge_request_id: merge_request_id.unwrap_or_default(),
+                    merge_request_id,
                     quiet,
                 };
 
@@ -202,6 +351,21 @@ mod tests {
         assert!(handler.working_directory.exists());
     }
 
+    #[test]
+    fn test_for_directory_finds_project_root() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path().canonicalize().unwrap();
+        std::fs::write(root.join("workspace.envie"), "version: \"1.0\"\n").unwrap();
+        let module_dir = root.join("services").join("api").join("modules").join("lambda");
+        std::fs::create_dir_all(&module_dir).unwrap();
+
+        let handler = CommandHandler::for_directory(&module_dir).unwrap();
+        assert_eq!(handler.working_directory, root);
+        assert_eq!(handler.current_directory, module_dir);
+
+        assert!(CommandHandler::for_directory(&root.join("missing")).is_err());
+    }
+
     #[test]
     fn test_parse_environments() {
         let temp_dir = TempDir::new().unwrap();
@@ -0,0 +1,230 @@
+use crate::common::*;
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+#[derive(Debug, Clone)]
+pub struct ApplyOptions {
+    pub service_name: Option<String>,
+    /// Inferred from CI variables or the git branch when not given
+    pub merge_request: Option<String>,
+    pub from_plans: bool,
+    pub log: bool,
+    pub verbose: bool,
+    /// Where envie was invoked; the service is inferred from it when none is named
+    pub current_directory: PathBuf,
+}
+
+pub struct ApplyCommand {
+    working_directory: PathBuf,
+    output_manager: OutputManager,
+}
+
+impl ApplyCommand {
+    pub fn new(working_directory: PathBuf) -> Self {
+        Self {
+            working_directory,
+            output_manager: OutputManager::new(),
+        }
+    }
+
+    pub async fn execute(&self, options: ApplyOptions) -> Result<()> {
+        if !options.from_plans {
+            return Err(EnvieError::ValidationError(
+                "envie apply only applies saved plans. Use --from-plans, or 'envie deploy' to plan and apply in one step.".to_string()
+            ));
+        }
+
+        let environment_id = EnvironmentId::resolve(options.merge_request.as_deref(), &self.working_directory)?;
+        if environment_id.source != IdSource::Flag {
+            self.output_manager.print_gray(&format!("Using environment ID {}", environment_id.describe()));
+        }
+        let context = DeploymentContext::load(&self.working_directory, &environment_id.id)?;
+        let service = context.select_service(options.service_name.as_deref(), &options.current_directory)?;
+        let workspace = &context.workspace;
+        let uses_workspaces = context.environment_resolver.environment_config.ephemeral.backend.kind()?.supports_workspaces();
+
+        let store = PlanStore::new(&self.working_directory);
+        let saved = store.load(workspace)?;
+        if service.config.name != saved.service {
+            return Err(EnvieError::ValidationError(format!(
+                "Saved plans for {} were made for service '{}', not '{}'. Run 'envie plan --service {}' first.",
+                workspace, saved.service, service.config.name, service.config.name
+            )));
+        }
+
+        self.verify(&saved)?;
+        // The plan the saved plans were made from, for the manifest
+        let plan = context.plan(&saved.service, &saved.environment_overrides)?;
+
+        let logs = options.log.then(|| RunLogs::new(&self.working_directory));
+        if let Some(logs) = &logs {
+            self.output_manager.print_gray(&format!("Writing Terraform logs to {}", logs.dir().display()));
+        }
+
+        self.output_manager.print_green(&format!(
+            "Applying saved plans for service {} in {} (planned {})",
+            saved.service, workspace, saved.created_at.format("%Y-%m-%d %H:%M:%S UTC")
+        ));
+
+        let mut statuses = HashMap::new();
+        let mut failure = None;
+        for plan in &saved.modules {
+            self.output_manager.print_green(&format!("  Applying module: {}", plan.module));
+
+            let terraform_manager = TerraformManager::new(&plan.module_path)
+                .with_verbose(options.verbose)
+                .with_label(&plan.module)
+                .with_log_file(logs.as_ref().map(|logs| logs.module_log(&plan.module)));
+            // The saved backend file (part of the fingerprint) points at this environment's state
+            let applied = terraform_manager.init_reconfigure()
+                .and_then(|_| if uses_workspaces { terraform_manager.workspace_select(workspace) } else { Ok(()) })
+                .and_then(|_| terraform_manager.apply_plan(&plan.plan_file));
+            match applied {
+                Ok(()) => {
+                    statuses.insert(plan.module.clone(), ModuleStatus::Deployed);
+                }
+                Err(e) => {
+                    statuses.insert(plan.module.clone(), ModuleStatus::Failed);
+                    failure = Some(e);
+                    break;
+                }
+            }
+        }
+
+        // Recorded like a deploy, even if it failed
+        context.record_deployment(&saved.service, &plan, &statuses, &saved.environment_overrides)?;
+        if let Some(e) = failure {
+            return Err(e);
+        }
+
+        // A saved plan can only be applied once
+        store.clear(workspace)?;
+
+        self.output_manager.print_success(&format!("Applied {} saved plan(s)", saved.modules.len()));
+        Ok(())
+    }
+
+    /// Refuse to apply anything if a plan file is missing or a module changed since it was planned
+    fn verify(&self, saved: &SavedPlanSet) -> Result<()> {
+        let mut problems = Vec::new();
+
+        for plan in &saved.modules {
+            if !plan.plan_file.exists() {
+                problems.push(format!("{}: plan file {} is missing", plan.module, plan.plan_file.display()));
+            } else if !plan.module_path.is_dir() {
+                problems.push(format!("{}: module directory {} no longer exists", plan.module, plan.module_path.display()));
+            } else if module_fingerprint(&plan.module_path)? != plan.fingerprint {
+                problems.push(format!("{}: source or generated files changed since the plan was made", plan.module));
+            }
+        }
+
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(EnvieError::ValidationError(format!(
+                "Refusing to apply saved plans for {}:\n  {}\nRun 'envie plan' again.",
+                saved.workspace,
+                problems.join("\n  ")
+            )))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::plan::{PlanCommand, PlanOptions};
+    use crate::common::test_support::write_file;
+    use chrono::Utc;
+    use std::fs;
+    use tempfile::TempDir;
+
+    fn saved_plan(temp_dir: &TempDir) -> SavedPlanSet {
+        let module_path = temp_dir.path().join("services/api/modules/lambda");
+        fs::create_dir_all(&module_path).unwrap();
+        fs::write(module_path.join("main.tf"), "# lambda\n").unwrap();
+
+        let store = PlanStore::new(temp_dir.path());
+        let plan_file = store.plan_path("myapp-123", "api", "lambda");
+        fs::create_dir_all(plan_file.parent().unwrap()).unwrap();
+        fs::write(&plan_file, "plan").unwrap();
+
+        SavedPlanSet {
+            workspace: "myapp-123".to_string(),
+            service: "api".to_string(),
+            environment_overrides: HashMap::new(),
+            created_at: Utc::now(),
+            modules: vec![SavedPlan {
+                module: "api/lambda".to_string(),
+                fingerprint: module_fingerprint(&module_path).unwrap(),
+                module_path,
+                plan_file,
+            }],
+        }
+    }
+
+    #[test]
+    fn test_verify_accepts_unchanged_modules() {
+        let temp_dir = TempDir::new().unwrap();
+        let saved = saved_plan(&temp_dir);
+        let command = ApplyCommand::new(temp_dir.path().to_path_buf());
+
+        assert!(command.verify(&saved).is_ok());
+    }
+
+    #[test]
+    fn test_verify_refuses_changed_module_or_missing_plan() {
+        let temp_dir = TempDir::new().unwrap();
+        let saved = saved_plan(&temp_dir);
+        let command = ApplyCommand::new(temp_dir.path().to_path_buf());
+
+        fs::write(saved.modules[0].module_path.join(".envie-remote-state.tf"), "# regenerated\n").unwrap();
+        let error = command.verify(&saved).unwrap_err().to_string();
+        assert!(error.contains("api/lambda: source or generated files changed"));
+
+        fs::remove_file(&saved.modules[0].plan_file).unwrap();
+        let error = command.verify(&saved).unwrap_err().to_string();
+        assert!(error.contains("plan file"));
+        assert!(error.contains("is missing"));
+    }
+
+    #[test]
+    fn test_apply_records_manifest() {
+        if which::which("terraform").is_err() {
+            return;
+        }
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        write_file(root, "workspace.envie", "version: \"1.0\"\nproject:\n  name: myapp\nservices:\n  - path: services/api\nenvironments:\n  ephemeral:\n    backend:\n      type: local\n");
+        write_file(root, "services/api/.envie", "name: api\nmodules:\n  - name: lambda\n");
+        write_file(root, "services/api/modules/lambda/main.tf", "output \"greeting\" {\n  value = \"hello\"\n}\n");
+        let module_dir = root.join("services/api/modules/lambda");
+
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        runtime.block_on(PlanCommand::new(root.to_path_buf()).execute(PlanOptions {
+            service_name: None,
+            merge_request: Some("123".to_string()),
+            environment_overrides: HashMap::new(),
+            json: true,
+            log: false,
+            execution: ExecutionOptions::new(1, false),
+            verbose: false,
+            current_directory: module_dir.clone(),
+        })).unwrap();
+        runtime.block_on(ApplyCommand::new(root.to_path_buf()).execute(ApplyOptions {
+            service_name: None,
+            merge_request: Some("123".to_string()),
+            from_plans: true,
+            log: false,
+            verbose: false,
+            current_directory: module_dir,
+        })).unwrap();
+
+        let manifest = ManifestStore::new(root).load("myapp-123").unwrap().unwrap();
+        assert_eq!(manifest.merge_request, "123");
+        let modules = &manifest.services["api"].modules;
+        assert_eq!(modules.len(), 1);
+        assert_eq!(modules[0].module, "api/lambda");
+        assert_eq!(modules[0].status, ModuleStatus::Deployed);
+    }
+}
@@ -0,0 +1,229 @@
+use crate::common::*;
+use serde::Serialize;
+use std::collections::{BTreeSet, HashSet, VecDeque};
+use std::path::PathBuf;
+
+#[derive(Debug, Clone)]
+pub struct DependentsOptions {
+    /// Module to look up, as `service/module` or a module name
+    pub module: String,
+    /// Service the module name is looked up in
+    pub service: Option<String>,
+    /// Also list the modules that depend on the dependents, and so on
+    pub transitive: bool,
+    /// Print the dependents as JSON
+    pub json: bool,
+}
+
+/// A module that depends on the queried module, directly or through other dependents
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Dependent {
+    /// `service/module` key
+    pub module: String,
+    /// The module it depends on: the queried one, or with `--transitive` another dependent
+    pub depends_on: String,
+    /// Configured environment of the dependency (ephemeral, stable.sandbox, ...)
+    pub environment: String,
+    /// Outputs of `depends_on` the module reads; empty for service-level `depends`
+    pub outputs: BTreeSet<String>,
+}
+
+pub struct DependentsCommand {
+    working_directory: PathBuf,
+    output_manager: OutputManager,
+}
+
+impl DependentsCommand {
+    pub fn new(working_directory: PathBuf) -> Self {
+        Self {
+            working_directory,
+            output_manager: OutputManager::new(),
+        }
+    }
+
+    pub fn execute(&self, options: DependentsOptions) -> Result<()> {
+        let registry = ServiceRegistry::discover_from_path(&self.working_directory)?;
+        let graph = DependencyGraph::from_registry(&registry)?;
+        let (key, _) = registry.find_module(&options.module, options.service.as_deref())?;
+
+        let dependents = find_dependents(&registry, &graph, key, options.transitive)?;
+        if options.json {
+            println!("{}", serde_json::to_string_pretty(&dependents)?);
+            return Ok(());
+        }
+        if dependents.is_empty() {
+            self.output_manager.print_gray(&format!("No modules depend on {}", key));
+            return Ok(());
+        }
+
+        self.output_manager.print_green(&format!("Modules that depend on {}:", key));
+        let module_width = dependents.iter().map(|d| d.module.len()).chain(std::iter::once("MODULE".len())).max().unwrap_or(0);
+        let via_width = dependents.iter().map(|d| d.depends_on.len()).chain(std::iter::once("DEPENDS ON".len())).max().unwrap_or(0);
+        let environment_width = dependents.iter().map(|d| d.environment.len()).chain(std::iter::once("ENVIRONMENT".len())).max().unwrap_or(0);
+
+        println!("  {:<module_width$}  {:<via_width$}  {:<environment_width$}  OUTPUTS READ", "MODULE", "DEPENDS ON", "ENVIRONMENT");
+        for dependent in &dependents {
+            let outputs = if dependent.outputs.is_empty() {
+                "-".to_string()
+            } else {
+                dependent.outputs.iter().cloned().collect::<Vec<_>>().join(", ")
+            };
+            println!(
+                "  {:<module_width$}  {:<via_width$}  {:<environment_width$}  {}",
+                dependent.module, dependent.depends_on, dependent.environment, outputs
+            );
+        }
+
+        Ok(())
+    }
+}
+
+/// Modules that depend on `module`, nearest first. With `transitive`, the dependents of every
+/// dependent are followed as well; each module is listed once, under the first module it was
+/// reached through.
+pub fn find_dependents(
+    registry: &ServiceRegistry,
+    graph: &DependencyGraph,
+    module: &str,
+    transitive: bool,
+) -> Result<Vec<Dependent>> {
+    let scanner = TerraformScanner::new()?;
+    let mut seen = HashSet::from([module.to_string()]);
+    let mut queue = VecDeque::from([module.to_string()]);
+    let mut dependents = Vec::new();
+
+    while let Some(target) = queue.pop_front() {
+        for (node, edge) in graph.dependents(&target) {
+            if !seen.insert(node.to_string()) {
+                continue;
+            }
+            dependents.push(Dependent {
+                module: node.to_string(),
+                depends_on: target.clone(),
+                environment: edge.environment.clone(),
+                outputs: outputs_read(registry, &scanner, node, &target)?,
+            });
+            if transitive {
+                queue.push_back(node.to_string());
+            }
+        }
+    }
+
+    Ok(dependents)
+}
+
+/// Outputs of `target` that `consumer` reads through the data sources Envie generates for it:
+/// every `data.terraform_remote_state.<name>.outputs.<output>` reference in its own Terraform
+/// files, and every `local.<name>.<output>` reference for its `remote_states`
+fn outputs_read(
+    registry: &ServiceRegistry,
+    scanner: &TerraformScanner,
+    consumer: &str,
+    target: &str,
+) -> Result<BTreeSet<String>> {
+    let module = registry.modules.get(consumer).ok_or_else(|| EnvieError::ValidationError(
+        format!("Module '{}' not found", consumer)
+    ))?;
+    let mut data_sources = Vec::new();
+    let mut locals = Vec::new();
+    let mut outputs = BTreeSet::new();
+
+    for dependency in &module.config.depends {
+        if registry.resolve_module_reference(&dependency.path, consumer)? == target {
+            let (service, module_name) = split_module_key(target);
+            data_sources.push(format!("{}_{}", service, module_name));
+        }
+    }
+    for remote in &module.config.remote_states {
+        if registry.resolve_module_reference(&remote.source, consumer)? == target {
+            data_sources.push(remote.name.clone());
+            locals.push(remote.name.clone());
+        }
+    }
+
+    for data_source in data_sources {
+        outputs.extend(scanner.scan_used_outputs(&module.path, &data_source)?);
+    }
+    for local in locals {
+        outputs.extend(scanner.scan_used_locals(&module.path, &local)?);
+    }
+
+    Ok(outputs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::test_support::write_file;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_find_dependents_with_outputs_read() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        write_file(root, "workspace.envie", "version: \"1.0\"\nservices:\n  - path: services/networking\n  - path: services/api\n  - path: services/web\n");
+        write_file(root, "services/networking/.envie", "name: networking\nmodules:\n  - name: vpc\n");
+        write_file(root, "services/api/.envie", r#"
+name: api
+modules:
+  - name: lambda
+    depends:
+      - path: ../../networking/modules/vpc
+        environment: stable.sandbox
+  - name: gateway
+    depends: [lambda]
+    remote_states:
+      - name: network
+        source: networking/vpc
+        outputs: [vpc_id, public_subnet_ids]
+"#);
+        write_file(root, "services/api/modules/lambda/main.tf", r#"
+resource "aws_lambda_function" "this" {
+  vpc_config {
+    subnet_ids         = data.terraform_remote_state.networking_vpc.outputs.private_subnet_ids
+    security_group_ids = [data.terraform_remote_state.networking_vpc.outputs.lambda-sg-id]
+  }
+}
+"#);
+        // Only the remote state outputs the module uses are read
+        write_file(root, "services/api/modules/gateway/main.tf", "resource \"aws_api_gateway_vpc_link\" \"this\" {\n  target_arns = [local.network.vpc_id]\n}\n");
+        // Generated files are not the module's own code
+        write_file(root, "services/api/modules/lambda/.envie-remote-state.tf", "# data.terraform_remote_state.networking_vpc.outputs.generated\n");
+        write_file(root, "services/web/.envie", "name: web\nmodules:\n  - name: site\ndepends: [../api]\n");
+
+        let registry = ServiceRegistry::discover_from_path(root).unwrap();
+        let graph = DependencyGraph::from_registry(&registry).unwrap();
+
+        let direct = find_dependents(&registry, &graph, "networking/vpc", false).unwrap();
+        assert_eq!(direct, vec![
+            Dependent {
+                module: "api/lambda".to_string(),
+                depends_on: "networking/vpc".to_string(),
+                environment: "stable.sandbox".to_string(),
+                outputs: BTreeSet::from(["lambda-sg-id".to_string(), "private_subnet_ids".to_string()]),
+            },
+            Dependent {
+                module: "api/gateway".to_string(),
+                depends_on: "networking/vpc".to_string(),
+                environment: "ephemeral".to_string(),
+                outputs: BTreeSet::from(["vpc_id".to_string()]),
+            },
+        ]);
+        assert_eq!(serde_json::to_value(&direct[1]).unwrap(), serde_json::json!({
+            "module": "api/gateway",
+            "depends_on": "networking/vpc",
+            "environment": "ephemeral",
+            "outputs": ["vpc_id"],
+        }));
+
+        let transitive: Vec<(String, String)> = find_dependents(&registry, &graph, "networking/vpc", true).unwrap()
+            .into_iter()
+            .map(|dependent| (dependent.module, dependent.depends_on))
+            .collect();
+        assert_eq!(transitive, vec![
+            ("api/lambda".to_string(), "networking/vpc".to_string()),
+            ("api/gateway".to_string(), "networking/vpc".to_string()),
+            ("web/site".to_string(), "api/lambda".to_string()),
+        ]);
+    }
+}
@@ -1,11 +1,27 @@
 use crate::common::*;
+use std::collections::HashMap;
 use std::path::PathBuf;
 
 #[derive(Debug, Clone)]
 pub struct DestroyOptions {
+    pub service_name: Option<String>,
+    /// Inferred from CI variables or the git branch when not given
     pub merge_request: Option<String>,
+    pub environment_overrides: HashMap<String, String>,
     pub dry_run: bool,
+    pub log: bool,
+    pub execution: ExecutionOptions,
     pub verbose: bool,
+    /// Where envie was invoked; the service is inferred from it when none is named
+    pub current_directory: PathBuf,
+}
+
+/// What happened to a module of the ephemeral environment
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ModuleDestroyResult {
+    Destroyed,
+    /// The workspace never existed in the module's backend
+    NotDeployed,
 }
 
 pub struct DestroyCommand {
@@ -22,141 +38,210 @@ impl DestroyCommand {
     }
 
     pub async fn execute(&self, options: DestroyOptions) -> Result<()> {
-        let envie_dir = self.working_directory.join(".envie");
-        let terraform_manager = TerraformManager::new(&envie_dir);
-
-        // Get workspace
-        let workspace = if let Some(merge_request) = options.merge_request {
-            let repo_name = std::env::current_dir()?
-                .file_name()
-                .and_then(|name| name.to_str())
-                .ok_or_else(|| EnvieError::ValidationError("Could not determine repository name".to_string()))?
-                .to_string();
-            format!("{}-{}", repo_name, merge_request)
-        } else {
-            terraform_manager.workspace_show()?
+        let environment_id = EnvironmentId::resolve(options.merge_request.as_deref(), &self.working_directory)?;
+        if environment_id.source != IdSource::Flag {
+            self.output_manager.print_gray(&format!("Using environment ID {}", environment_id.describe()));
+        }
+        let context = DeploymentContext::load(&self.working_directory, &environment_id.id)?
+            .with_logs(options.log && !options.dry_run);
+        let service = context.select_service(options.service_name.as_deref(), &options.current_directory)?;
+        let service_name = service.config.name.clone();
+
+        let store = context.manifests();
+        let mut manifest = store.load(&context.workspace)?;
+        let targets = match &manifest {
+            Some(manifest) if manifest.services.contains_key(&service_name) => {
+                destroy_targets_from_manifest(&context, manifest, &service_name, &options.environment_overrides)?
+            }
+            _ => {
+                self.output_manager.print_warning(&format!(
+                    "No deployment of {} recorded in {}; destroying the modules deploy would create",
+                    service_name,
+                    store.path(&context.workspace).display()
+                ));
+                destroy_targets_from_plan(&context, &service_name, &options.environment_overrides)?
+            }
         };
+        let to_destroy: HashMap<&str, &PlannedModule> = targets.modules.iter()
+            .map(|planned| (planned.key.as_str(), planned))
+            .collect();
+        let executor = destroy_executor(&context, &targets.modules);
 
-        // Validate workspace
-        if workspace == "default" {
-            return Err(EnvieError::ValidationError(
-                "No active development environment to destroy".to_string()
-            ));
+        if options.dry_run {
+            self.print_destroy_order(&context, &executor, &targets);
+            return Ok(());
         }
 
-        if !terraform_manager.workspace_list()?.contains(&workspace) {
-            return Err(EnvieError::ValidationError(
-                format!("Envie environment '{}' does not exist", workspace)
-            ));
+        self.output_manager.print_green(&format!(
+            "Destroying service {} in {}", service_name, context.workspace
+        ));
+        if let Some(logs) = &context.logs {
+            self.output_manager.print_gray(&format!("Writing Terraform logs to {}", logs.dir().display()));
         }
 
-        // Select workspace
-        terraform_manager.workspace_select(&workspace)?;
+        let mut summary = executor.run(options.execution, |key| {
+            self.destroy_module(&context, to_destroy[key], &targets.environment_overrides, options.verbose)
+        });
 
-        // Get service name and dependencies from terraform state
-        let service_name = terraform_manager.output_value("service")?
-            .as_str()
-            .ok_or_else(|| EnvieError::TerraformError("Service name not found in terraform state".to_string()))?
-            .to_string();
+        // Forget what is gone; the service is dropped from the manifest once all of it is
+        if let Some(manifest) = &mut manifest {
+            if summary.failed().next().is_none() {
+                manifest.services.remove(&service_name);
+            } else if let Some(deployment) = manifest.services.get_mut(&service_name) {
+                for (module, _) in summary.succeeded() {
+                    if let Some(recorded) = deployment.modules.iter_mut().find(|m| m.module == module) {
+                        recorded.status = ModuleStatus::NotDeployed;
+                    }
+                }
+            }
+            store.save_or_remove(manifest)?;
+        }
 
-        let dependencies: Vec<String> = terraform_manager.output_value("dependencies")?
-            .as_array()
-            .ok_or_else(|| EnvieError::TerraformError("Dependencies not found in terraform state".to_string()))?
-            .iter()
-            .filter_map(|v| v.as_str().map(|s| s.to_string()))
+        for (module, reason) in &targets.skipped {
+            summary.push_skipped(module, reason);
+        }
+        let not_deployed: Vec<String> = summary.succeeded()
+            .filter(|(_, result)| **result == ModuleDestroyResult::NotDeployed)
+            .map(|(module, _)| module.to_string())
             .collect();
-
-        if options.dry_run {
-            self.print_destroy_order(&dependencies, &service_name);
-            return Ok(());
+        for module in &not_deployed {
+            self.output_manager.print_gray(&format!("  {} was never deployed to {}", module, context.workspace));
         }
 
-        // Destroy components
-        self.destroy_components(&dependencies).await?;
-
-        // Destroy envie state
-        self.destroy_envie_state(&service_name, &workspace).await?;
-
-        self.output_manager.print_green(&format!(">> Successfully destroyed envie environment: {}", workspace));
+        summary.print(&self.output_manager);
+        summary.into_result()?;
 
+        self.output_manager.print_green(&format!(">> Successfully destroyed envie environment: {}", context.workspace));
         Ok(())
     }
 
-    fn print_destroy_order(&self, dependencies: &[String], service_name: &str) {
-        self.output_manager.print_green(&format!("Destroy order for service: {}", service_name));
-        
-        let mut index = 1;
-        for dep in dependencies.iter().rev() {
-            let parts: Vec<&str> = dep.split(':').collect();
-            if parts.len() == 2 {
-                let comp_name = parts[0];
-                let comp_env = parts[1];
-                
-                if comp_env != "dev" {
-                    self.output_manager.print_blue(&format!("  {}. {}: {} (skipped)", index, comp_name, comp_env));
-                } else {
-                    self.output_manager.print_yellow(&format!("  {}. {}: {}", index, comp_name, comp_env));
-                }
-            }
-            index += 1;
+    /// Destroy a module's resources in the ephemeral workspace, then delete the workspace
+    fn destroy_module(
+        &self,
+        context: &DeploymentContext,
+        planned: &PlannedModule,
+        environment_overrides: &HashMap<String, String>,
+        verbose: bool,
+    ) -> Result<ModuleDestroyResult> {
+        // Remote state data sources must still resolve while the module is destroyed
+        let module = context.write_module_files(planned, environment_overrides)?;
+
+        let terraform_manager = context.terraform(&planned.key, &module.path, verbose);
+        terraform_manager.init_reconfigure()?;
+
+        // Without workspaces the environment's state lives at an address of its own
+        if !planned.resolved.backend.kind()?.supports_workspaces() {
+            self.output_manager.print_green(&format!("  Destroying module: {}", planned.key));
+            terraform_manager.destroy(&[])?;
+            return Ok(ModuleDestroyResult::Destroyed);
         }
-    }
 
-    async fn destroy_components(&self, dependencies: &[String]) -> Result<()> {
-        self.output_manager.print_green(">> Destroying deployments for service");
-
-        for dep in dependencies.iter().rev() {
-            let parts: Vec<&str> = dep.split(':').collect();
-            if parts.len() == 2 {
-                let comp_name = parts[0];
-                let comp_env = parts[1];
-                
-                if comp_env == "dev" {
-                    self.output_manager.print_green(&format!(">> Destroying component: {}", comp_name));
-                    self.destroy_component(comp_name).await?;
-                } else {
-                    self.output_manager.print_green(&format!(">> Skipping destruction of component: {} in environment: {}", comp_name, comp_env));
-                }
-            }
+        if !terraform_manager.workspace_list()?.contains(&context.workspace) {
+            return Ok(ModuleDestroyResult::NotDeployed);
         }
 
-        Ok(())
-    }
+        self.output_manager.print_green(&format!("  Destroying module: {}", planned.key));
+        terraform_manager.workspace_select(&context.workspace)?;
+        terraform_manager.destroy(&[])?;
 
-    async fn destroy_component(&self, component: &str) -> Result<()> {
-        let _component_dir = self.working_directory.join("services").join(component).join("temp_deployments");
-        
-        // This would call the actual destroy command
-        // For now, we'll just create a placeholder
-        self.output_manager.print_green(&format!("Component {} destroyed successfully", component));
-        
-        Ok(())
+        // A workspace cannot delete itself while selected
+        terraform_manager.workspace_select("default")?;
+        terraform_manager.workspace_delete(&context.workspace)?;
+
+        Ok(ModuleDestroyResult::Destroyed)
     }
 
-    async fn destroy_envie_state(&self, service_name: &str, workspace: &str) -> Result<()> {
-        let envie_dir = self.working_directory.join(".envie");
-        let terraform_manager = TerraformManager::new(&envie_dir);
+    fn print_destroy_order(&self, context: &DeploymentContext, executor: &ModuleExecutor, targets: &DestroyTargets) {
+        self.output_manager.print_yellow(&format!("Destroy order for {}:", context.workspace));
+
+        for (i, module) in executor.order().iter().enumerate() {
+            self.output_manager.print_yellow(&format!("  {}. {}: {}", i + 1, module, context.workspace));
+        }
+
+        for (module, reason) in &targets.skipped {
+            self.output_manager.print_blue(&format!("  -  {}: {} (skipped)", module, reason));
+        }
+    }
+}
 
-        // Destroy terraform configuration
-        let vars = vec![
-            ("service", service_name),
-            ("dependencies", "[]"),
-        ];
-        terraform_manager.destroy(&vars)?;
+/// The modules a destroy removes, in dependency order, and those it leaves alone
+#[derive(Debug)]
+struct DestroyTargets {
+    modules: Vec<PlannedModule>,
+    skipped: Vec<(String, String)>,
+    environment_overrides: HashMap<String, String>,
+}
 
-        // Select default workspace
-        terraform_manager.workspace_select("default")?;
+/// What the manifest says was deployed for the service, minus modules another deployed service
+/// of the same environment still uses
+fn destroy_targets_from_manifest(
+    context: &DeploymentContext,
+    manifest: &EnvironmentManifest,
+    service_name: &str,
+    environment_overrides: &HashMap<String, String>,
+) -> Result<DestroyTargets> {
+    let deployment = &manifest.services[service_name];
 
-        // Delete the workspace
-        terraform_manager.workspace_delete(workspace)?;
+    // Overrides given now take precedence over the ones recorded at deploy time
+    let mut overrides: HashMap<String, String> = deployment.environment_overrides.clone().into_iter().collect();
+    overrides.extend(environment_overrides.iter().map(|(key, value)| (key.clone(), value.clone())));
 
-        Ok(())
+    let mut targets = DestroyTargets {
+        modules: Vec::new(),
+        skipped: Vec::new(),
+        environment_overrides: overrides,
+    };
+    for recorded in &deployment.modules {
+        if recorded.status == ModuleStatus::External {
+            targets.skipped.push((recorded.module.clone(), format!(
+                "resolves to {} (workspace {})", recorded.environment, recorded.workspace
+            )));
+        } else if !recorded.status.has_resources() {
+            targets.skipped.push((recorded.module.clone(), "not deployed".to_string()));
+        } else {
+            let owners = manifest.other_owners(&recorded.module, service_name);
+            if owners.is_empty() {
+                targets.modules.push(context.planned_from_manifest(recorded)?);
+            } else {
+                targets.skipped.push((recorded.module.clone(), format!(
+                    "still deployed for {}", owners.join(", ")
+                )));
+            }
+        }
     }
+    Ok(targets)
+}
+
+/// The closure deploy would produce: only modules that resolve to this ephemeral workspace
+fn destroy_targets_from_plan(
+    context: &DeploymentContext,
+    service_name: &str,
+    environment_overrides: &HashMap<String, String>,
+) -> Result<DestroyTargets> {
+    let plan = context.plan(service_name, environment_overrides)?;
+    Ok(DestroyTargets {
+        modules: plan.to_deploy().cloned().collect(),
+        skipped: plan.skipped()
+            .map(|planned| (planned.key.clone(), format!(
+                "resolves to {} (workspace {})", planned.environment, planned.resolved.workspace
+            )))
+            .collect(),
+        environment_overrides: environment_overrides.clone(),
+    })
+}
+
+/// Dependents are destroyed before the modules they depend on
+fn destroy_executor(context: &DeploymentContext, modules: &[PlannedModule]) -> ModuleExecutor {
+    ModuleExecutor::new(
+        modules.iter().map(|planned| planned.key.clone()).collect(),
+        &context.graph,
+    ).reversed()
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::fs;
     use tempfile::TempDir;
 
     #[test]
@@ -166,17 +251,101 @@ mod tests {
         assert_eq!(destroyer.working_directory, temp_dir.path());
     }
 
-    #[test]
-    fn test_destroy_order_printing() {
+    fn fixture() -> TempDir {
         let temp_dir = TempDir::new().unwrap();
-        let destroyer = DestroyCommand::new(temp_dir.path().to_path_buf());
-        
-        let dependencies = vec![
-            "service1/component1:dev".to_string(),
-            "service1/component2:prod".to_string(),
-        ];
-        
-        // This test just ensures the function doesn't panic
-        destroyer.print_destroy_order(&dependencies, "service1");
+        let root = temp_dir.path();
+        fs::write(root.join("workspace.envie"), r#"
+version: "1.0"
+project:
+  name: myapp
+services:
+  - path: services/api
+  - path: services/database
+  - path: services/networking
+environments:
+  ephemeral:
+    backend:
+      type: local
+      config: {}
+  stable:
+    sandbox:
+      workspace: sandbox
+      backend:
+        type: local
+        config: {}
+"#).unwrap();
+        for (name, config) in [
+            ("networking", "name: networking\nmodules:\n  - name: vpc\n"),
+            ("database", "name: database\nmodules:\n  - name: dynamodb\n"),
+            ("api", r#"
+name: api
+modules:
+  - name: lambda
+    depends:
+      - path: ../../networking/modules/vpc
+        environment: ephemeral
+      - path: ../../database/modules/dynamodb
+        environment: stable.sandbox
+  - name: gateway
+    depends: [lambda]
+"#),
+        ] {
+            let service_dir = root.join("services").join(name);
+            fs::create_dir_all(&service_dir).unwrap();
+            fs::write(service_dir.join(".envie"), config).unwrap();
+        }
+
+        temp_dir
+    }
+
+    #[test]
+    fn test_destroy_order_reverses_ephemeral_closure() {
+        let temp_dir = fixture();
+        let context = DeploymentContext::load(temp_dir.path(), "123").unwrap();
+        let targets = destroy_targets_from_plan(&context, "api", &HashMap::new()).unwrap();
+        let executor = destroy_executor(&context, &targets.modules);
+
+        assert_eq!(context.workspace, "myapp-123");
+        assert_eq!(executor.order(), &["api/gateway", "api/lambda", "networking/vpc"]);
+        assert_eq!(targets.skipped[0].0, "database/dynamodb");
+    }
+
+    #[test]
+    fn test_manifest_targets_keep_modules_other_services_use() {
+        let temp_dir = fixture();
+        let context = DeploymentContext::load(temp_dir.path(), "123").unwrap();
+
+        let module = |module: &str, environment: &str, workspace: &str, status| ManifestModule {
+            module: module.to_string(),
+            environment: environment.to_string(),
+            workspace: workspace.to_string(),
+            status,
+        };
+        let deployment = |modules| ServiceDeployment {
+            environment_overrides: Default::default(),
+            modules,
+            deployed_at: chrono::Utc::now(),
+        };
+
+        let mut manifest = EnvironmentManifest::new("myapp-123", "123");
+        manifest.record("api", deployment(vec![
+            module("networking/vpc", "ephemeral", "myapp-123", ModuleStatus::Deployed),
+            module("database/dynamodb", "stable.sandbox", "sandbox", ModuleStatus::External),
+            module("api/lambda", "ephemeral", "myapp-123", ModuleStatus::Failed),
+            module("api/gateway", "ephemeral", "myapp-123", ModuleStatus::NotDeployed),
+        ]), None);
+        manifest.record("networking", deployment(vec![
+            module("networking/vpc", "ephemeral", "myapp-123", ModuleStatus::Deployed),
+        ]), None);
+
+        let targets = destroy_targets_from_manifest(&context, &manifest, "api", &HashMap::new()).unwrap();
+
+        let modules: Vec<&str> = targets.modules.iter().map(|m| m.key.as_str()).collect();
+        assert_eq!(modules, vec!["api/lambda"]);
+        assert_eq!(targets.skipped, vec![
+            ("networking/vpc".to_string(), "still deployed for networking".to_string()),
+            ("database/dynamodb".to_string(), "resolves to stable.sandbox (workspace sandbox)".to_string()),
+            ("api/gateway".to_string(), "not deployed".to_string()),
+        ]);
     }
 }
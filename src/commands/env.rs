@@ -1,10 +1,10 @@
 use crate::common::*;
 use std::path::PathBuf;
-use regex::Regex;
 
 #[derive(Debug, Clone)]
 pub struct EnvOptions {
-    pub merge_request_id: String,
+    /// Inferred from CI variables or the git branch when not given
+    pub merge_request_id: Option<String>,
     pub quiet: bool,
 }
 
@@ -22,11 +22,7 @@ impl EnvCommand {
     }
 
     pub async fn start(&self, options: EnvOptions) -> Result<()> {
-        // Validate merge request ID
-        self.validate_merge_request_id(&options.merge_request_id)?;
-
-        // Format workspace name
-        let workspace_name = self.format_workspace_name(&options.merge_request_id)?;
+        let workspace_name = self.resolve_workspace_name(options.merge_request_id.as_deref())?;
 
         // Initialize terraform
         let terraform_manager = TerraformManager::new(&self.working_directory);
@@ -56,13 +52,7 @@ impl EnvCommand {
     pub async fn destroy(&self, options: EnvOptions) -> Result<()> {
         let terraform_manager = TerraformManager::new(&self.working_directory);
 
-        // Get workspace name
-        let workspace_name = if let Some(merge_request_id) = Some(&options.merge_request_id) {
-            self.validate_merge_request_id(merge_request_id)?;
-            self.format_workspace_name(merge_request_id)?
-        } else {
-            terraform_manager.workspace_show()?
-        };
+        let workspace_name = self.resolve_workspace_name(options.merge_request_id.as_deref())?;
 
         // Validate workspace
         if workspace_name == "default" {
@@ -91,96 +81,81 @@ impl EnvCommand {
         Ok(())
     }
 
+    /// Environments with a deployment manifest, i.e. deployed by `envie deploy`
     pub fn list(&self) -> Result<()> {
-        let terraform_manager = TerraformManager::new(&self.working_directory);
-        let workspaces = terraform_manager.workspace_list()?;
-        
-        let dev_workspaces: Vec<String> = workspaces
-            .into_iter()
-            .filter(|w| w != "default")
-            .collect();
+        let manifests = ManifestStore::new(&self.working_directory).list()?;
 
-        if dev_workspaces.is_empty() {
+        if manifests.is_empty() {
             self.output_manager.print_yellow("No development environments available.");
             return Ok(());
         }
 
-        // Remove repository name prefix from workspace names
-        let repo_name = std::env::current_dir()?
-            .file_name()
-            .and_then(|name| name.to_str())
-            .ok_or_else(|| EnvieError::ValidationError("Could not determine repository name".to_string()))?
-            .to_string();
-        let clean_workspaces: Vec<String> = dev_workspaces
-            .into_iter()
-            .map(|w| {
-                if w.starts_with(&format!("{}-", repo_name)) {
-                    w.strip_prefix(&format!("{}-", repo_name)).unwrap_or(&w).to_string()
-                } else {
-                    w
-                }
-            })
-            .collect();
-
         self.output_manager.print_green("Available development environments:");
-        for workspace in clean_workspaces {
-            self.output_manager.print_blue(&workspace);
+        for manifest in manifests {
+            let services: Vec<&str> = manifest.services.keys().map(|s| s.as_str()).collect();
+            let commit = manifest.git_commit.as_deref()
+                .map(|commit| format!(" at {}", &commit[..commit.len().min(8)]))
+                .unwrap_or_default();
+            self.output_manager.print_blue(&format!(
+                "{} ({}): {} - updated {}{}",
+                manifest.merge_request,
+                manifest.workspace,
+                services.join(", "),
+                manifest.updated_at.format("%Y-%m-%d %H:%M UTC"),
+                commit
+            ));
         }
 
         Ok(())
     }
 
+    /// The environment commands use when no --merge-request is given, and where its ID comes from
     pub fn current(&self) -> Result<()> {
-        let terraform_manager = TerraformManager::new(&self.working_directory);
-        let workspace_name = terraform_manager.workspace_show()?;
-
-        if workspace_name == "default" {
-            self.output_manager.print_yellow("No active development environment.");
-            return Ok(());
-        }
-
-        // Remove repository name prefix from workspace name
-        let repo_name = std::env::current_dir()?
-            .file_name()
-            .and_then(|name| name.to_str())
-            .ok_or_else(|| EnvieError::ValidationError("Could not determine repository name".to_string()))?
-            .to_string();
-        let clean_workspace = if workspace_name.starts_with(&format!("{}-", repo_name)) {
-            workspace_name.strip_prefix(&format!("{}-", repo_name)).unwrap_or(&workspace_name).to_string()
-        } else {
-            workspace_name
+        let environment_id = match EnvironmentId::resolve(None, &self.working_directory) {
+            Ok(environment_id) => environment_id,
+            Err(e) => {
+                self.output_manager.print_yellow(&format!("No active development environment. {}", e));
+                return Ok(());
+            }
         };
 
-        self.output_manager.print_green(&format!("Current development environment: {}.", clean_workspace));
+        let environment_config = EnvironmentConfig::load(&self.working_directory)?;
+        let project_name = environment_config.project_name(&self.working_directory)?;
+        let workspace = environment_config.ephemeral_workspace_name(&self.working_directory, &project_name, &environment_id.id)?;
+
+        self.output_manager.print_green(&format!("Current development environment: {} ({})", environment_id.id, workspace));
+        self.output_manager.print_blue(&format!("  ID from {}", environment_id.source));
+        match ManifestStore::new(&self.working_directory).load(&workspace)? {
+            Some(manifest) => {
+                let services: Vec<&str> = manifest.services.keys().map(|s| s.as_str()).collect();
+                self.output_manager.print_blue(&format!("  Deployed services: {}", services.join(", ")));
+            }
+            None => self.output_manager.print_gray("  Not deployed yet"),
+        }
 
         Ok(())
     }
 
-    fn validate_merge_request_id(&self, merge_request_id: &str) -> Result<()> {
-        let re = Regex::new(r"^[0-9]+(-[0-9A-Za-z]+)?$")?;
-        
-        if !re.is_match(merge_request_id) {
-            return Err(EnvieError::ValidationError(
-                "Invalid merge request ID. Please provide a valid merge request ID in the format {number}-({number})?".to_string()
-            ));
+    /// Workspace of the given environment ID, or of the one inferred from CI variables or the git branch
+    fn resolve_workspace_name(&self, merge_request_id: Option<&str>) -> Result<String> {
+        let environment_id = EnvironmentId::resolve(merge_request_id, &self.working_directory)?;
+        if environment_id.source != IdSource::Flag {
+            self.output_manager.print_gray(&format!("Using environment ID {}", environment_id.describe()));
         }
-
-        Ok(())
+        self.format_workspace_name(&environment_id.id)
     }
 
     fn format_workspace_name(&self, merge_request_id: &str) -> Result<String> {
-        let repo_name = std::env::current_dir()?
-            .file_name()
-            .and_then(|name| name.to_str())
-            .ok_or_else(|| EnvieError::ValidationError("Could not determine repository name".to_string()))?
-            .to_string();
-        Ok(format!("{}-{}", repo_name, merge_request_id))
+        let environment_config = EnvironmentConfig::load(&self.working_directory)?;
+        let project_name = environment_config.project_name(&self.working_directory)?;
+        environment_config.ephemeral_workspace_name(&self.working_directory, &project_name, merge_request_id)
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::common::test_support::write_file;
     use tempfile::TempDir;
 
     #[test]
@@ -190,36 +165,14 @@ mod tests {
         assert_eq!(env_cmd.working_directory, temp_dir.path());
     }
 
-    #[test]
-    fn test_merge_request_id_validation() {
-        let temp_dir = TempDir::new().unwrap();
-        let env_cmd = EnvCommand::new(temp_dir.path().to_path_buf());
-        
-        // Valid IDs
-        assert!(env_cmd.validate_merge_request_id("123").is_ok());
-        assert!(env_cmd.validate_merge_request_id("123-abc").is_ok());
-        assert!(env_cmd.validate_merge_request_id("123-456").is_ok());
-        
-        // Invalid IDs
-        assert!(env_cmd.validate_merge_request_id("abc").is_err());
-        assert!(env_cmd.validate_merge_request_id("123-").is_err());
-        assert!(env_cmd.validate_merge_request_id("-123").is_err());
-    }
-
     #[test]
     fn test_workspace_name_formatting() {
         let temp_dir = TempDir::new().unwrap();
+        write_file(temp_dir.path(), "workspace.envie", "version: \"1.0\"\nproject:\n  name: myapp\nservices: []\nenvironments:\n  ephemeral:\n    backend:\n      type: local\n");
         let env_cmd = EnvCommand::new(temp_dir.path().to_path_buf());
-        
-        // This test would require a git repository to work properly
-        // For now, we'll just test that the function doesn't panic
-        let result = env_cmd.format_workspace_name("123");
-        // The result depends on the git repository name, so we can't assert a specific value
-        match result {
-            Ok(name) => assert!(!name.is_empty()),
-            Err(_) => {
-                // Expected in test environment without git
-            }
-        }
+
+        assert_eq!(env_cmd.format_workspace_name("123").unwrap(), "myapp-123");
+        // Branch slugs are IDs as well
+        assert_eq!(env_cmd.resolve_workspace_name(Some("feature-login")).unwrap(), "myapp-feature-login");
     }
 }
@@ -1,3 +1,4 @@
+use crate::commands::output::OutputCommand;
 use crate::common::*;
 use std::path::PathBuf;
 use serde_json::Value;
@@ -7,6 +8,10 @@ pub struct GenerateOptions {
     pub env_file: PathBuf,
     pub output_file: Option<PathBuf>,
     pub use_envie_output: bool,
+    pub service_name: Option<String>,
+    pub merge_request: Option<String>,
+    /// Where envie was invoked; the service is inferred from it when none is named
+    pub current_directory: PathBuf,
 }
 
 pub struct GenerateCommand {
@@ -25,7 +30,7 @@ impl GenerateCommand {
     pub async fn execute(&self, options: GenerateOptions) -> Result<()> {
         // Get terraform outputs
         let terraform_output = if options.use_envie_output {
-            self.get_envie_output().await?
+            self.get_envie_output(&options).await?
         } else {
             self.get_terraform_output_from_file(options.output_file.as_ref().unwrap()).await?
         };
@@ -41,16 +46,27 @@ impl GenerateCommand {
         Ok(())
     }
 
-    async fn get_envie_output(&self) -> Result<Value> {
+    async fn get_envie_output(&self, options: &GenerateOptions) -> Result<Value> {
         self.output_manager.print_yellow("Calling `envie output`...");
         
-        // This would call the envie output command
-        // For now, we'll return a placeholder
-        Ok(serde_json::json!({
-            "example_key": {
-                "value": "example_value"
-            }
-        }))
+        let output_command = OutputCommand::new(self.working_directory.clone());
+        let (_service_name, combined_output) = output_command.collect(
+            options.merge_request.as_deref(),
+            options.service_name.as_deref(),
+            &options.current_directory,
+        ).await?;
+
+        // Re-wrap values in the `terraform output -json` shape the env file parser expects
+        let wrapped = combined_output
+            .as_object()
+            .map(|outputs| {
+                outputs
+                    .iter()
+                    .map(|(key, value)| (key.clone(), serde_json::json!({ "value": value })))
+                    .collect::<serde_json::Map<_, _>>()
+            })
+            .unwrap_or_default();
+        Ok(Value::Object(wrapped))
     }
 
     async fn get_terraform_output_from_file(&self, file_path: &PathBuf) -> Result<Value> {
@@ -162,7 +178,7 @@ impl GenerateCommand {
 
     async fn generate_env_file(&self, env_vars: &[String]) -> Result<()> {
         // Check if running in CI
-        if std::env::var("CI_PIPELINE_URL").is_ok() {
+        if running_in_ci() {
             self.output_manager.print_yellow("Running in CI, skipping .env clearing...");
         } else {
             self.output_manager.print_yellow("Clearing .env...");
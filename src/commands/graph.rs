@@ -0,0 +1,334 @@
+use crate::common::*;
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+
+#[derive(Debug, Clone)]
+pub struct GraphOptions {
+    /// Limit the graph to the modules a deployment of this service involves
+    pub service_name: Option<String>,
+    /// Inferred from CI variables or the git branch when not given
+    pub merge_request: Option<String>,
+    pub environment_overrides: HashMap<String, String>,
+    pub format: GraphFormat,
+}
+
+/// Output formats of `envie graph`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum GraphFormat {
+    Dot,
+    Mermaid,
+    Json,
+}
+
+/// The resolved module dependency graph, as printed by `envie graph --format json`
+#[derive(Debug, Clone, Serialize)]
+pub struct GraphReport {
+    pub workspace: String,
+    pub nodes: Vec<GraphNode>,
+    pub edges: Vec<GraphEdge>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct GraphNode {
+    /// `service/module` key
+    pub id: String,
+    pub service: String,
+    pub module: String,
+    /// Whether the module is used from a stable environment rather than deployed ephemerally
+    pub stable: bool,
+}
+
+/// A dependency of `from` on `to`, with the environment it resolves to after `-E` overrides
+#[derive(Debug, Clone, Serialize)]
+pub struct GraphEdge {
+    pub from: String,
+    pub to: String,
+    pub environment: String,
+    pub workspace: String,
+}
+
+impl GraphEdge {
+    /// e.g. `stable.sandbox (sandbox)`
+    pub fn label(&self) -> String {
+        format!("{} ({})", self.environment, self.workspace)
+    }
+}
+
+impl GraphReport {
+    /// Every module of the project, or with `service_name` the modules a deployment of that
+    /// service involves. Dependencies of modules that are not deployed are not followed, as in
+    /// `envie deploy`.
+    ///
+    /// Without a service, a module is stable when every dependency on it resolves to a stable
+    /// environment.
+    pub fn build(
+        context: &DeploymentContext,
+        service_name: Option<&str>,
+        environment_overrides: &HashMap<String, String>,
+    ) -> Result<Self> {
+        let resolver = &context.environment_resolver;
+
+        // Modules to draw, the ones whose dependencies are drawn, and the stable ones of a plan
+        let (keys, followed, planned_stable): (Vec<String>, HashSet<String>, Option<HashSet<String>>) = match service_name {
+            Some(service_name) => {
+                let plan = context.plan(service_name, environment_overrides)?;
+                (
+                    plan.modules.iter().map(|planned| planned.key.clone()).collect(),
+                    plan.to_deploy().map(|planned| planned.key.clone()).collect(),
+                    Some(plan.modules.iter()
+                        .filter(|planned| matches!(planned.resolved.environment_type, EnvironmentType::Stable(_)))
+                        .map(|planned| planned.key.clone())
+                        .collect()),
+                )
+            }
+            None => {
+                let keys = context.graph.nodes().to_vec();
+                let followed = keys.iter().cloned().collect();
+                (keys, followed, None)
+            }
+        };
+
+        let included: HashSet<&String> = keys.iter().collect();
+        let mut edges = Vec::new();
+        for key in keys.iter().filter(|key| followed.contains(*key)) {
+            for dependency in context.graph.dependencies(key) {
+                if !included.contains(&dependency.target) {
+                    continue;
+                }
+                let (dep_service, dep_module) = split_module_key(&dependency.target);
+                let environment = dependency_environment(environment_overrides, dep_service, dep_module, &dependency.environment);
+                let resolved = resolver.resolve_environment(environment)?;
+                edges.push((
+                    GraphEdge {
+                        from: key.clone(),
+                        to: dependency.target.clone(),
+                        environment: environment.to_string(),
+                        workspace: resolved.workspace.clone(),
+                    },
+                    matches!(resolved.environment_type, EnvironmentType::Stable(_)),
+                ));
+            }
+        }
+
+        let stable = planned_stable.unwrap_or_else(|| keys.iter()
+            .filter(|key| {
+                let mut incoming = edges.iter().filter(|(edge, _)| &edge.to == *key).peekable();
+                incoming.peek().is_some() && incoming.all(|(_, stable)| *stable)
+            })
+            .cloned()
+            .collect());
+
+        Ok(Self {
+            workspace: context.workspace.clone(),
+            nodes: keys.iter()
+                .map(|key| {
+                    let (service, module) = split_module_key(key);
+                    GraphNode {
+                        id: key.clone(),
+                        service: service.to_string(),
+                        module: module.to_string(),
+                        stable: stable.contains(key),
+                    }
+                })
+                .collect(),
+            edges: edges.into_iter().map(|(edge, _)| edge).collect(),
+        })
+    }
+
+    /// Graphviz source; stable modules are drawn dashed and grey
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from("digraph envie {\n  rankdir=LR;\n  node [shape=box];\n\n");
+        for node in &self.nodes {
+            if node.stable {
+                dot.push_str(&format!("  {} [style=\"filled,dashed\", fillcolor=\"lightgrey\"];\n", dot_quote(&node.id)));
+            } else {
+                dot.push_str(&format!("  {};\n", dot_quote(&node.id)));
+            }
+        }
+        if !self.edges.is_empty() {
+            dot.push('\n');
+        }
+        for edge in &self.edges {
+            dot.push_str(&format!(
+                "  {} -> {} [label={}];\n",
+                dot_quote(&edge.from),
+                dot_quote(&edge.to),
+                dot_quote(&edge.label())
+            ));
+        }
+        dot.push_str("}\n");
+        dot
+    }
+
+    /// Mermaid flowchart, e.g. for a Markdown page; stable modules use the `stable` class
+    pub fn to_mermaid(&self) -> String {
+        let mut mermaid = String::from("flowchart LR\n");
+        for node in &self.nodes {
+            mermaid.push_str(&format!(
+                "  {}[\"{}\"]{}\n",
+                mermaid_id(&node.id),
+                mermaid_text(&node.id),
+                if node.stable { ":::stable" } else { "" }
+            ));
+        }
+        for edge in &self.edges {
+            mermaid.push_str(&format!(
+                "  {} -->|\"{}\"| {}\n",
+                mermaid_id(&edge.from),
+                mermaid_text(&edge.label()),
+                mermaid_id(&edge.to)
+            ));
+        }
+        mermaid.push_str("  classDef stable fill:#eee,stroke:#999,stroke-dasharray: 5 5\n");
+        mermaid
+    }
+}
+
+fn dot_quote(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// Node IDs may only contain letters, digits and `_`
+fn mermaid_id(key: &str) -> String {
+    key.chars().map(|c| if c.is_ascii_alphanumeric() { c } else { '_' }).collect()
+}
+
+fn mermaid_text(value: &str) -> String {
+    value.replace('"', "#quot;")
+}
+
+pub struct GraphCommand {
+    working_directory: PathBuf,
+}
+
+impl GraphCommand {
+    pub fn new(working_directory: PathBuf) -> Self {
+        Self { working_directory }
+    }
+
+    /// Print the resolved module dependency graph
+    pub fn execute(&self, options: GraphOptions) -> Result<()> {
+        let environment_id = EnvironmentId::resolve(options.merge_request.as_deref(), &self.working_directory)?;
+        let context = DeploymentContext::load(&self.working_directory, &environment_id.id)?;
+        if let Some(service_name) = &options.service_name {
+            if !context.registry.services.contains_key(service_name) {
+                return Err(EnvieError::ValidationError(format!("Service '{}' not found", service_name)));
+            }
+        }
+
+        let report = GraphReport::build(&context, options.service_name.as_deref(), &options.environment_overrides)?;
+        match options.format {
+            GraphFormat::Dot => print!("{}", report.to_dot()),
+            GraphFormat::Mermaid => print!("{}", report.to_mermaid()),
+            GraphFormat::Json => println!("{}", serde_json::to_string_pretty(&report)?),
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::test_support::write_file;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_graph_formats() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        write_file(root, "workspace.envie", r#"
+version: "1.0"
+project:
+  name: myapp
+services:
+  - path: services/networking
+  - path: services/database
+  - path: services/api
+environments:
+  ephemeral:
+    backend:
+      type: local
+  stable:
+    sandbox:
+      workspace: sandbox
+      backend:
+        type: local
+"#);
+        write_file(root, "services/networking/.envie", "name: networking\nmodules:\n  - name: vpc\n");
+        write_file(root, "services/database/.envie", "name: database\nmodules:\n  - name: dynamodb\n    depends: [networking/vpc]\n");
+        write_file(root, "services/api/.envie", r#"
+name: api
+modules:
+  - name: lambda
+    depends:
+      - path: ../../database/modules/dynamodb
+        environment: stable.sandbox
+      - networking.vpc
+  - name: gateway
+    depends: [lambda]
+"#);
+        let context = DeploymentContext::load(root, "123").unwrap();
+
+        let service = GraphReport::build(&context, Some("api"), &HashMap::new()).unwrap();
+        assert_eq!(service.to_dot(), r#"digraph envie {
+  rankdir=LR;
+  node [shape=box];
+
+  "networking/vpc";
+  "database/dynamodb" [style="filled,dashed", fillcolor="lightgrey"];
+  "api/lambda";
+  "api/gateway";
+
+  "api/lambda" -> "database/dynamodb" [label="stable.sandbox (sandbox)"];
+  "api/lambda" -> "networking/vpc" [label="ephemeral (myapp-123)"];
+  "api/gateway" -> "api/lambda" [label="ephemeral (myapp-123)"];
+}
+"#);
+
+        let project = GraphReport::build(&context, None, &HashMap::new()).unwrap();
+        assert_eq!(project.to_mermaid(), r#"flowchart LR
+  api_lambda["api/lambda"]
+  api_gateway["api/gateway"]
+  database_dynamodb["database/dynamodb"]:::stable
+  networking_vpc["networking/vpc"]
+  api_lambda -->|"stable.sandbox (sandbox)"| database_dynamodb
+  api_lambda -->|"ephemeral (myapp-123)"| networking_vpc
+  api_gateway -->|"ephemeral (myapp-123)"| api_lambda
+  database_dynamodb -->|"ephemeral (myapp-123)"| networking_vpc
+  classDef stable fill:#eee,stroke:#999,stroke-dasharray: 5 5
+"#);
+
+        // -E overrides relabel the edges and the modules they point at
+        let overrides = HashMap::from([("database".to_string(), "ephemeral".to_string())]);
+        let overridden = serde_json::to_value(GraphReport::build(&context, Some("api"), &overrides).unwrap()).unwrap();
+        assert_eq!(overridden["nodes"][1], serde_json::json!({
+            "id": "database/dynamodb",
+            "service": "database",
+            "module": "dynamodb",
+            "stable": false,
+        }));
+        assert_eq!(overridden["edges"][1], serde_json::json!({
+            "from": "api/lambda",
+            "to": "database/dynamodb",
+            "environment": "ephemeral",
+            "workspace": "myapp-123",
+        }));
+    }
+
+    #[test]
+    fn test_graph_format_flag() {
+        use crate::cli::args::{Cli, Commands};
+        use clap::Parser;
+
+        let cli = Cli::try_parse_from(["envie", "graph"]).unwrap();
+        assert!(matches!(cli.command, Commands::Graph { format: GraphFormat::Dot, .. }));
+
+        let cli = Cli::try_parse_from(["envie", "graph", "--format", "mermaid"]).unwrap();
+        assert!(matches!(cli.command, Commands::Graph { format: GraphFormat::Mermaid, .. }));
+
+        let error = Cli::try_parse_from(["envie", "graph", "--format", "png"]).err().unwrap();
+        assert!(error.to_string().contains("[possible values: dot, mermaid, json]"));
+    }
+}
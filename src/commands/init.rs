@@ -1,9 +1,26 @@
 use crate::common::Result;
+use crate::common::hcl::{Block, HclFile};
+use crate::common::environment::{BackendConfig, EnvironmentsConfig, EphemeralConfig, StableEnvironmentConfig};
 use crate::common::service_config::{ProjectInfo, WorkspaceConfig, ServiceConfig, ModuleConfig, ServiceDiscovery};
 use std::collections::HashMap;
 use std::io::{self, Write};
 use std::path::Path;
 
+/// Files Envie and Terraform write that must not be committed
+const GITIGNORE_ENTRIES: &[&str] = &[
+    ".envie-remote-state.tf",
+    ".envie-backend.tf",
+    ".envie.auto.tfvars.json",
+    ".envie/state/",
+    ".envie/plans/",
+    ".envie/logs/",
+    ".envie/environments/",
+    ".terraform/",
+    ".terraform.lock.hcl",
+    "*.tfstate",
+    "*.tfstate.*",
+];
+
 #[derive(Debug, Clone)]
 pub struct InitOptions {
     pub name: Option<String>,
@@ -128,6 +145,7 @@ impl InitCommand {
         Ok(WorkspaceConfig {
             version: "1.0".to_string(),
             project: Some(project_info.clone()),
+            environments: Some(self.create_environments_config()),
             services: vec![
                 ServiceDiscovery {
                     name: Some("networking".to_string()),
@@ -143,9 +161,44 @@ impl InitCommand {
                 },
             ],
             defaults: HashMap::new(),
+            strict: false,
         })
     }
 
+    fn create_environments_config(&self) -> EnvironmentsConfig {
+        let s3_backend = |bucket: &str, key_pattern: &str| BackendConfig {
+            backend_type: "s3".to_string(),
+            config: HashMap::from([
+                ("bucket".to_string(), bucket.into()),
+                ("region".to_string(), "eu-west-1".into()),
+                ("key_pattern".to_string(), key_pattern.into()),
+            ]),
+        };
+
+        EnvironmentsConfig {
+            ephemeral: EphemeralConfig {
+                naming_pattern: "{project}-{id}".to_string(),
+                backend: s3_backend(
+                    "terraform-state-ephemeral",
+                    "ephemeral/{service}/{module}/terraform.tfstate",
+                ),
+                variables: HashMap::new(),
+            },
+            stable: HashMap::from([(
+                "sandbox".to_string(),
+                StableEnvironmentConfig {
+                    workspace: "sandbox".to_string(),
+                    backend: s3_backend(
+                        "terraform-state-stable",
+                        "stable/{environment}/{service}/{module}/terraform.tfstate",
+                    ),
+                    description: "Shared sandbox for testing".to_string(),
+                    variables: HashMap::new(),
+                },
+            )]),
+        }
+    }
+
     fn write_workspace_config(&self, config: &WorkspaceConfig) -> Result<()> {
         let workspace_envie = self.working_directory.join("workspace.envie");
         let content = serde_yaml::to_string(config)?;
@@ -188,12 +241,16 @@ impl InitCommand {
                     name: "vpc".to_string(),
                     description: "VPC configuration".to_string(),
                     path: "modules/vpc".to_string(),
+                    remote_states: Vec::new(),
+                    variables: HashMap::new(),
                     depends: vec![],
                 },
                 ModuleConfig {
                     name: "subnets".to_string(),
                     description: "Subnet configuration".to_string(),
                     path: "modules/subnets".to_string(),
+                    remote_states: Vec::new(),
+                    variables: HashMap::new(),
                     depends: vec![
                         crate::common::service_config::DependencyReference {
                             path: "./vpc".to_string(),
@@ -205,6 +262,8 @@ impl InitCommand {
                     name: "security-groups".to_string(),
                     description: "Security group configuration".to_string(),
                     path: "modules/security-groups".to_string(),
+                    remote_states: Vec::new(),
+                    variables: HashMap::new(),
                     depends: vec![
                         crate::common::service_config::DependencyReference {
                             path: "./vpc".to_string(),
@@ -214,6 +273,7 @@ impl InitCommand {
                 },
             ],
             depends: vec![],
+            variables: HashMap::new(),
         };
 
         let content = serde_yaml::to_string(&config)?;
@@ -240,6 +300,8 @@ impl InitCommand {
                     name: "dynamodb".to_string(),
                     description: "DynamoDB table configuration".to_string(),
                     path: "modules/dynamodb".to_string(),
+                    remote_states: Vec::new(),
+                    variables: HashMap::new(),
                     depends: vec![
                         crate::common::service_config::DependencyReference {
                             path: "../networking/modules/vpc".to_string(),
@@ -251,6 +313,8 @@ impl InitCommand {
                     name: "rds".to_string(),
                     description: "RDS database configuration".to_string(),
                     path: "modules/rds".to_string(),
+                    remote_states: Vec::new(),
+                    variables: HashMap::new(),
                     depends: vec![
                         crate::common::service_config::DependencyReference {
                             path: "../networking/modules/vpc".to_string(),
@@ -264,6 +328,7 @@ impl InitCommand {
                 },
             ],
             depends: vec!["../networking".to_string()],
+            variables: HashMap::new(),
         };
 
         let content = serde_yaml::to_string(&config)?;
@@ -291,6 +356,8 @@ impl InitCommand {
                     name: "lambda".to_string(),
                     description: "Lambda function for API handler".to_string(),
                     path: "modules/lambda".to_string(),
+                    remote_states: Vec::new(),
+                    variables: HashMap::new(),
                     depends: vec![
                         crate::common::service_config::DependencyReference {
                             path: "../../database/modules/dynamodb".to_string(),
@@ -306,6 +373,8 @@ impl InitCommand {
                     name: "step-functions".to_string(),
                     description: "Step Functions state machine".to_string(),
                     path: "modules/step-functions".to_string(),
+                    remote_states: Vec::new(),
+                    variables: HashMap::new(),
                     depends: vec![
                         crate::common::service_config::DependencyReference {
                             path: "./lambda".to_string(),
@@ -317,6 +386,8 @@ impl InitCommand {
                     name: "gateway".to_string(),
                     description: "API Gateway configuration".to_string(),
                     path: "modules/gateway".to_string(),
+                    remote_states: Vec::new(),
+                    variables: HashMap::new(),
                     depends: vec![
                         crate::common::service_config::DependencyReference {
                             path: "./step-functions".to_string(),
@@ -326,6 +397,7 @@ impl InitCommand {
                 },
             ],
             depends: vec!["../database".to_string(), "../networking".to_string()],
+            variables: HashMap::new(),
         };
 
         let content = serde_yaml::to_string(&config)?;
@@ -343,29 +415,25 @@ impl InitCommand {
             let module_dir = module_dir?;
             if module_dir.file_type()?.is_dir() {
                 let main_tf = module_dir.path().join("main.tf");
-                let content = format!(
-                    r#"# {module_name} Module
-# This is an example Terraform module for {module_name}
-
-resource "null_resource" "example" {{
-  provisioner "local-exec" {{
-    command = "echo 'Hello from {module_name} module'"
-  }}
-}}
-
-output "example_output" {{
-  value = "This is output from {module_name} module"
-  description = "Example output from {module_name} module"
-}}
-"#,
-                    module_name = module_dir.file_name().to_string_lossy()
-                );
+                let module_name = module_dir.file_name().to_string_lossy().to_string();
+                let content = HclFile::new()
+                    .comment(&format!("{} Module", module_name))
+                    .comment(&format!("This is an example Terraform module for {}", module_name))
+                    .block(Block::new("resource").label("null_resource").label("example")
+                        .block(Block::new("provisioner").label("local-exec")
+                            .attribute("command", format!("echo 'Hello from {} module'", module_name))))
+                    .block(Block::new("output").label("example_output")
+                        .attribute("value", format!("This is output from {} module", module_name))
+                        .attribute("description", format!("Example output from {} module", module_name)))
+                    .to_string();
                 std::fs::write(main_tf, content)?;
             }
         }
         Ok(())
     }
 
+    /// Append every Envie entry the .gitignore is missing, so projects initialized by an older
+    /// version pick up the files added since
     fn update_gitignore(&self) -> Result<()> {
         let gitignore_path = self.working_directory.join(".gitignore");
         let mut gitignore_content = if gitignore_path.exists() {
@@ -374,12 +442,27 @@ output "example_output" {{
             String::new()
         };
 
-        let envie_entries = "\n# Envie generated files\n.envie-remote-state.tf\n.envie-variables.tf\n.terraform/\n.terraform.lock.hcl\n*.tfstate\n*.tfstate.*\n";
+        let present = |entry: &str| gitignore_content.lines().any(|line| line.trim() == entry);
+        let missing: Vec<&str> = GITIGNORE_ENTRIES.iter()
+            .copied()
+            .filter(|entry| !present(entry))
+            .collect();
+        if missing.is_empty() {
+            return Ok(());
+        }
+        let has_header = present("# Envie generated files");
 
-        if !gitignore_content.contains(".envie-remote-state.tf") {
-            gitignore_content.push_str(envie_entries);
-            std::fs::write(gitignore_path, gitignore_content)?;
+        if !gitignore_content.is_empty() && !gitignore_content.ends_with('\n') {
+            gitignore_content.push('\n');
+        }
+        if !has_header {
+            gitignore_content.push_str("\n# Envie generated files\n");
         }
+        for entry in missing {
+            gitignore_content.push_str(entry);
+            gitignore_content.push('\n');
+        }
+        std::fs::write(gitignore_path, gitignore_content)?;
 
         Ok(())
     }
@@ -456,4 +539,28 @@ For more information about Envie, see the [documentation](https://github.com/you
         std::fs::write(self.working_directory.join("README.md"), readme_content)?;
         Ok(())
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_update_gitignore_appends_missing_entries() {
+        let temp_dir = TempDir::new().unwrap();
+        let gitignore = temp_dir.path().join(".gitignore");
+        std::fs::write(&gitignore, "node_modules/\n\n# Envie generated files\n.envie-remote-state.tf\n.terraform/\n").unwrap();
+
+        let init = InitCommand::new(temp_dir.path().to_path_buf());
+        init.update_gitignore().unwrap();
+        init.update_gitignore().unwrap();
+
+        let content = std::fs::read_to_string(&gitignore).unwrap();
+        for entry in GITIGNORE_ENTRIES {
+            assert_eq!(content.lines().filter(|line| line == entry).count(), 1, "{}", entry);
+        }
+        assert_eq!(content.matches("# Envie generated files").count(), 1);
+        assert!(content.starts_with("node_modules/\n"));
+    }
+}
@@ -1,19 +1,33 @@
+pub mod apply;
 pub mod deploy;
+pub mod dependents;
 pub mod destroy;
 pub mod env;
 pub mod generate;
+pub mod graph;
 pub mod init;
 pub mod list;
 pub mod output;
+pub mod plan;
 pub mod clean;
 pub mod show;
+pub mod status;
+pub mod validate;
+pub mod schema;
 
+pub use apply::*;
 pub use deploy::*;
+pub use dependents::*;
 pub use destroy::*;
 pub use env::*;
 pub use generate::*;
+pub use graph::*;
 pub use init::*;
 pub use list::*;
 pub use output::*;
+pub use plan::*;
 pub use clean::*;
 pub use show::*;
+pub use status::*;
+pub use validate::*;
+pub use schema::*;
@@ -1,10 +1,14 @@
 use crate::common::*;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 #[derive(Debug, Clone)]
 pub struct OutputOptions {
+    pub service_name: Option<String>,
+    pub merge_request: Option<String>,
     pub output_file: Option<String>,
     pub verbose: bool,
+    /// Where envie was invoked; the service is inferred from it when none is named
+    pub current_directory: PathBuf,
 }
 
 pub struct OutputCommand {
@@ -21,28 +25,11 @@ impl OutputCommand {
     }
 
     pub async fn execute(&self, options: OutputOptions) -> Result<()> {
-        let envie_dir = self.working_directory.join(".envie");
-        let terraform_manager = TerraformManager::new(&envie_dir);
-
-        // Get current workspace
-        let workspace = terraform_manager.workspace_show()?;
-        terraform_manager.workspace_select(&workspace)?;
-
-        // Get service name and dependencies
-        let service_name = terraform_manager.output_value("service")?
-            .as_str()
-            .ok_or_else(|| EnvieError::TerraformError("Service name not found in terraform state".to_string()))?
-            .to_string();
-
-        let dependencies: Vec<String> = terraform_manager.output_value("dependencies")?
-            .as_array()
-            .ok_or_else(|| EnvieError::TerraformError("Dependencies not found in terraform state".to_string()))?
-            .iter()
-            .filter_map(|v| v.as_str().map(|s| s.to_string()))
-            .collect();
-
-        // Get combined outputs
-        let combined_output = self.get_combined_output(&dependencies).await?;
+        let (service_name, combined_output) = self.collect(
+            options.merge_request.as_deref(),
+            options.service_name.as_deref(),
+            &options.current_directory,
+        ).await?;
 
         // Print or save output
         if let Some(output_file) = options.output_file {
@@ -58,70 +45,63 @@ impl OutputCommand {
         Ok(())
     }
 
-    async fn get_combined_output(&self, dependencies: &[String]) -> Result<serde_json::Value> {
+    /// Collect the combined outputs of a deployed service from the modules recorded in its
+    /// environment manifest, returning the service name alongside them. Without a service name,
+    /// the one containing `current_directory` is used.
+    pub async fn collect(&self, merge_request: Option<&str>, service_name: Option<&str>, current_directory: &Path) -> Result<(String, serde_json::Value)> {
+        let store = ManifestStore::new(&self.working_directory);
+        let merge_request = match merge_request {
+            Some(merge_request) => merge_request.to_string(),
+            None => store.only()?.merge_request,
+        };
+
+        let context = DeploymentContext::load(&self.working_directory, &merge_request)?.with_quiet(true);
+        let manifest = store.load(&context.workspace)?.ok_or_else(|| EnvieError::ValidationError(format!(
+            "Nothing has been deployed to {}", context.workspace
+        )))?;
+
+        let service_name = match service_name {
+            Some(service_name) => service_name.to_string(),
+            None if manifest.services.len() == 1 => manifest.services.keys().next().cloned().unwrap_or_default(),
+            None => context.select_service(None, current_directory)?.config.name.clone(),
+        };
+        let deployment = manifest.services.get(&service_name).ok_or_else(|| EnvieError::ValidationError(format!(
+            "Service '{}' is not deployed to {} (deployed: {})",
+            service_name,
+            context.workspace,
+            manifest.services.keys().cloned().collect::<Vec<_>>().join(", ")
+        )))?;
+
+        // Dependencies first, so the service's own outputs win on conflicting names
         let mut combined_outputs = serde_json::Map::new();
-
-        // Separate dev and non-dev components
-        let (dev_components, non_dev_components): (Vec<_>, Vec<_>) = dependencies
-            .iter()
-            .partition(|dep| dep.ends_with(":dev"));
-
-        // Process non-dev components (stable deployments)
-        let mut unique_service_envs = std::collections::HashSet::new();
-        for comp in &non_dev_components {
-            let parts: Vec<&str> = comp.split(':').collect();
-            if parts.len() == 2 {
-                let comp_name = parts[0];
-                let comp_env = parts[1];
-                let service_name = comp_name.split('/').next().unwrap();
-                unique_service_envs.insert((service_name, comp_env));
-            }
-        }
-
-        // Get outputs for stable deployments
-        for (service, env) in unique_service_envs {
-            let service_dir = self.working_directory.join("services").join(service).join("stable_deployments");
-            if service_dir.exists() {
-                let output = self.get_terraform_output(&service_dir, env).await?;
-                self.merge_outputs(&mut combined_outputs, output);
+        for recorded in &deployment.modules {
+            if !matches!(recorded.status, ModuleStatus::Deployed | ModuleStatus::External) {
+                continue;
             }
+            // Read the state of the environment the module was deployed from
+            let planned = context.planned_from_manifest(recorded)?;
+            let module = context.write_backend_file(&planned)?;
+            let workspace = planned.resolved.backend.kind()?.supports_workspaces()
+                .then_some(recorded.workspace.as_str());
+            let terraform_manager = context.terraform(&recorded.module, &module.path, false);
+            let output = self.get_terraform_output(&terraform_manager, workspace).await?;
+            self.merge_outputs(&mut combined_outputs, output);
         }
 
-        // Get outputs for dev components (temp deployments)
-        for comp in &dev_components {
-            let parts: Vec<&str> = comp.split(':').collect();
-            if parts.len() == 2 {
-                let comp_name = parts[0];
-                let comp_env = parts[1];
-                let component_dir = self.working_directory.join("services").join(comp_name).join("temp_deployments");
-                if component_dir.exists() {
-                    let output = self.get_terraform_output(&component_dir, comp_env).await?;
-                    self.merge_outputs(&mut combined_outputs, output);
-                }
-            }
-        }
-
-        Ok(serde_json::Value::Object(combined_outputs))
+        Ok((service_name, serde_json::Value::Object(combined_outputs)))
     }
 
-    async fn get_terraform_output(&self, dir: &std::path::Path, env: &str) -> Result<serde_json::Value> {
-        let terraform_manager = TerraformManager::new(dir);
-
-        // Initialize terraform if not dev environment
-        if env != "dev" {
-            let backend_config = dir.join("backend").join(format!("{}.conf", env));
-            if backend_config.exists() {
-                // This would run terraform init with backend config
-                // For now, we'll skip this step
-            }
-        }
-
-        // Check if terraform is initialized
-        let terraform_dir = dir.join(".terraform");
-        if !terraform_dir.exists() {
-            return Err(EnvieError::TerraformError(
-                format!("Terraform not initialized in {}", dir.display())
-            ));
+    async fn get_terraform_output(
+        &self,
+        terraform_manager: &TerraformManager,
+        workspace: Option<&str>,
+    ) -> Result<serde_json::Value> {
+        // The module may last have been initialized against another environment's backend
+        terraform_manager.init_reconfigure()?;
+
+        // Read outputs from the workspace the environment resolved to
+        if let Some(workspace) = workspace {
+            terraform_manager.workspace_select(workspace)?;
         }
 
         // Get terraform outputs
@@ -0,0 +1,247 @@
+use crate::common::*;
+use chrono::Utc;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+#[derive(Debug, Clone)]
+pub struct PlanOptions {
+    pub service_name: Option<String>,
+    /// Inferred from CI variables or the git branch when not given
+    pub merge_request: Option<String>,
+    pub environment_overrides: HashMap<String, String>,
+    pub json: bool,
+    pub log: bool,
+    pub execution: ExecutionOptions,
+    pub verbose: bool,
+    /// Where envie was invoked; the service is inferred from it when none is named
+    pub current_directory: PathBuf,
+}
+
+/// Machine-readable result of `envie plan --json`
+#[derive(Debug, Clone, Serialize)]
+pub struct PlanReport {
+    pub workspace: String,
+    pub service: String,
+    pub modules: Vec<ModulePlanReport>,
+    pub skipped: Vec<SkippedModuleReport>,
+    pub total: PlanSummary,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ModulePlanReport {
+    pub module: String,
+    pub plan_file: PathBuf,
+    #[serde(flatten)]
+    pub summary: PlanSummary,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SkippedModuleReport {
+    pub module: String,
+    pub environment: String,
+    pub workspace: String,
+}
+
+pub struct PlanCommand {
+    working_directory: PathBuf,
+    output_manager: OutputManager,
+}
+
+impl PlanCommand {
+    pub fn new(working_directory: PathBuf) -> Self {
+        Self {
+            working_directory,
+            output_manager: OutputManager::new(),
+        }
+    }
+
+    pub async fn execute(&self, options: PlanOptions) -> Result<()> {
+        let environment_id = EnvironmentId::resolve(options.merge_request.as_deref(), &self.working_directory)?;
+        if environment_id.source != IdSource::Flag && !options.json {
+            self.output_manager.print_gray(&format!("Using environment ID {}", environment_id.describe()));
+        }
+        let context = DeploymentContext::load(&self.working_directory, &environment_id.id)?
+            .with_logs(options.log)
+            .with_quiet(options.json);
+        if let (Some(logs), false) = (&context.logs, options.json) {
+            self.output_manager.print_gray(&format!("Writing Terraform logs to {}", logs.dir().display()));
+        }
+        let service = context.select_service(options.service_name.as_deref(), &options.current_directory)?;
+        let plan = context.plan(&service.config.name, &options.environment_overrides)?;
+        let store = PlanStore::new(&context.root);
+
+        let mut report = PlanReport {
+            workspace: context.workspace.clone(),
+            service: service.config.name.clone(),
+            modules: Vec::new(),
+            skipped: plan.skipped()
+                .map(|planned| SkippedModuleReport {
+                    module: planned.key.clone(),
+                    environment: planned.environment.clone(),
+                    workspace: planned.resolved.workspace.clone(),
+                })
+                .collect(),
+            total: PlanSummary::default(),
+        };
+
+        // Plans from an earlier run of this workspace must not be applied alongside the new ones
+        store.clear(&context.workspace)?;
+        let mut saved = SavedPlanSet {
+            workspace: context.workspace.clone(),
+            service: service.config.name.clone(),
+            environment_overrides: options.environment_overrides.clone(),
+            created_at: Utc::now(),
+            modules: Vec::new(),
+        };
+
+        let to_plan: HashMap<&str, &PlannedModule> = plan.to_deploy()
+            .map(|planned| (planned.key.as_str(), planned))
+            .collect();
+        let executor = ModuleExecutor::new(
+            plan.to_deploy().map(|planned| planned.key.clone()).collect(),
+            &context.graph,
+        );
+        let execution = executor.run(options.execution, |key| {
+            self.plan_module(&context, &store, to_plan[key], &options)
+        });
+
+        if execution.failed().next().is_some() {
+            if !options.json {
+                execution.print(&self.output_manager);
+            }
+            // A partial set of plans must never be applied
+            store.clear(&context.workspace)?;
+            return execution.into_result().map(|_| ());
+        }
+
+        for (_, (module_report, saved_plan)) in execution.succeeded() {
+            report.total += module_report.summary;
+            report.modules.push(module_report.clone());
+            saved.modules.push(saved_plan.clone());
+        }
+
+        store.save(&saved)?;
+
+        if options.json {
+            println!("{}", serde_json::to_string_pretty(&report)?);
+        } else {
+            self.print_report(&report);
+        }
+
+        Ok(())
+    }
+
+    fn plan_module(
+        &self,
+        context: &DeploymentContext,
+        store: &PlanStore,
+        planned: &PlannedModule,
+        options: &PlanOptions,
+    ) -> Result<(ModulePlanReport, SavedPlan)> {
+        if !options.json {
+            self.output_manager.print_green(&format!("  Planning module: {}", planned.key));
+        }
+
+        let module = context.module(&planned.key)?;
+        let plan_file = store.plan_path(&context.workspace, &planned.service, &module.config.name);
+        if let Some(parent) = plan_file.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        let terraform_manager = context.prepare_module(planned, &options.environment_overrides, options.verbose)?;
+        terraform_manager.plan(&plan_file)?;
+        let summary = PlanSummary::from_plan(&terraform_manager.show_plan_json(&plan_file)?);
+
+        let saved_plan = SavedPlan {
+            module: planned.key.clone(),
+            module_path: module.path.clone(),
+            plan_file: plan_file.clone(),
+            fingerprint: module_fingerprint(&module.path)?,
+        };
+        let module_report = ModulePlanReport {
+            module: planned.key.clone(),
+            plan_file,
+            summary,
+        };
+
+        Ok((module_report, saved_plan))
+    }
+
+    fn print_report(&self, report: &PlanReport) {
+        self.output_manager.print_green(&format!(
+            "Plan for service {} in {}:", report.service, report.workspace
+        ));
+
+        let width = report.modules.iter()
+            .map(|module| module.module.len())
+            .chain(std::iter::once("MODULE".len()))
+            .max()
+            .unwrap_or(0);
+
+        println!("  {:<width$}  {:>6}  {:>6}  {:>7}", "MODULE", "ADD", "CHANGE", "DESTROY", width = width);
+        for module in &report.modules {
+            let line = format!(
+                "  {:<width$}  {:>6}  {:>6}  {:>7}",
+                module.module, module.summary.add, module.summary.change, module.summary.destroy,
+                width = width
+            );
+            if module.summary.has_changes() {
+                self.output_manager.print_yellow(&line);
+            } else {
+                println!("{}", line);
+            }
+        }
+        println!(
+            "  {:<width$}  {:>6}  {:>6}  {:>7}",
+            "Total", report.total.add, report.total.change, report.total.destroy,
+            width = width
+        );
+
+        for skipped in &report.skipped {
+            self.output_manager.print_blue(&format!(
+                "  Skipped {}: resolves to {} (workspace {})",
+                skipped.module, skipped.environment, skipped.workspace
+            ));
+        }
+
+        if let Some(first) = report.modules.first() {
+            if let Some(plans_dir) = first.plan_file.parent().and_then(|dir| dir.parent()) {
+                self.output_manager.print_gray(&format!("Plans saved to {}", plans_dir.display()));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_plan_command_creation() {
+        let temp_dir = TempDir::new().unwrap();
+        let command = PlanCommand::new(temp_dir.path().to_path_buf());
+        assert_eq!(command.working_directory, temp_dir.path());
+    }
+
+    #[test]
+    fn test_plan_report_json() {
+        let report = PlanReport {
+            workspace: "myapp-123".to_string(),
+            service: "api".to_string(),
+            modules: vec![ModulePlanReport {
+                module: "api/lambda".to_string(),
+                plan_file: PathBuf::from(".envie/plans/myapp-123/api/lambda.tfplan"),
+                summary: PlanSummary { add: 3, change: 1, destroy: 0 },
+            }],
+            skipped: vec![],
+            total: PlanSummary { add: 3, change: 1, destroy: 0 },
+        };
+
+        let json = serde_json::to_value(&report).unwrap();
+        assert_eq!(json["modules"][0]["module"], "api/lambda");
+        assert_eq!(json["modules"][0]["add"], 3);
+        assert_eq!(json["total"]["change"], 1);
+    }
+}
@@ -0,0 +1,41 @@
+use crate::common::*;
+
+#[derive(Debug, Clone)]
+pub struct SchemaOptions {
+    pub kind: ConfigKind,
+}
+
+#[derive(Debug, Default)]
+pub struct SchemaCommand;
+
+impl SchemaCommand {
+    pub fn new() -> Self {
+        Self
+    }
+
+    /// Print the JSON Schema of a config file to stdout
+    pub fn execute(&self, options: SchemaOptions) -> Result<()> {
+        let schema = serde_json::to_string_pretty(&options.kind.schema())?;
+        println!("{}", schema);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::args::{Cli, Commands};
+    use clap::Parser;
+
+    #[test]
+    fn test_kind_argument() {
+        let cli = Cli::try_parse_from(["envie", "schema"]).unwrap();
+        assert!(matches!(cli.command, Commands::Schema { kind: ConfigKind::Workspace }));
+
+        let cli = Cli::try_parse_from(["envie", "schema", "module"]).unwrap();
+        assert!(matches!(cli.command, Commands::Schema { kind: ConfigKind::Module }));
+
+        let error = Cli::try_parse_from(["envie", "schema", "environment"]).err().unwrap();
+        assert!(error.to_string().contains("[possible values: workspace, service, module]"));
+    }
+}
@@ -7,7 +7,15 @@ pub struct ShowOptions {
     pub service: Option<String>,
     pub modules: bool,
     pub dependencies: bool,
+    /// Module to show, as `service/module` or a module name
+    pub module: Option<String>,
+    /// Show the effective Terraform variables of the module and where each was set
+    pub vars: bool,
+    /// Environment the variables are resolved for (ephemeral, stable.sandbox, ...)
+    pub environment: String,
     pub verbose: bool,
+    /// Where envie was invoked; the module is inferred from it when none is named
+    pub current_directory: PathBuf,
 }
 
 pub struct ShowCommand {
@@ -31,7 +39,9 @@ impl ShowCommand {
         // Load workspace configuration
         let workspace_config = self.load_workspace_config()?;
         
-        if let Some(service_name) = &options.service {
+        if options.module.is_some() || options.vars {
+            self.show_module(&options)?;
+        } else if let Some(service_name) = &options.service {
             // Show specific service
             self.show_service(service_name, &options)?;
         } else {
@@ -122,6 +132,79 @@ impl ShowCommand {
         Ok(())
     }
 
+    fn show_module(&self, options: &ShowOptions) -> Result<()> {
+        let registry = ServiceRegistry::discover_from_path(&self.working_directory)?;
+        let (key, module) = match &options.module {
+            Some(module) => registry.find_module(module, options.service.as_deref())?,
+            None => registry.find_module_containing(&options.current_directory).ok_or_else(|| EnvieError::ValidationError(
+                "No module found in current directory. Specify one with --module or run from a module directory.".to_string()
+            ))?,
+        };
+
+        self.output_manager.print_green(&format!("🔧 Module: {}", key));
+        println!("  Description: {}", module.config.description);
+        println!("  Path: {}", module.path.strip_prefix(&self.working_directory).unwrap_or(&module.path).display());
+        if !module.config.depends.is_empty() {
+            println!("  Dependencies:");
+            for dep in &module.config.depends {
+                println!("    - {} ({})", dep.path, dep.environment);
+            }
+        }
+        println!();
+
+        if options.vars {
+            let environment_config = EnvironmentConfig::load(&self.working_directory)?;
+            let environment = Self::environment_type(&environment_config, &options.environment)?;
+            let service = registry.services.get(split_module_key(key).0).ok_or_else(|| EnvieError::ValidationError(
+                format!("Service '{}' not found", split_module_key(key).0)
+            ))?;
+            let variables = ModuleVariables::resolve(&environment_config, &environment, service, module);
+            self.show_module_variables(&variables, &options.environment);
+        }
+
+        Ok(())
+    }
+
+    fn show_module_variables(&self, variables: &ModuleVariables, environment: &str) {
+        self.output_manager.print_blue(&format!("  Variables ({}):", environment));
+        if variables.is_empty() {
+            println!("    (none)");
+            return;
+        }
+
+        let values: Vec<(&String, String, &VariableSource)> = variables.variables.iter()
+            .map(|(name, variable)| (name, variable.value.to_string(), &variable.source))
+            .collect();
+        let name_width = values.iter().map(|(name, _, _)| name.len()).max().unwrap_or(0);
+        let value_width = values.iter().map(|(_, value, _)| value.len()).max().unwrap_or(0);
+        for (name, value, source) in values {
+            println!("    {:<name_width$} = {:<value_width$}  # {}", name, value, source);
+        }
+    }
+
+    /// `ephemeral`, `ephemeral.<id>`, `stable.<name>` or the name of a stable environment
+    fn environment_type(environment_config: &EnvironmentConfig, environment: &str) -> Result<EnvironmentType> {
+        if environment == "ephemeral" || environment.starts_with("ephemeral.") {
+            return Ok(EnvironmentType::Ephemeral);
+        }
+
+        let name = environment.strip_prefix("stable.").unwrap_or(environment);
+        if environment_config.stable.contains_key(name) {
+            return Ok(EnvironmentType::Stable(name.to_string()));
+        }
+
+        let mut available: Vec<String> = environment_config.stable.keys()
+            .map(|name| format!("stable.{}", name))
+            .collect();
+        available.sort();
+        available.insert(0, "ephemeral".to_string());
+        Err(EnvieError::EnvironmentError(format!(
+            "Unknown environment '{}'. Available: {}",
+            environment,
+            available.join(", ")
+        )))
+    }
+
     fn load_service_config(&self, service_path: &str) -> Result<ServiceConfig> {
         let service_dir = self.working_directory.join(service_path);
         let envie_file = service_dir.join(".envie");
@@ -183,7 +266,11 @@ mod tests {
             service: Some("test-service".to_string()),
             modules: true,
             dependencies: false,
+            module: None,
+            vars: false,
+            environment: "ephemeral".to_string(),
             verbose: true,
+            current_directory: PathBuf::from("."),
         };
         
         assert_eq!(options.service, Some("test-service".to_string()));
@@ -191,4 +278,29 @@ mod tests {
         assert!(!options.dependencies);
         assert!(options.verbose);
     }
+
+    #[test]
+    fn test_show_module_variables() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        std::fs::write(root.join("workspace.envie"), "version: \"1.0\"\nservices:\n  - path: services/api\nenvironments:\n  ephemeral:\n    backend:\n      type: local\n").unwrap();
+        std::fs::create_dir_all(root.join("services/api/modules/lambda")).unwrap();
+        std::fs::write(root.join("services/api/.envie"), "name: api\nvariables:\n  memory: 256\nmodules:\n  - name: lambda\n").unwrap();
+
+        let show_cmd = ShowCommand::new(root.to_path_buf());
+        let options = ShowOptions {
+            service: None,
+            modules: false,
+            dependencies: false,
+            module: None,
+            vars: true,
+            environment: "ephemeral".to_string(),
+            verbose: false,
+            current_directory: root.join("services/api/modules/lambda"),
+        };
+        assert!(show_cmd.execute(options.clone()).is_ok());
+
+        let unknown = show_cmd.execute(ShowOptions { environment: "stable.prod".to_string(), ..options }).unwrap_err();
+        assert!(unknown.to_string().contains("Unknown environment 'stable.prod'. Available: ephemeral"));
+    }
 }
@@ -0,0 +1,98 @@
+use crate::common::*;
+use std::path::PathBuf;
+
+#[derive(Debug, Clone)]
+pub struct StatusOptions {
+    pub merge_request: Option<String>,
+    pub json: bool,
+}
+
+pub struct StatusCommand {
+    working_directory: PathBuf,
+    output_manager: OutputManager,
+}
+
+impl StatusCommand {
+    pub fn new(working_directory: PathBuf) -> Self {
+        Self {
+            working_directory,
+            output_manager: OutputManager::new(),
+        }
+    }
+
+    pub async fn execute(&self, options: StatusOptions) -> Result<()> {
+        let store = ManifestStore::new(&self.working_directory);
+        let manifest = match &options.merge_request {
+            Some(merge_request) => {
+                let environment_config = EnvironmentConfig::load(&self.working_directory)?;
+                let project_name = environment_config.project_name(&self.working_directory)?;
+                let workspace = environment_config.ephemeral_workspace_name(&self.working_directory, &project_name, merge_request)?;
+                store.load(&workspace)?.ok_or_else(|| EnvieError::ValidationError(format!(
+                    "Nothing has been deployed to {}", workspace
+                )))?
+            }
+            None => store.only()?,
+        };
+
+        if options.json {
+            println!("{}", serde_json::to_string_pretty(&manifest)?);
+        } else {
+            self.print_manifest(&manifest);
+        }
+
+        Ok(())
+    }
+
+    fn print_manifest(&self, manifest: &EnvironmentManifest) {
+        self.output_manager.print_green(&format!(
+            "Environment {} (merge request {})", manifest.workspace, manifest.merge_request
+        ));
+        println!("  Created:  {}", manifest.created_at.format("%Y-%m-%d %H:%M:%S UTC"));
+        println!("  Updated:  {}", manifest.updated_at.format("%Y-%m-%d %H:%M:%S UTC"));
+        println!("  Commit:   {}", manifest.git_commit.as_deref().unwrap_or("unknown"));
+        println!("  Envie:    {}", manifest.envie_version);
+
+        for (service, deployment) in &manifest.services {
+            self.output_manager.print_green(&format!(
+                "Service {} (deployed {})", service, deployment.deployed_at.format("%Y-%m-%d %H:%M:%S UTC")
+            ));
+            for (dependency, environment) in &deployment.environment_overrides {
+                self.output_manager.print_gray(&format!("  -E {}:{}", dependency, environment));
+            }
+            for module in &deployment.modules {
+                let line = format!(
+                    "  {:<12} {} ({}, workspace {})",
+                    status_label(module.status), module.module, module.environment, module.workspace
+                );
+                match module.status {
+                    ModuleStatus::Deployed => self.output_manager.print_green(&line),
+                    ModuleStatus::Failed => self.output_manager.print_red(&line),
+                    ModuleStatus::NotDeployed => self.output_manager.print_yellow(&line),
+                    ModuleStatus::External => self.output_manager.print_blue(&line),
+                }
+            }
+        }
+    }
+}
+
+fn status_label(status: ModuleStatus) -> &'static str {
+    match status {
+        ModuleStatus::Deployed => "deployed",
+        ModuleStatus::Failed => "failed",
+        ModuleStatus::NotDeployed => "not deployed",
+        ModuleStatus::External => "external",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_status_command_creation() {
+        let temp_dir = TempDir::new().unwrap();
+        let command = StatusCommand::new(temp_dir.path().to_path_buf());
+        assert_eq!(command.working_directory, temp_dir.path());
+    }
+}
@@ -0,0 +1,50 @@
+use crate::common::*;
+use std::path::PathBuf;
+
+pub struct ValidateCommand {
+    working_directory: PathBuf,
+    output_manager: OutputManager,
+}
+
+impl ValidateCommand {
+    pub fn new(working_directory: PathBuf) -> Self {
+        Self {
+            working_directory,
+            output_manager: OutputManager::new(),
+        }
+    }
+
+    /// Print every problem as `file:line:column: message`; fails when there is any, so CI
+    /// jobs stop on a broken config
+    pub fn execute(&self) -> Result<()> {
+        let diagnostics = ConfigValidator::new(&self.working_directory).validate()?;
+
+        if diagnostics.is_empty() {
+            self.output_manager.print_green("✓ Configuration is valid");
+            return Ok(());
+        }
+
+        for diagnostic in &diagnostics {
+            self.output_manager.print_red(&diagnostic.to_string());
+        }
+
+        Err(EnvieError::ValidationError(format!(
+            "{} problem{} found in the configuration",
+            diagnostics.len(),
+            if diagnostics.len() == 1 { "" } else { "s" }
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_validate_command_creation() {
+        let temp_dir = TempDir::new().unwrap();
+        let validator = ValidateCommand::new(temp_dir.path().to_path_buf());
+        assert_eq!(validator.working_directory, temp_dir.path());
+    }
+}
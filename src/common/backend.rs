@@ -0,0 +1,253 @@
+use crate::common::*;
+use std::path::Path;
+
+/// Default base directory of local backend state, relative to the project root
+pub const LOCAL_STATE_DIR: &str = ".envie/state";
+
+/// Backend settings that only Envie reads; they are never passed to Terraform
+const ENVIE_SETTINGS: &[&str] = &["key_pattern"];
+
+/// Terraform backend types Envie can generate state configuration for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BackendKind {
+    S3,
+    Gcs,
+    AzureRm,
+    Local,
+    Http,
+    Pg,
+    Consul,
+}
+
+impl BackendKind {
+    pub const ALL: [BackendKind; 7] = [
+        BackendKind::S3,
+        BackendKind::Gcs,
+        BackendKind::AzureRm,
+        BackendKind::Local,
+        BackendKind::Http,
+        BackendKind::Pg,
+        BackendKind::Consul,
+    ];
+
+    pub fn parse(backend_type: &str) -> Result<Self> {
+        Self::ALL.iter()
+            .copied()
+            .find(|kind| kind.name() == backend_type)
+            .ok_or_else(|| EnvieError::ConfigError(format!(
+                "Unsupported backend type '{}'. Supported types: {}",
+                backend_type,
+                Self::ALL.iter().map(|kind| kind.name()).collect::<Vec<_>>().join(", ")
+            )))
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            BackendKind::S3 => "s3",
+            BackendKind::Gcs => "gcs",
+            BackendKind::AzureRm => "azurerm",
+            BackendKind::Local => "local",
+            BackendKind::Http => "http",
+            BackendKind::Pg => "pg",
+            BackendKind::Consul => "consul",
+        }
+    }
+
+    /// Config field that carries the location of one module's state. Envie always sets it.
+    ///
+    /// The local backend ignores `path` outside the default workspace, so state is placed
+    /// through `workspace_dir` instead.
+    pub fn state_field(&self) -> &'static str {
+        match self {
+            BackendKind::S3 | BackendKind::AzureRm => "key",
+            BackendKind::Gcs => "prefix",
+            BackendKind::Local => "workspace_dir",
+            BackendKind::Http => "address",
+            BackendKind::Pg => "schema_name",
+            BackendKind::Consul => "path",
+        }
+    }
+
+    /// Fields the backend cannot work without
+    pub fn required_fields(&self) -> &'static [&'static str] {
+        match self {
+            BackendKind::S3 => &["bucket", "region"],
+            BackendKind::Gcs => &["bucket"],
+            BackendKind::AzureRm => &["storage_account_name", "container_name"],
+            BackendKind::Local => &[],
+            // The configured address is the base URL every state lives under
+            BackendKind::Http => &["address"],
+            BackendKind::Pg => &["conn_str"],
+            BackendKind::Consul => &[],
+        }
+    }
+
+    /// Whether Terraform workspaces can be used with this backend
+    pub fn supports_workspaces(&self) -> bool {
+        !matches!(self, BackendKind::Http)
+    }
+
+    /// Value of the state field for a state key like `ephemeral/myapp-123/api/lambda/terraform.tfstate`.
+    /// `configured` is the value of the state field in the backend config, if any.
+    pub fn state_location(&self, state_key: &str, configured: Option<&str>, project_root: &Path) -> String {
+        // Backends that take a directory-like location store each workspace under it themselves
+        let state_dir = state_key
+            .rsplit_once('/')
+            .filter(|(_, file)| file.ends_with(".tfstate"))
+            .map(|(dir, _)| dir)
+            .unwrap_or(state_key);
+
+        match self {
+            BackendKind::S3 | BackendKind::AzureRm => state_key.to_string(),
+            BackendKind::Gcs | BackendKind::Consul => state_dir.to_string(),
+            BackendKind::Local => {
+                // Absolute, so modules reading each other's state agree on where it is
+                let base = project_root.join(configured.unwrap_or(LOCAL_STATE_DIR));
+                base.join(state_dir).to_string_lossy().to_string()
+            }
+            BackendKind::Http => format!(
+                "{}/{}",
+                configured.unwrap_or_default().trim_end_matches('/'),
+                state_key
+            ),
+            BackendKind::Pg => state_dir
+                .chars()
+                .map(|c| if c.is_ascii_alphanumeric() { c.to_ascii_lowercase() } else { '_' })
+                .collect(),
+        }
+    }
+}
+
+impl environment::BackendConfig {
+    pub fn kind(&self) -> Result<BackendKind> {
+        BackendKind::parse(&self.backend_type)
+    }
+
+    /// A config setting that is a string
+    pub fn string(&self, key: &str) -> Option<&str> {
+        self.config.get(key).and_then(|value| value.as_str())
+    }
+
+    /// Check the type is supported and every required field is set
+    pub fn validate(&self, location: &str) -> Result<()> {
+        if self.backend_type.trim().is_empty() {
+            return Err(EnvieError::ConfigError(
+                format!("{}.backend is missing a 'type'", location)
+            ));
+        }
+
+        let kind = self.kind().map_err(|e| EnvieError::ConfigError(format!("{}.backend: {}", location, e)))?;
+        let missing: Vec<&str> = kind.required_fields().iter()
+            .copied()
+            .filter(|field| !self.config.contains_key(*field))
+            .collect();
+        if !missing.is_empty() {
+            return Err(EnvieError::ConfigError(format!(
+                "{}.backend ({}) is missing required config: {}",
+                location, kind.name(), missing.join(", ")
+            )));
+        }
+
+        Ok(())
+    }
+
+    /// Config Terraform needs to reach the state at `state_key`, sorted by name
+    pub fn settings_for_state(&self, state_key: &str, project_root: &Path) -> Result<Vec<(String, serde_json::Value)>> {
+        let kind = self.kind()?;
+        let state_field = kind.state_field();
+
+        let mut settings: Vec<(String, serde_json::Value)> = self.config.iter()
+            .filter(|(key, _)| !ENVIE_SETTINGS.contains(&key.as_str()) && key.as_str() != state_field)
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect();
+        settings.push((
+            state_field.to_string(),
+            kind.state_location(state_key, self.string(state_field), project_root).into(),
+        ));
+        settings.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+        Ok(settings)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::environment::BackendConfig;
+    use std::collections::HashMap;
+
+    const STATE_KEY: &str = "ephemeral/myapp-123/api/lambda/terraform.tfstate";
+
+    fn backend(backend_type: &str, config: &[(&str, &str)]) -> BackendConfig {
+        BackendConfig {
+            backend_type: backend_type.to_string(),
+            config: config.iter()
+                .map(|(key, value)| (key.to_string(), serde_json::Value::from(*value)))
+                .collect::<HashMap<_, _>>(),
+        }
+    }
+
+    fn state_setting(backend: &BackendConfig) -> (String, String) {
+        let field = backend.kind().unwrap().state_field();
+        backend.settings_for_state(STATE_KEY, Path::new("/project")).unwrap()
+            .into_iter()
+            .find(|(key, _)| key == field)
+            .map(|(key, value)| (key, value.as_str().unwrap().to_string()))
+            .unwrap()
+    }
+
+    #[test]
+    fn test_state_location_per_backend() {
+        let cases = [
+            (backend("s3", &[("bucket", "b"), ("region", "eu-west-1")]), "key", STATE_KEY.to_string()),
+            (backend("azurerm", &[("storage_account_name", "a"), ("container_name", "c")]), "key", STATE_KEY.to_string()),
+            (backend("gcs", &[("bucket", "b")]), "prefix", "ephemeral/myapp-123/api/lambda".to_string()),
+            (backend("consul", &[]), "path", "ephemeral/myapp-123/api/lambda".to_string()),
+            (backend("pg", &[("conn_str", "postgres://db")]), "schema_name", "ephemeral_myapp_123_api_lambda".to_string()),
+            (
+                backend("http", &[("address", "https://state.example.com/")]),
+                "address",
+                format!("https://state.example.com/{}", STATE_KEY),
+            ),
+            (
+                backend("local", &[]),
+                "workspace_dir",
+                "/project/.envie/state/ephemeral/myapp-123/api/lambda".to_string(),
+            ),
+        ];
+
+        for (backend, field, location) in cases {
+            assert_eq!(state_setting(&backend), (field.to_string(), location), "{}", backend.backend_type);
+        }
+    }
+
+    #[test]
+    fn test_settings_drop_envie_only_keys_and_replace_state_field() {
+        let s3 = backend("s3", &[
+            ("region", "eu-west-1"),
+            ("bucket", "state"),
+            ("key", "ignored"),
+            ("key_pattern", "stable/{environment}/{service}/{module}/terraform.tfstate"),
+        ]);
+
+        let settings = s3.settings_for_state(STATE_KEY, Path::new("/project")).unwrap();
+        let keys: Vec<&str> = settings.iter().map(|(key, _)| key.as_str()).collect();
+        assert_eq!(keys, vec!["bucket", "key", "region"]);
+        assert_eq!(settings[1].1, STATE_KEY);
+
+        let local = backend("local", &[("workspace_dir", "state")]);
+        assert_eq!(state_setting(&local).1, "/project/state/ephemeral/myapp-123/api/lambda");
+    }
+
+    #[test]
+    fn test_validate_backend() {
+        assert!(backend("local", &[]).validate("environments.ephemeral").is_ok());
+
+        let error = backend("s3", &[("bucket", "state")]).validate("environments.ephemeral").unwrap_err();
+        assert!(error.to_string().contains("environments.ephemeral.backend (s3) is missing required config: region"));
+
+        let error = backend("swift", &[]).validate("environments.stable.sandbox").unwrap_err();
+        assert!(error.to_string().contains("Unsupported backend type 'swift'"));
+        assert!(error.to_string().contains("s3, gcs, azurerm, local, http, pg, consul"));
+    }
+}
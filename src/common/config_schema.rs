@@ -0,0 +1,233 @@
+use crate::common::*;
+use crate::common::service_config::{ServiceConfig, WorkspaceConfig};
+use serde::de::DeserializeOwned;
+use serde_json::{json, Value};
+use std::path::Path;
+
+/// JSON Schema of a config type, kept next to the type it describes
+pub trait ConfigSchema {
+    fn schema() -> Value;
+}
+
+/// The config files Envie reads
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum ConfigKind {
+    /// workspace.envie
+    Workspace,
+    /// `.envie` of a service
+    Service,
+    /// `.envie` of a module
+    Module,
+}
+
+impl ConfigKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ConfigKind::Workspace => "workspace",
+            ConfigKind::Service => "service",
+            ConfigKind::Module => "module",
+        }
+    }
+
+    /// Standalone JSON Schema document for the file
+    pub fn schema(&self) -> Value {
+        let (title, mut schema) = match self {
+            ConfigKind::Workspace => ("Envie workspace (workspace.envie)", WorkspaceConfig::schema()),
+            ConfigKind::Service => ("Envie service (.envie)", ServiceConfig::schema()),
+            ConfigKind::Module => ("Envie module (.envie)", ModuleConfig::schema()),
+        };
+        if let Value::Object(schema) = &mut schema {
+            schema.insert("$schema".to_string(), json!("https://json-schema.org/draft/2020-12/schema"));
+            schema.insert("title".to_string(), json!(title));
+        }
+        schema
+    }
+}
+
+/// A step into a config document
+#[derive(Debug, Clone, PartialEq)]
+pub enum PathSegment {
+    Key(String),
+    Index(usize),
+}
+
+/// e.g. `modules[0].depends`
+pub fn describe_path(path: &[PathSegment]) -> String {
+    let mut described = String::new();
+    for segment in path {
+        match segment {
+            PathSegment::Key(name) if described.is_empty() => described.push_str(name),
+            PathSegment::Key(name) => described.push_str(&format!(".{}", name)),
+            PathSegment::Index(index) => described.push_str(&format!("[{}]", index)),
+        }
+    }
+    described
+}
+
+/// A key the schema does not allow
+#[derive(Debug, Clone, PartialEq)]
+pub struct UnknownKey {
+    pub path: Vec<PathSegment>,
+    pub message: String,
+}
+
+/// Keys of `value` not declared in `schema`, in document order
+pub fn find_unknown_keys(value: &serde_yaml::Value, schema: &Value) -> Vec<UnknownKey> {
+    let mut unknown = Vec::new();
+    walk_unknown_keys(value, schema, &mut Vec::new(), &mut unknown);
+    unknown
+}
+
+fn walk_unknown_keys(
+    value: &serde_yaml::Value,
+    schema: &Value,
+    path: &mut Vec<PathSegment>,
+    unknown: &mut Vec<UnknownKey>,
+) {
+    // `depends` entries are either a string or an object
+    if let Some(branches) = schema.get("oneOf").and_then(|branches| branches.as_array()) {
+        if let Some(branch) = branches.iter().find(|branch| schema_accepts(branch, value)) {
+            walk_unknown_keys(value, branch, path, unknown);
+        }
+        return;
+    }
+
+    match value {
+        serde_yaml::Value::Mapping(mapping) => {
+            let properties = schema.get("properties").and_then(|properties| properties.as_object());
+            let additional = schema.get("additionalProperties");
+            for (name, child) in mapping {
+                let Some(name) = name.as_str() else { continue };
+                path.push(PathSegment::Key(name.to_string()));
+                match (properties.and_then(|properties| properties.get(name)), additional) {
+                    (Some(child_schema), _) => walk_unknown_keys(child, child_schema, path, unknown),
+                    (None, Some(Value::Bool(false))) => {
+                        let expected: Vec<&str> = properties
+                            .map(|properties| properties.keys().map(|key| key.as_str()).collect())
+                            .unwrap_or_default();
+                        let parent = describe_path(&path[..path.len() - 1]);
+                        let within = if parent.is_empty() { String::new() } else { format!(" in {}", parent) };
+                        unknown.push(UnknownKey {
+                            path: path.clone(),
+                            message: format!("unknown key '{}'{}. Expected one of: {}", name, within, expected.join(", ")),
+                        });
+                    }
+                    (None, Some(child_schema @ Value::Object(_))) => walk_unknown_keys(child, child_schema, path, unknown),
+                    (None, _) => {}
+                }
+                path.pop();
+            }
+        }
+        serde_yaml::Value::Sequence(items) => {
+            if let Some(item_schema) = schema.get("items") {
+                for (index, item) in items.iter().enumerate() {
+                    path.push(PathSegment::Index(index));
+                    walk_unknown_keys(item, item_schema, path, unknown);
+                    path.pop();
+                }
+            }
+        }
+        // Wrong types are reported when the config is parsed
+        _ => {}
+    }
+}
+
+fn schema_accepts(schema: &Value, value: &serde_yaml::Value) -> bool {
+    let expected = match value {
+        serde_yaml::Value::Mapping(_) => "object",
+        serde_yaml::Value::Sequence(_) => "array",
+        serde_yaml::Value::String(_) => "string",
+        serde_yaml::Value::Bool(_) => "boolean",
+        serde_yaml::Value::Number(_) => "number",
+        _ => return false,
+    };
+    schema.get("type").and_then(|t| t.as_str()) == Some(expected)
+}
+
+/// Parse a config file. With `strict`, keys its schema does not declare are an error
+/// instead of being ignored.
+pub fn read_config<T: DeserializeOwned + ConfigSchema>(path: &Path, kind: ConfigKind, strict: bool) -> Result<T> {
+    let content = std::fs::read_to_string(path)?;
+    let error = |message: String| EnvieError::ConfigError(
+        format!("Failed to parse {} config {}: {}", kind.name(), path.display(), message)
+    );
+
+    if strict {
+        let value: serde_yaml::Value = serde_yaml::from_str(&content).map_err(|e| error(e.to_string()))?;
+        let unknown = find_unknown_keys(&value, &T::schema());
+        if !unknown.is_empty() {
+            let messages: Vec<String> = unknown.into_iter().map(|key| key.message).collect();
+            return Err(error(format!("{} (strict is enabled in workspace.envie)", messages.join("; "))));
+        }
+    }
+
+    serde_yaml::from_str(&content).map_err(|e| error(e.to_string()))
+}
+
+/// Schema of an object that only allows the given properties
+pub fn object_schema(description: &str, properties: Value, required: &[&str]) -> Value {
+    json!({
+        "type": "object",
+        "description": description,
+        "properties": properties,
+        "required": required,
+        "additionalProperties": false,
+    })
+}
+
+/// Schema of a `variables` block: Terraform variable names to any value
+pub fn variables_schema(description: &str) -> Value {
+    json!({
+        "type": "object",
+        "description": description,
+        "additionalProperties": true,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_unknown_keys() {
+        let service: serde_yaml::Value = serde_yaml::from_str(r#"
+name: api
+modules:
+  - name: lambda
+    timeout: 30
+    depends:
+      - gateway
+      - path: ../database/modules/dynamodb
+        workspace: sandbox
+"#).unwrap();
+
+        let unknown = find_unknown_keys(&service, &ServiceConfig::schema());
+        let paths: Vec<String> = unknown.iter().map(|key| describe_path(&key.path)).collect();
+        assert_eq!(paths, vec!["modules[0].timeout", "modules[0].depends[1].workspace"]);
+        assert!(unknown[0].message.starts_with("unknown key 'timeout' in modules[0]. Expected one of:"));
+    }
+
+    #[test]
+    fn test_schema_documents() {
+        let workspace = ConfigKind::Workspace.schema();
+        assert_eq!(workspace["$schema"], "https://json-schema.org/draft/2020-12/schema");
+        assert_eq!(workspace["properties"]["environments"]["properties"]["ephemeral"]["properties"]["backend"]["properties"]["type"]["enum"][0], "s3");
+
+        // Stable environments are a map of arbitrary names
+        let stable = &workspace["properties"]["environments"]["properties"]["stable"];
+        assert_eq!(stable["additionalProperties"]["required"], json!(["workspace", "backend"]));
+    }
+
+    #[test]
+    fn test_strict_parsing_is_opt_in() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let path = temp_dir.path().join(".envie");
+        std::fs::write(&path, "name: api\nowner: team-a\n").unwrap();
+
+        let lenient: ServiceConfig = read_config(&path, ConfigKind::Service, false).unwrap();
+        assert_eq!(lenient.name, "api");
+
+        let strict = read_config::<ServiceConfig>(&path, ConfigKind::Service, true).unwrap_err();
+        assert!(strict.to_string().contains("unknown key 'owner'"));
+    }
+}
@@ -0,0 +1,666 @@
+use crate::common::*;
+use crate::common::service_config::{ServiceConfig, WorkspaceConfig};
+use serde::de::DeserializeOwned;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// A problem in a config file, at a 1-based line and column
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Diagnostic {
+    pub file: PathBuf,
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}:{}: {}", self.file.display(), self.line, self.column, self.message)
+    }
+}
+
+fn key(name: &str) -> PathSegment {
+    PathSegment::Key(name.to_string())
+}
+
+fn value_at<'a>(value: &'a serde_yaml::Value, path: &[PathSegment]) -> Option<&'a serde_yaml::Value> {
+    path.iter().try_fold(value, |value, segment| match segment {
+        PathSegment::Key(name) => value.get(name.as_str()),
+        PathSegment::Index(index) => value.get(*index),
+    })
+}
+
+/// Finds where a key or list item of a block-style YAML document starts. serde_yaml values
+/// carry no positions, so the source is re-read line by line; flow collections (`[a, b]`)
+/// resolve to the key holding them.
+struct YamlLocator {
+    entries: Vec<YamlEntry>,
+}
+
+struct YamlEntry {
+    line: usize,
+    indent: usize,
+    text: String,
+    item: bool,
+}
+
+impl YamlLocator {
+    fn new(content: &str) -> Self {
+        let mut entries = Vec::new();
+
+        for (index, raw) in content.lines().enumerate() {
+            let trimmed = raw.trim_start();
+            if trimmed.is_empty() || trimmed.starts_with('#') || trimmed.starts_with("---") {
+                continue;
+            }
+
+            let mut indent = raw.len() - trimmed.len();
+            let mut rest = trimmed;
+            // `- - a` and `- name: a` hold several entries on one line
+            while rest == "-" || rest.starts_with("- ") {
+                entries.push(YamlEntry { line: index + 1, indent, text: "-".to_string(), item: true });
+                let after = rest[1..].trim_start();
+                indent += rest.len() - after.len();
+                rest = after;
+            }
+            if !rest.is_empty() {
+                entries.push(YamlEntry { line: index + 1, indent, text: rest.to_string(), item: false });
+            }
+        }
+
+        Self { entries }
+    }
+
+    /// Line and column of `path`, or of the deepest part of it that could be found
+    fn locate(&self, path: &[PathSegment]) -> (usize, usize) {
+        let mut position = (1, 1);
+        let mut start = 0;
+        let mut end = self.entries.len();
+
+        for segment in path {
+            let Some(level) = self.entries[start..end].first().map(|entry| entry.indent) else {
+                break;
+            };
+            let mut siblings = (start..end).filter(|&i| {
+                let entry = &self.entries[i];
+                entry.indent == level && match segment {
+                    PathSegment::Key(name) => !entry.item && Self::is_key(&entry.text, name),
+                    PathSegment::Index(_) => entry.item,
+                }
+            });
+            let found = match segment {
+                PathSegment::Key(_) => siblings.next(),
+                PathSegment::Index(index) => siblings.nth(*index),
+            };
+            let Some(found) = found else {
+                break;
+            };
+
+            let entry = &self.entries[found];
+            position = (entry.line, entry.indent + 1);
+            start = found + 1;
+            end = self.block_end(found, end);
+        }
+
+        position
+    }
+
+    /// End of the block nested under an entry. A key's list may sit at the key's own indent.
+    fn block_end(&self, index: usize, limit: usize) -> usize {
+        let parent = &self.entries[index];
+        (index + 1..limit)
+            .find(|&i| {
+                let entry = &self.entries[i];
+                entry.indent < parent.indent
+                    || (entry.indent == parent.indent && (parent.item || !entry.item))
+            })
+            .unwrap_or(limit)
+    }
+
+    fn is_key(text: &str, name: &str) -> bool {
+        [name.to_string(), format!("\"{}\"", name), format!("'{}'", name)]
+            .iter()
+            .any(|quoted| text.strip_prefix(quoted.as_str()).is_some_and(|rest| rest.trim_start().starts_with(':')))
+    }
+}
+
+/// A config file that parsed as YAML
+struct ConfigFile {
+    /// Relative to the project root
+    display_path: PathBuf,
+    locator: YamlLocator,
+    value: serde_yaml::Value,
+}
+
+impl ConfigFile {
+    fn diagnostic(&self, path: &[PathSegment], message: String) -> Diagnostic {
+        let (line, column) = self.locator.locate(path);
+        Diagnostic {
+            file: self.display_path.clone(),
+            line,
+            column,
+            message,
+        }
+    }
+
+    fn has(&self, path: &[PathSegment]) -> bool {
+        value_at(&self.value, path).is_some()
+    }
+}
+
+/// Where a dependency edge is declared, for reporting cycles
+struct Edge {
+    target: String,
+    file: usize,
+    path: Vec<PathSegment>,
+}
+
+/// Checks every workspace, service and module config of a project and collects all problems
+/// instead of stopping at the first one
+pub struct ConfigValidator {
+    root: PathBuf,
+    files: Vec<ConfigFile>,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl ConfigValidator {
+    pub fn new<P: AsRef<Path>>(root: P) -> Self {
+        Self {
+            root: root.as_ref().to_path_buf(),
+            files: Vec::new(),
+            diagnostics: Vec::new(),
+        }
+    }
+
+    /// All problems found, sorted by file and position. An error is only returned when the
+    /// project has no workspace.envie at all.
+    pub fn validate(mut self) -> Result<Vec<Diagnostic>> {
+        let workspace_path = ["workspace.envie", ".envie.yaml"].iter()
+            .map(|name| self.root.join(name))
+            .find(|path| path.is_file())
+            .ok_or_else(|| EnvieError::ConfigError(format!(
+                "No workspace.envie found in {}. Run 'envie init' first.",
+                self.root.display()
+            )))?;
+
+        if let Some((workspace_file, workspace)) = self.load::<WorkspaceConfig>(&workspace_path) {
+            self.validate_workspace(workspace_file, &workspace);
+        }
+
+        let mut diagnostics = self.diagnostics;
+        diagnostics.sort();
+        diagnostics.dedup();
+        Ok(diagnostics)
+    }
+
+    fn validate_workspace(&mut self, workspace_file: usize, workspace: &WorkspaceConfig) {
+        let stable_environments = self.validate_environments(workspace_file, workspace);
+        let project_name = match &workspace.project {
+            Some(project) => project.name.clone(),
+            None => self.root.canonicalize().ok()
+                .and_then(|root| root.file_name().map(|name| name.to_string_lossy().to_string()))
+                .unwrap_or_default(),
+        };
+
+        let mut registry = ServiceRegistry {
+            services: HashMap::new(),
+            modules: HashMap::new(),
+            root: self.root.clone(),
+        };
+        // Service name -> file its .envie was read from
+        let mut service_files: Vec<(String, usize)> = Vec::new();
+        // Module key -> file and path of its config within the file
+        let mut module_entries: Vec<(String, String, usize, Vec<PathSegment>)> = Vec::new();
+
+        for (index, service_entry) in workspace.services.iter().enumerate() {
+            let entry_path = vec![key("services"), PathSegment::Index(index), key("path")];
+            let service_dir = self.root.join(&service_entry.path);
+            let config_path = service_dir.join(".envie");
+            if !config_path.is_file() {
+                let message = if service_dir.is_dir() {
+                    format!("service directory '{}' has no .envie file", service_entry.path)
+                } else {
+                    format!("service directory '{}' does not exist", service_entry.path)
+                };
+                self.report(workspace_file, &entry_path, message);
+                continue;
+            }
+
+            let Some((service_file, service_config)) = self.load::<ServiceConfig>(&config_path) else {
+                continue;
+            };
+
+            if let Some((_, first_file)) = service_files.iter().find(|(name, _)| name == &service_config.name) {
+                let message = format!(
+                    "service '{}' is already defined in {}",
+                    service_config.name,
+                    self.files[*first_file].display_path.display()
+                );
+                self.report(service_file, &[key("name")], message);
+                continue;
+            }
+            service_files.push((service_config.name.clone(), service_file));
+
+            let mut modules = Vec::new();
+            let mut module_names = HashSet::new();
+            for (module_index, module_entry) in service_config.modules.iter().enumerate() {
+                let module_path = vec![key("modules"), PathSegment::Index(module_index)];
+                if !module_names.insert(module_entry.name.clone()) {
+                    self.report(
+                        service_file,
+                        &[module_path.clone(), vec![key("name")]].concat(),
+                        format!("module '{}' is defined more than once in service '{}'", module_entry.name, service_config.name),
+                    );
+                    continue;
+                }
+
+                let module_dir = if module_entry.path.is_empty() {
+                    service_dir.join("modules").join(&module_entry.name)
+                } else {
+                    service_dir.join(&module_entry.path)
+                };
+                if !module_dir.is_dir() {
+                    let field = if self.files[service_file].has(&[module_path.clone(), vec![key("path")]].concat()) { "path" } else { "name" };
+                    let message = format!(
+                        "module directory '{}' does not exist",
+                        module_dir.strip_prefix(&self.root).unwrap_or(&module_dir).display()
+                    );
+                    self.report(service_file, &[module_path.clone(), vec![key(field)]].concat(), message);
+                    continue;
+                }
+
+                // A module's own .envie replaces its entry in the service config
+                let module_envie = module_dir.join(".envie");
+                let (module_config, module_file, module_path) = if module_envie.is_file() {
+                    match self.load::<ModuleConfig>(&module_envie) {
+                        Some((file, config)) => (config, file, vec![]),
+                        None => continue,
+                    }
+                } else {
+                    (module_entry.clone(), service_file, module_path)
+                };
+
+                module_entries.push((
+                    service_config.name.clone(),
+                    module_config.name.clone(),
+                    module_file,
+                    module_path,
+                ));
+                modules.push(DiscoveredModule {
+                    path: module_dir,
+                    config: module_config,
+                });
+            }
+
+            for module in &modules {
+                registry.modules.insert(format!("{}/{}", service_config.name, module.config.name), module.clone());
+            }
+            registry.services.insert(service_config.name.clone(), DiscoveredService {
+                path: service_dir,
+                config: service_config,
+                modules,
+            });
+        }
+
+        let mut edges: HashMap<String, Vec<Edge>> = HashMap::new();
+
+        for (service_name, service_file) in &service_files {
+            let service = &registry.services[service_name];
+            for (index, dep_path) in service.config.depends.iter().enumerate() {
+                let path = vec![key("depends"), PathSegment::Index(index)];
+                let dep_service = match registry.resolve_dependency_name(dep_path, service_name) {
+                    Ok(name) => &registry.services[&name],
+                    Err(e) => {
+                        self.report(*service_file, &path, Self::message(e));
+                        continue;
+                    }
+                };
+                for module in &service.modules {
+                    for dep_module in &dep_service.modules {
+                        edges.entry(format!("{}/{}", service_name, module.config.name)).or_default().push(Edge {
+                            target: format!("{}/{}", dep_service.config.name, dep_module.config.name),
+                            file: *service_file,
+                            path: path.clone(),
+                        });
+                    }
+                }
+            }
+        }
+
+        for (service_name, module_name, file, module_path) in &module_entries {
+            let node = format!("{}/{}", service_name, module_name);
+            let dependencies = registry.modules[&node].config.depends.clone();
+            let depends_path = [module_path.clone(), vec![key("depends")]].concat();
+
+            for (index, dependency) in dependencies.iter().enumerate() {
+                let item_path = [depends_path.clone(), vec![PathSegment::Index(index)]].concat();
+                let full_form = self.files[*file].has(&[item_path.clone(), vec![key("path")]].concat());
+                let field_path = |field: &str| if full_form {
+                    [item_path.clone(), vec![key(field)]].concat()
+                } else {
+                    item_path.clone()
+                };
+
+                if let Some(message) = Self::check_environment(&dependency.environment, &stable_environments, &project_name) {
+                    self.report(*file, &field_path("environment"), message);
+                }
+
+                match registry.resolve_module_reference(&dependency.path, &node) {
+                    Ok(target) if target == node => {
+                        self.report(*file, &field_path("path"), format!("module '{}' depends on itself", node));
+                    }
+                    Ok(target) => edges.entry(node.clone()).or_default().push(Edge {
+                        target,
+                        file: *file,
+                        path: field_path("path"),
+                    }),
+                    Err(e) => self.report(*file, &field_path("path"), Self::message(e)),
+                }
+            }
+
+            let scanner = TerraformScanner::default();
+            let remote_states = registry.modules[&node].config.remote_states.clone();
+            for (index, remote) in remote_states.iter().enumerate() {
+                let field_path = |field: &str| [module_path.clone(), vec![key("remote_states"), PathSegment::Index(index), key(field)]].concat();
+
+                if let Some(message) = Self::check_environment(&remote.environment, &stable_environments, &project_name) {
+                    self.report(*file, &field_path("environment"), message);
+                }
+
+                let target = match registry.resolve_module_reference(&remote.source, &node) {
+                    Ok(target) if target == node => {
+                        self.report(*file, &field_path("source"), format!("module '{}' reads its own remote state", node));
+                        continue;
+                    }
+                    Ok(target) => target,
+                    Err(e) => {
+                        self.report(*file, &field_path("source"), Self::message(e));
+                        continue;
+                    }
+                };
+
+                if let Ok(available) = scanner.scan_outputs(&registry.modules[&target].path) {
+                    for (output_index, output) in remote.outputs.iter().enumerate() {
+                        if !available.contains(output) {
+                            let output_path = [field_path("outputs"), vec![PathSegment::Index(output_index)]].concat();
+                            self.report(*file, &output_path, format!("{} does not declare output '{}'", target, output));
+                        }
+                    }
+                }
+
+                edges.entry(node.clone()).or_default().push(Edge {
+                    target,
+                    file: *file,
+                    path: field_path("source"),
+                });
+            }
+        }
+
+        self.report_cycles(&edges);
+    }
+
+    /// Check the `environments` block, returning the names of the stable environments
+    fn validate_environments(&mut self, workspace_file: usize, workspace: &WorkspaceConfig) -> HashSet<String> {
+        let Some(environments) = &workspace.environments else {
+            self.report(workspace_file, &[], "no 'environments' block. Define at least environments.ephemeral.backend".to_string());
+            return HashSet::new();
+        };
+
+        let ephemeral = vec![key("environments"), key("ephemeral")];
+        if let Err(e) = Template::parse(&environments.ephemeral.naming_pattern, NAMING_PLACEHOLDERS, "naming_pattern") {
+            self.report(workspace_file, &[ephemeral.clone(), vec![key("naming_pattern")]].concat(), Self::message(e));
+        }
+        if let Err(e) = EnvironmentConfig::validate_backend("environments.ephemeral", &environments.ephemeral.backend) {
+            self.report(workspace_file, &[ephemeral, vec![key("backend")]].concat(), Self::message(e));
+        }
+
+        let mut names: Vec<&String> = environments.stable.keys().collect();
+        names.sort();
+        for name in names {
+            let stable_env = &environments.stable[name];
+            let location = vec![key("environments"), key("stable"), key(name)];
+            if let Err(e) = EnvironmentConfig::validate_backend(&format!("environments.stable.{}", name), &stable_env.backend) {
+                self.report(workspace_file, &[location.clone(), vec![key("backend")]].concat(), Self::message(e));
+            }
+            if stable_env.workspace.trim().is_empty() {
+                self.report(workspace_file, &[location, vec![key("workspace")]].concat(), format!("environments.stable.{} has an empty 'workspace'", name));
+            }
+        }
+
+        environments.stable.keys().cloned().collect()
+    }
+
+    /// Why an environment reference cannot resolve, if it cannot
+    fn check_environment(environment: &str, stable: &HashSet<String>, project_name: &str) -> Option<String> {
+        if environment == "ephemeral" {
+            return None;
+        }
+        if let Some(id) = environment.strip_prefix("ephemeral.") {
+            return id.is_empty().then(|| "environment 'ephemeral.' is missing an ID".to_string());
+        }
+        if let Some(name) = environment.strip_prefix("stable.") {
+            if stable.contains(name) {
+                return None;
+            }
+            let mut available: Vec<&String> = stable.iter().collect();
+            available.sort();
+            return Some(format!(
+                "environment '{}' is not defined in environments.stable of workspace.envie. Available: {:?}",
+                environment, available
+            ));
+        }
+        // Direct workspace names: a stable environment or an ephemeral workspace of this project
+        if stable.contains(environment) || (!project_name.is_empty() && environment.starts_with(&format!("{}-", project_name))) {
+            return None;
+        }
+        Some(format!(
+            "environment '{}' is not defined in workspace.envie. Use ephemeral, ephemeral.<id> or stable.<name>",
+            environment
+        ))
+    }
+
+    fn report_cycles(&mut self, edges: &HashMap<String, Vec<Edge>>) {
+        let mut nodes: Vec<&String> = edges.keys().collect();
+        nodes.sort();
+
+        let mut visited = HashSet::new();
+        for node in nodes {
+            self.visit(node, edges, &mut visited, &mut Vec::new(), &mut Vec::new());
+        }
+    }
+
+    /// Depth-first search; `path_edges[i]` is the edge taken from `stack[i]`
+    fn visit<'a>(
+        &mut self,
+        node: &'a str,
+        edges: &'a HashMap<String, Vec<Edge>>,
+        visited: &mut HashSet<&'a str>,
+        stack: &mut Vec<&'a str>,
+        path_edges: &mut Vec<&'a Edge>,
+    ) {
+        if let Some(position) = stack.iter().position(|n| *n == node) {
+            let mut cycle = stack[position..].to_vec();
+            cycle.push(node);
+            // Reported at the dependency that leaves the first module of the cycle
+            let edge = path_edges[position];
+            self.report(edge.file, &edge.path, format!("dependency cycle: {}", cycle.join(" -> ")));
+            return;
+        }
+        if !visited.insert(node) {
+            return;
+        }
+
+        stack.push(node);
+        for edge in edges.get(node).map(|edges| edges.as_slice()).unwrap_or(&[]) {
+            path_edges.push(edge);
+            self.visit(&edge.target, edges, visited, stack, path_edges);
+            path_edges.pop();
+        }
+        stack.pop();
+    }
+
+    /// Read and parse a config file, reporting syntax errors, unknown keys and type errors
+    fn load<T: DeserializeOwned + ConfigSchema>(&mut self, path: &Path) -> Option<(usize, T)> {
+        let display_path = path.strip_prefix(&self.root).unwrap_or(path).to_path_buf();
+        let content = match std::fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(e) => {
+                self.diagnostics.push(Diagnostic { file: display_path, line: 1, column: 1, message: format!("cannot read file: {}", e) });
+                return None;
+            }
+        };
+
+        let value: serde_yaml::Value = match serde_yaml::from_str(&content) {
+            Ok(value) => value,
+            Err(e) => {
+                self.diagnostics.push(Self::parse_error(display_path, &e));
+                return None;
+            }
+        };
+
+        let file = self.files.len();
+        self.files.push(ConfigFile {
+            display_path: display_path.clone(),
+            locator: YamlLocator::new(&content),
+            value,
+        });
+
+        for unknown in find_unknown_keys(&self.files[file].value, &T::schema()) {
+            self.report(file, &unknown.path, unknown.message);
+        }
+
+        match serde_yaml::from_str::<T>(&content) {
+            Ok(config) => Some((file, config)),
+            Err(e) => {
+                self.diagnostics.push(Self::parse_error(display_path, &e));
+                None
+            }
+        }
+    }
+
+    fn parse_error(file: PathBuf, error: &serde_yaml::Error) -> Diagnostic {
+        let (line, column) = error.location().map(|l| (l.line(), l.column())).unwrap_or((1, 1));
+        let message = error.to_string();
+        // The position is already part of the diagnostic
+        let message = message.split(" at line ").next().unwrap_or(&message).to_string();
+        Diagnostic { file, line, column, message }
+    }
+
+    fn report(&mut self, file: usize, path: &[PathSegment], message: String) {
+        let diagnostic = self.files[file].diagnostic(path, message);
+        self.diagnostics.push(diagnostic);
+    }
+
+    /// The message of an error without its category prefix
+    fn message(error: EnvieError) -> String {
+        match error {
+            EnvieError::ConfigError(message)
+            | EnvieError::ValidationError(message)
+            | EnvieError::DependencyError(message)
+            | EnvieError::EnvironmentError(message) => message,
+            other => other.to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::test_support::write_file;
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_reports_every_problem_with_position() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        write_file(root, "workspace.envie", r#"version: "1.0"
+project:
+  name: myapp
+environments:
+  ephemeral:
+    backend:
+      type: local
+  stable:
+    sandbox:
+      workspace: sandbox
+      backend:
+        type: local
+services:
+  - path: services/api
+  - path: services/database
+  - path: services/missing
+  - path: services/copy
+unknown_top: 1
+"#);
+        write_file(root, "services/api/.envie", r#"name: api
+modules:
+  - name: lambda
+    depends:
+      - path: ../../database/modules/dynamodb
+        environment: stable.prod
+      - gateway
+    timeout: 30
+  - name: gateway
+    depends: [lambda]
+    remote_states:
+      - name: table
+        source: ../../database/modules/dynamodb
+        outputs:
+          - table_name
+  - name: gone
+"#);
+        write_file(root, "services/database/.envie", r#"name: database
+modules:
+  - name: dynamodb
+    depends:
+      - path: ../../networking/modules/vpc
+"#);
+        write_file(root, "services/copy/.envie", "name: api\n");
+        for dir in ["services/api/modules/lambda", "services/api/modules/gateway", "services/database/modules/dynamodb"] {
+            fs::create_dir_all(root.join(dir)).unwrap();
+        }
+
+        let diagnostics: Vec<String> = ConfigValidator::new(root).validate().unwrap()
+            .iter()
+            .map(|diagnostic| diagnostic.to_string())
+            .collect();
+
+        assert_eq!(diagnostics, vec![
+            "services/api/.envie:6:9: environment 'stable.prod' is not defined in environments.stable of workspace.envie. Available: [\"sandbox\"]",
+            "services/api/.envie:8:5: unknown key 'timeout' in modules[0]. Expected one of: depends, description, name, path, remote_states, variables",
+            "services/api/.envie:10:5: dependency cycle: api/gateway -> api/lambda -> api/gateway",
+            "services/api/.envie:15:11: database/dynamodb does not declare output 'table_name'",
+            "services/api/.envie:16:5: module directory 'services/api/modules/gone' does not exist",
+            "services/copy/.envie:1:1: service 'api' is already defined in services/api/.envie",
+            "services/database/.envie:5:9: Dependency '../../networking/modules/vpc' of database/dynamodb does not match any discovered module. \
+                Considered: services/database/networking/modules/vpc, services/networking/modules/vpc, networking/modules/vpc",
+            "workspace.envie:16:5: service directory 'services/missing' does not exist",
+            "workspace.envie:18:1: unknown key 'unknown_top'. Expected one of: defaults, environments, project, services, strict, version",
+        ]);
+    }
+
+    #[test]
+    fn test_type_errors_have_positions() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        write_file(root, "workspace.envie", "version: \"1.0\"\nenvironments:\n  ephemeral:\n    backend:\n      type: s3\nservices:\n  - path: services/api\n");
+        write_file(root, "services/api/.envie", "name: api\nmodules: lambda\n");
+
+        let diagnostics = ConfigValidator::new(root).validate().unwrap();
+        assert_eq!(diagnostics.len(), 2);
+        assert_eq!((diagnostics[0].file.as_path(), diagnostics[0].line), (Path::new("services/api/.envie"), 2));
+        assert!(diagnostics[0].message.contains("invalid type"));
+        assert_eq!(diagnostics[1].to_string(), "workspace.envie:4:5: environments.ephemeral.backend (s3) is missing required config: bucket, region");
+    }
+
+    #[test]
+    fn test_locate_yaml_paths() {
+        let locator = YamlLocator::new("a:\n- b: 1\n  c:\n    - x\n    - y: 2\n\"d\": [1, 2]\n");
+        assert_eq!(locator.locate(&[key("a"), PathSegment::Index(0), key("c"), PathSegment::Index(1), key("y")]), (5, 7));
+        assert_eq!(locator.locate(&[key("a"), PathSegment::Index(0), key("c")]), (3, 3));
+        assert_eq!(locator.locate(&[key("d"), PathSegment::Index(1)]), (6, 1));
+        assert_eq!(locator.locate(&[key("missing")]), (1, 1));
+    }
+}
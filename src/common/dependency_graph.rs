@@ -0,0 +1,260 @@
+use crate::common::*;
+use std::collections::{HashMap, HashSet};
+
+/// Module-granular dependency graph. Nodes are `service/module` keys as registered in the
+/// `ServiceRegistry`, edges point from a module to the modules it depends on.
+#[derive(Debug, Clone)]
+pub struct DependencyGraph {
+    /// Nodes in discovery order (services by name, modules in file order)
+    nodes: Vec<String>,
+    /// Node -> dependencies, in declaration order
+    edges: HashMap<String, Vec<DependencyEdge>>,
+}
+
+#[derive(Debug, Clone)]
+pub struct DependencyEdge {
+    /// `service/module` key of the dependency
+    pub target: String,
+    /// Configured environment reference (ephemeral, stable.sandbox, ...)
+    pub environment: String,
+}
+
+impl DependencyGraph {
+    /// Build the graph from module `depends` and `remote_states` and service-level `depends`.
+    ///
+    /// A service-level dependency makes every module of the service depend on every module
+    /// of the referenced service.
+    pub fn from_registry(registry: &ServiceRegistry) -> Result<Self> {
+        let mut service_names: Vec<&String> = registry.services.keys().collect();
+        service_names.sort();
+
+        let mut nodes = Vec::new();
+        let mut edges: HashMap<String, Vec<DependencyEdge>> = HashMap::new();
+
+        for service_name in service_names {
+            let service = &registry.services[service_name];
+
+            // Modules of every service this service depends on
+            let mut service_level_deps = Vec::new();
+            for dep_path in &service.config.depends {
+                let dep_service_name = registry.resolve_dependency_name(dep_path, service_name)?;
+                let dep_service = &registry.services[&dep_service_name];
+                for dep_module in &dep_service.modules {
+                    service_level_deps.push(DependencyEdge {
+                        target: format!("{}/{}", dep_service_name, dep_module.config.name),
+                        environment: "ephemeral".to_string(),
+                    });
+                }
+            }
+
+            for module in &service.modules {
+                let node = format!("{}/{}", service_name, module.config.name);
+                let mut deps = Vec::new();
+
+                let declared = module.config.depends.iter()
+                    .map(|dependency| (&dependency.path, &dependency.environment))
+                    .chain(module.config.remote_states.iter().map(|remote| (&remote.source, &remote.environment)));
+                for (path, environment) in declared {
+                    let dep_node = registry.resolve_module_reference(path, &node)?;
+                    if dep_node == node {
+                        return Err(EnvieError::DependencyError(
+                            format!("Module '{}' depends on itself", node)
+                        ));
+                    }
+                    deps.push(DependencyEdge {
+                        target: dep_node,
+                        environment: environment.clone(),
+                    });
+                }
+
+                // Explicit module dependencies take precedence over implied service-level ones
+                deps.extend(service_level_deps.iter().cloned());
+                let mut seen = HashSet::new();
+                deps.retain(|dep| seen.insert(dep.target.clone()));
+
+                nodes.push(node.clone());
+                edges.insert(node, deps);
+            }
+        }
+
+        Ok(Self { nodes, edges })
+    }
+
+    /// Every node, in discovery order
+    pub fn nodes(&self) -> &[String] {
+        &self.nodes
+    }
+
+    pub fn contains(&self, node: &str) -> bool {
+        self.edges.contains_key(node)
+    }
+
+    /// Direct dependencies of a node
+    pub fn dependencies(&self, node: &str) -> &[DependencyEdge] {
+        self.edges.get(node).map(|deps| deps.as_slice()).unwrap_or(&[])
+    }
+
+    /// Nodes that depend directly on `node`, in discovery order, with the edge to it
+    pub fn dependents(&self, node: &str) -> Vec<(&str, &DependencyEdge)> {
+        self.nodes.iter()
+            .filter_map(|dependent| {
+                self.dependencies(dependent).iter()
+                    .find(|edge| edge.target == node)
+                    .map(|edge| (dependent.as_str(), edge))
+            })
+            .collect()
+    }
+
+    /// Topological order of `roots` and everything they transitively depend on
+    pub fn order_from(&self, roots: &[String]) -> Result<Vec<String>> {
+        let mut visited = HashSet::new();
+        let mut stack = Vec::new();
+        let mut order = Vec::new();
+
+        for root in roots {
+            if !self.contains(root) {
+                return Err(EnvieError::DependencyError(
+                    format!("Module '{}' is not part of the dependency graph", root)
+                ));
+            }
+            self.visit(root, &mut visited, &mut stack, &mut order)?;
+        }
+
+        Ok(order)
+    }
+
+    /// Topological order of every module of `service_name` and their upstream dependencies
+    pub fn order_for_service(&self, service_name: &str) -> Result<Vec<String>> {
+        let prefix = format!("{}/", service_name);
+        let roots: Vec<String> = self.nodes.iter()
+            .filter(|node| node.starts_with(&prefix))
+            .cloned()
+            .collect();
+        self.order_from(&roots)
+    }
+
+    fn visit(
+        &self,
+        node: &str,
+        visited: &mut HashSet<String>,
+        stack: &mut Vec<String>,
+        order: &mut Vec<String>,
+    ) -> Result<()> {
+        if let Some(position) = stack.iter().position(|n| n == node) {
+            let mut cycle: Vec<&str> = stack[position..].iter().map(|n| n.as_str()).collect();
+            cycle.push(node);
+            return Err(EnvieError::DependencyError(
+                format!("Cyclic dependency detected: {}", cycle.join(" -> "))
+            ));
+        }
+
+        if visited.contains(node) {
+            return Ok(());
+        }
+
+        stack.push(node.to_string());
+        for dep in self.dependencies(node) {
+            self.visit(&dep.target, visited, stack, order)?;
+        }
+        stack.pop();
+
+        visited.insert(node.to_string());
+        order.push(node.to_string());
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::test_support::write_service;
+    use tempfile::TempDir;
+
+    fn position(order: &[String], node: &str) -> usize {
+        order.iter().position(|n| n == node).unwrap()
+    }
+
+    #[test]
+    fn test_module_order_within_and_across_services() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+
+        write_service(root, "networking", r#"
+name: networking
+modules:
+  - name: subnets
+    depends: [vpc]
+  - name: vpc
+"#);
+        write_service(root, "api", r#"
+name: api
+modules:
+  - name: gateway
+    depends:
+      - path: ./lambda
+        environment: ephemeral
+  - name: lambda
+    depends:
+      - path: ../../networking/modules/vpc
+        environment: ephemeral
+depends:
+  - ../networking
+"#);
+
+        let registry = ServiceRegistry::discover_from_path(root).unwrap();
+        let graph = DependencyGraph::from_registry(&registry).unwrap();
+
+        let order = graph.order_for_service("api").unwrap();
+        assert_eq!(order.len(), 4);
+        assert!(position(&order, "networking/vpc") < position(&order, "networking/subnets"));
+        assert!(position(&order, "networking/subnets") < position(&order, "api/lambda"));
+        assert!(position(&order, "api/lambda") < position(&order, "api/gateway"));
+
+        let networking_only = graph.order_for_service("networking").unwrap();
+        assert_eq!(networking_only, vec!["networking/vpc".to_string(), "networking/subnets".to_string()]);
+
+        let dependents: Vec<&str> = graph.dependents("networking/vpc").iter().map(|(node, _)| *node).collect();
+        assert_eq!(dependents, vec!["api/gateway", "api/lambda", "networking/subnets"]);
+    }
+
+    #[test]
+    fn test_cycle_reports_full_path() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+
+        write_service(root, "app", r#"
+name: app
+modules:
+  - name: a
+    depends: [b]
+  - name: b
+    depends: [c]
+  - name: c
+    depends: [a]
+"#);
+
+        let registry = ServiceRegistry::discover_from_path(root).unwrap();
+        let graph = DependencyGraph::from_registry(&registry).unwrap();
+
+        let error = graph.order_for_service("app").unwrap_err();
+        assert!(matches!(error, EnvieError::DependencyError(_)));
+        assert!(error.to_string().contains("app/a -> app/b -> app/c -> app/a"));
+    }
+
+    #[test]
+    fn test_unknown_module_dependency() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+
+        write_service(root, "app", r#"
+name: app
+modules:
+  - name: a
+    depends: [missing]
+"#);
+
+        let registry = ServiceRegistry::discover_from_path(root).unwrap();
+        assert!(DependencyGraph::from_registry(&registry).is_err());
+    }
+}
@@ -0,0 +1,279 @@
+use crate::common::*;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// Everything a command needs to work on one ephemeral environment: the discovered services,
+/// their module graph and an environment resolver for the target workspace.
+#[derive(Debug, Clone)]
+pub struct DeploymentContext {
+    pub root: PathBuf,
+    pub registry: ServiceRegistry,
+    pub graph: DependencyGraph,
+    pub merge_request: String,
+    pub workspace: String,
+    pub environment_resolver: EnvironmentResolver,
+    /// Where the full Terraform output of each module is written, if anywhere
+    pub logs: Option<RunLogs>,
+    /// Keep Terraform output off the terminal, e.g. when printing JSON
+    pub quiet: bool,
+}
+
+impl DeploymentContext {
+    pub fn load<P: AsRef<Path>>(root: P, merge_request: &str) -> Result<Self> {
+        let root = root.as_ref().to_path_buf();
+
+        // Discover services from the project root
+        let registry = ServiceRegistry::discover_from_path(&root)?;
+        if registry.services.is_empty() {
+            return Err(EnvieError::ValidationError(
+                "No services found. Make sure you're in a directory with .envie files or run from the project root.".to_string()
+            ));
+        }
+        let graph = DependencyGraph::from_registry(&registry)?;
+
+        // Load environment configuration from workspace.envie
+        let environment_config = EnvironmentConfig::load(&root)?;
+
+        // Resolve workspace name
+        let project_name = environment_config.project_name(&root)?;
+        let workspace = environment_config.ephemeral_workspace_name(&root, &project_name, merge_request)?;
+
+        // Create environment resolver, backed by the workspaces that exist in the ephemeral backend
+        let environment_resolver = EnvironmentResolver::new(
+            workspace.clone(),
+            project_name,
+            environment_config,
+        )
+        .with_project_root(&root);
+        let inventory = EnvironmentInventory::from_registry(&registry, &environment_resolver, &root)?;
+        let environment_resolver = environment_resolver.with_inventory(inventory);
+
+        Ok(Self {
+            root,
+            registry,
+            graph,
+            merge_request: merge_request.to_string(),
+            workspace,
+            environment_resolver,
+            logs: None,
+            quiet: false,
+        })
+    }
+
+    /// Tee the Terraform output of every module into `.envie/logs/<run-id>/`
+    pub fn with_logs(mut self, enabled: bool) -> Self {
+        self.logs = enabled.then(|| RunLogs::new(&self.root));
+        self
+    }
+
+    pub fn with_quiet(mut self, quiet: bool) -> Self {
+        self.quiet = quiet;
+        self
+    }
+
+    /// Terraform manager for a module, streaming its output prefixed with the module key
+    pub fn terraform(&self, module_key: &str, module_path: &Path, verbose: bool) -> TerraformManager {
+        TerraformManager::new(module_path)
+            .with_verbose(verbose)
+            .with_label(module_key)
+            .with_log_file(self.logs.as_ref().map(|logs| logs.module_log(module_key)))
+            .with_quiet(self.quiet)
+    }
+
+    /// The named service, or the service (or module of a service) containing `current_dir` when
+    /// no name is given
+    pub fn select_service(&self, service_name: Option<&str>, current_dir: &Path) -> Result<&DiscoveredService> {
+        if let Some(service_name) = service_name {
+            return self.registry.services.get(service_name).ok_or_else(|| EnvieError::ValidationError(
+                format!("Service '{}' not found", service_name)
+            ));
+        }
+
+        // Module directories may live outside their service's directory
+        let from_module = self.registry.find_module_containing(current_dir)
+            .and_then(|(key, _)| self.registry.services.get(split_module_key(key).0));
+        from_module
+            .or_else(|| self.registry.find_service_by_path(current_dir))
+            .ok_or_else(|| EnvieError::ValidationError(
+                "No service found in current directory. Specify a service name or run from a service directory.".to_string()
+            ))
+    }
+
+    pub fn plan(&self, service_name: &str, environment_overrides: &HashMap<String, String>) -> Result<DeploymentPlan> {
+        DeploymentPlan::build(
+            &self.registry,
+            &self.graph,
+            service_name,
+            &self.environment_resolver,
+            environment_overrides,
+        )
+    }
+
+    /// Generate the Envie-managed files of a module, initialize it and select the target workspace
+    pub fn prepare_module(
+        &self,
+        planned: &PlannedModule,
+        environment_overrides: &HashMap<String, String>,
+        verbose: bool,
+    ) -> Result<TerraformManager> {
+        let module = self.write_module_files(planned, environment_overrides)?;
+
+        let terraform_manager = self.terraform(&planned.key, &module.path, verbose);
+        terraform_manager.init_reconfigure()?;
+        if planned.resolved.backend.kind()?.supports_workspaces() {
+            terraform_manager.select_or_create_workspace(&self.workspace)?;
+        }
+
+        Ok(terraform_manager)
+    }
+
+    /// Regenerate the Envie-managed files (backend, remote state data sources and variables) of a module
+    pub fn write_module_files(
+        &self,
+        planned: &PlannedModule,
+        environment_overrides: &HashMap<String, String>,
+    ) -> Result<&DiscoveredModule> {
+        let module = self.write_backend_file(planned)?;
+
+        let generator = TerraformGenerator::new();
+        let dependencies = generator.resolve_dependencies(&self.registry, &planned.service, &module.config)?;
+        let remote_states = generator.resolve_remote_states(&self.registry, &planned.service, &module.config)?;
+        generator.write_generated_files(
+            &module.path,
+            &dependencies,
+            &remote_states,
+            &self.environment_resolver,
+            environment_overrides,
+        )?;
+        generator.write_variables_file(&module.path, &self.module_variables(planned)?)?;
+
+        Ok(module)
+    }
+
+    /// The variables of a module in the environment it resolves to
+    pub fn module_variables(&self, planned: &PlannedModule) -> Result<ModuleVariables> {
+        let service = self.registry.services.get(&planned.service).ok_or_else(|| EnvieError::ValidationError(
+            format!("Service '{}' not found", planned.service)
+        ))?;
+        Ok(ModuleVariables::resolve(
+            &self.environment_resolver.environment_config,
+            &planned.resolved.environment_type,
+            service,
+            self.module(&planned.key)?,
+        ))
+    }
+
+    /// Point the module's own state at its key in the environment it resolves to
+    pub fn write_backend_file(&self, planned: &PlannedModule) -> Result<&DiscoveredModule> {
+        let module = self.module(&planned.key)?;
+
+        TerraformGenerator::new().write_backend_file(
+            &module.path,
+            &self.environment_resolver,
+            &planned.resolved,
+            &planned.service,
+            &module.config.name,
+        )?;
+
+        Ok(module)
+    }
+
+    /// A module recorded in the environment manifest, resolved against the current configuration
+    pub fn planned_from_manifest(&self, recorded: &ManifestModule) -> Result<PlannedModule> {
+        let (service, _) = split_module_key(&recorded.module);
+        let resolved = self.environment_resolver.resolve_environment(&recorded.environment)?;
+        Ok(PlannedModule {
+            key: recorded.module.clone(),
+            service: service.to_string(),
+            environment: recorded.environment.clone(),
+            deploy: self.environment_resolver.is_current(&resolved),
+            resolved,
+        })
+    }
+
+    pub fn manifests(&self) -> ManifestStore {
+        ManifestStore::new(&self.root)
+    }
+
+    /// Record the status each module of `plan` reached in a run in the manifest of the environment
+    pub fn record_deployment(
+        &self,
+        service_name: &str,
+        plan: &DeploymentPlan,
+        statuses: &HashMap<String, ModuleStatus>,
+        environment_overrides: &HashMap<String, String>,
+    ) -> Result<()> {
+        let store = self.manifests();
+        let mut manifest = store.load(&self.workspace)?
+            .unwrap_or_else(|| EnvironmentManifest::new(&self.workspace, &self.merge_request));
+
+        let deployment = ServiceDeployment::from_run(
+            plan,
+            statuses,
+            environment_overrides,
+            manifest.services.get(service_name),
+        );
+
+        manifest.record(service_name, deployment, current_git_commit(&self.root));
+        store.save(&manifest)
+    }
+
+    pub fn module(&self, key: &str) -> Result<&DiscoveredModule> {
+        self.registry.modules.get(key).ok_or_else(|| EnvieError::ValidationError(
+            format!("Module '{}' not found", key)
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    fn fixture(main_tf: &str) -> TempDir {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        fs::write(root.join("workspace.envie"), r#"
+version: "1.0"
+project:
+  name: myapp
+services:
+  - path: services/api
+environments:
+  ephemeral:
+    backend:
+      type: local
+"#).unwrap();
+        let module_dir = root.join("services").join("api").join("modules").join("lambda");
+        fs::create_dir_all(&module_dir).unwrap();
+        fs::write(root.join("services").join("api").join(".envie"), "name: api\nmodules:\n  - name: lambda\n").unwrap();
+        fs::write(module_dir.join("main.tf"), main_tf).unwrap();
+        temp_dir
+    }
+
+    #[test]
+    fn test_write_module_files_writes_backend() {
+        let temp_dir = fixture("resource \"null_resource\" \"this\" {}\n");
+        let context = DeploymentContext::load(temp_dir.path(), "123").unwrap();
+        let plan = context.plan("api", &HashMap::new()).unwrap();
+
+        let module = context.write_module_files(&plan.modules[0], &HashMap::new()).unwrap();
+
+        let backend = fs::read_to_string(module.path.join(BACKEND_FILE)).unwrap();
+        let state_dir = temp_dir.path().join(".envie/state/ephemeral/api/lambda");
+        assert!(backend.contains("backend \"local\""));
+        assert!(backend.contains(&format!("workspace_dir = \"{}\"", state_dir.display())));
+    }
+
+    #[test]
+    fn test_module_with_own_backend_is_rejected() {
+        let temp_dir = fixture("terraform {\n  backend \"s3\" {}\n}\n");
+        let context = DeploymentContext::load(temp_dir.path(), "123").unwrap();
+        let plan = context.plan("api", &HashMap::new()).unwrap();
+
+        let error = context.write_module_files(&plan.modules[0], &HashMap::new()).unwrap_err();
+        assert!(error.to_string().contains("api/lambda declares its own backend \"s3\""));
+        assert!(error.to_string().contains("main.tf:2"));
+    }
+}
@@ -0,0 +1,205 @@
+use crate::common::*;
+use std::collections::{HashMap, VecDeque};
+
+/// A module in the dependency closure of a deployment, with the environment it resolved to
+#[derive(Debug, Clone)]
+pub struct PlannedModule {
+    /// `service/module` key
+    pub key: String,
+    pub service: String,
+    /// Environment reference after `-E` overrides (ephemeral, stable.sandbox, ...)
+    pub environment: String,
+    pub resolved: ResolvedEnvironment,
+    /// Whether the module resolved to the current ephemeral workspace and is deployed by this run
+    pub deploy: bool,
+}
+
+/// The modules of a service plus every upstream module reachable through ephemeral dependencies,
+/// in topological order.
+///
+/// Dependencies that resolve to a stable (or another ephemeral) environment are included so they
+/// can be reported, but they are not deployed and their own dependencies are not followed.
+#[derive(Debug, Clone)]
+pub struct DeploymentPlan {
+    pub modules: Vec<PlannedModule>,
+}
+
+impl DeploymentPlan {
+    pub fn build(
+        registry: &ServiceRegistry,
+        graph: &DependencyGraph,
+        service_name: &str,
+        environment_resolver: &EnvironmentResolver,
+        environment_overrides: &HashMap<String, String>,
+    ) -> Result<Self> {
+        let service = registry.services.get(service_name)
+            .ok_or_else(|| EnvieError::ValidationError(format!("Service '{}' not found", service_name)))?;
+
+        // The requested service always deploys into the current ephemeral workspace
+        let roots: Vec<String> = service.modules.iter()
+            .map(|module| format!("{}/{}", service_name, module.config.name))
+            .collect();
+
+        let mut environments: HashMap<String, (String, ResolvedEnvironment)> = HashMap::new();
+        let mut queue = VecDeque::new();
+        for root in &roots {
+            let resolved = environment_resolver.resolve_environment("ephemeral")?;
+            environments.insert(root.clone(), ("ephemeral".to_string(), resolved));
+            queue.push_back(root.clone());
+        }
+
+        // Follow dependencies only from modules that this run deploys
+        while let Some(node) = queue.pop_front() {
+            for edge in graph.dependencies(&node) {
+                let (dep_service, dep_module) = split_module_key(&edge.target);
+                let environment = dependency_environment(
+                    environment_overrides,
+                    dep_service,
+                    dep_module,
+                    &edge.environment,
+                );
+                let resolved = environment_resolver.resolve_environment(environment)?;
+                let is_current = environment_resolver.is_current(&resolved);
+
+                let already_current = environments.get(&edge.target)
+                    .map(|(_, existing)| environment_resolver.is_current(existing));
+                match already_current {
+                    Some(true) => continue,
+                    Some(false) if !is_current => continue,
+                    _ => {}
+                }
+
+                environments.insert(edge.target.clone(), (environment.to_string(), resolved));
+                if is_current {
+                    queue.push_back(edge.target.clone());
+                }
+            }
+        }
+
+        let mut modules = Vec::new();
+        for key in graph.order_for_service(service_name)? {
+            if let Some((environment, resolved)) = environments.remove(&key) {
+                let (service, _) = split_module_key(&key);
+                modules.push(PlannedModule {
+                    service: service.to_string(),
+                    deploy: environment_resolver.is_current(&resolved),
+                    environment,
+                    resolved,
+                    key,
+                });
+            }
+        }
+
+        Ok(Self { modules })
+    }
+
+    /// Modules this run deploys, in dependency order
+    pub fn to_deploy(&self) -> impl Iterator<Item = &PlannedModule> {
+        self.modules.iter().filter(|module| module.deploy)
+    }
+
+    /// Dependencies that resolve to another environment and are left untouched
+    pub fn skipped(&self) -> impl Iterator<Item = &PlannedModule> {
+        self.modules.iter().filter(|module| !module.deploy)
+    }
+}
+
+/// Split a `service/module` key
+pub fn split_module_key(key: &str) -> (&str, &str) {
+    key.split_once('/').unwrap_or((key, ""))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::test_support::write_service;
+    use tempfile::TempDir;
+
+    fn resolver() -> EnvironmentResolver {
+        let workspace_config = WorkspaceConfig::from_str(r#"
+version: "1.0"
+environments:
+  ephemeral:
+    backend:
+      type: local
+      config: {}
+  stable:
+    sandbox:
+      workspace: sandbox
+      backend:
+        type: local
+        config: {}
+"#).unwrap();
+        EnvironmentResolver::new(
+            "myapp-123".to_string(),
+            "myapp".to_string(),
+            EnvironmentConfig::from_workspace(&workspace_config).unwrap(),
+        )
+    }
+
+    fn fixture() -> (TempDir, ServiceRegistry, DependencyGraph) {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+
+        write_service(root, "networking", r#"
+name: networking
+modules:
+  - name: vpc
+"#);
+        write_service(root, "database", r#"
+name: database
+modules:
+  - name: dynamodb
+    depends:
+      - path: ../networking/modules/vpc
+        environment: ephemeral
+"#);
+        write_service(root, "api", r#"
+name: api
+modules:
+  - name: lambda
+    depends:
+      - path: ../../database/modules/dynamodb
+        environment: stable.sandbox
+      - path: ../../networking/modules/vpc
+        environment: ephemeral
+  - name: gateway
+    depends: [lambda]
+"#);
+
+        let registry = ServiceRegistry::discover_from_path(root).unwrap();
+        let graph = DependencyGraph::from_registry(&registry).unwrap();
+        (temp_dir, registry, graph)
+    }
+
+    #[test]
+    fn test_plan_deploys_ephemeral_closure_and_skips_stable() {
+        let (_temp_dir, registry, graph) = fixture();
+
+        let plan = DeploymentPlan::build(&registry, &graph, "api", &resolver(), &HashMap::new()).unwrap();
+
+        let deployed: Vec<&str> = plan.to_deploy().map(|m| m.key.as_str()).collect();
+        assert_eq!(deployed, vec!["networking/vpc", "api/lambda", "api/gateway"]);
+
+        let skipped: Vec<&str> = plan.skipped().map(|m| m.key.as_str()).collect();
+        assert_eq!(skipped, vec!["database/dynamodb"]);
+        assert_eq!(plan.skipped().next().unwrap().environment, "stable.sandbox");
+    }
+
+    #[test]
+    fn test_plan_applies_environment_overrides() {
+        let (_temp_dir, registry, graph) = fixture();
+
+        let mut overrides = HashMap::new();
+        overrides.insert("database".to_string(), "ephemeral".to_string());
+        overrides.insert("networking/vpc".to_string(), "stable.sandbox".to_string());
+
+        let plan = DeploymentPlan::build(&registry, &graph, "api", &resolver(), &overrides).unwrap();
+
+        let deployed: Vec<&str> = plan.to_deploy().map(|m| m.key.as_str()).collect();
+        assert_eq!(deployed, vec!["database/dynamodb", "api/lambda", "api/gateway"]);
+
+        let skipped: Vec<&str> = plan.skipped().map(|m| m.key.as_str()).collect();
+        assert_eq!(skipped, vec!["networking/vpc"]);
+    }
+}
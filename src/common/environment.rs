@@ -1,7 +1,9 @@
 use crate::common::*;
 use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 use serde::{Deserialize, Serialize};
-use crate::common::service_config::ProjectInfo;
+use crate::common::service_config::{ProjectInfo, WorkspaceConfig};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct EnvironmentConfig {
@@ -9,29 +11,136 @@ pub struct EnvironmentConfig {
     pub project: Option<ProjectInfo>,
     pub ephemeral: EphemeralConfig,
     pub stable: HashMap<String, StableEnvironmentConfig>,
+    /// `defaults.variables` of workspace.envie
+    #[serde(default)]
+    pub default_variables: HashMap<String, serde_json::Value>,
 }
 
 // Use the ProjectInfo from service_config module
 
+/// The `environments` block of workspace.envie
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EnvironmentsConfig {
+    pub ephemeral: EphemeralConfig,
+    #[serde(default)]
+    pub stable: HashMap<String, StableEnvironmentConfig>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct EphemeralConfig {
+    #[serde(default = "default_naming_pattern")]
     pub naming_pattern: String,
     pub backend: BackendConfig,
+    /// Terraform variables of every module deployed to an ephemeral environment
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub variables: HashMap<String, serde_json::Value>,
+}
+
+fn default_naming_pattern() -> String {
+    "{project}-{id}".to_string()
 }
 
+/// State key used when an environment's backend has no `key_pattern`. Every ephemeral
+/// environment shares the key of a module, so the backend's workspaces tell them apart and
+/// `terraform workspace list` sees all of them.
+const DEFAULT_EPHEMERAL_KEY_PATTERN: &str = "ephemeral/{service}/{module}/terraform.tfstate";
+/// Backends without workspaces keep each ephemeral environment at its own key instead
+const DEFAULT_EPHEMERAL_KEY_PATTERN_WITHOUT_WORKSPACES: &str = "ephemeral/{workspace}/{service}/{module}/terraform.tfstate";
+const DEFAULT_STABLE_KEY_PATTERN: &str = "stable/{environment}/{service}/{module}/terraform.tfstate";
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct StableEnvironmentConfig {
     pub workspace: String,
     pub backend: BackendConfig,
     #[serde(default)]
     pub description: String,
+    /// Terraform variables of every module deployed to this environment
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub variables: HashMap<String, serde_json::Value>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct BackendConfig {
     #[serde(rename = "type")]
     pub backend_type: String,
-    pub config: HashMap<String, String>,
+    /// Backend settings; any YAML value, rendered as typed HCL
+    #[serde(default)]
+    pub config: HashMap<String, serde_json::Value>,
+}
+
+impl ConfigSchema for EnvironmentsConfig {
+    fn schema() -> serde_json::Value {
+        let mut stable = StableEnvironmentConfig::schema();
+        stable["description"] = "A stable environment shared by every ephemeral one".into();
+        object_schema(
+            "Where ephemeral and stable environments keep their state",
+            serde_json::json!({
+                "ephemeral": EphemeralConfig::schema(),
+                "stable": {
+                    "type": "object",
+                    "description": "Stable environments by name, referenced as stable.<name>",
+                    "additionalProperties": stable,
+                },
+            }),
+            &["ephemeral"],
+        )
+    }
+}
+
+impl ConfigSchema for EphemeralConfig {
+    fn schema() -> serde_json::Value {
+        object_schema(
+            "The per merge request environments",
+            serde_json::json!({
+                "naming_pattern": {
+                    "type": "string",
+                    "description": "Workspace name template. Placeholders: {project}, {id}, {branch}, {user}, {region}",
+                    "default": default_naming_pattern(),
+                },
+                "backend": BackendConfig::schema(),
+                "variables": variables_schema("Terraform variables of every module deployed to an ephemeral environment"),
+            }),
+            &["backend"],
+        )
+    }
+}
+
+impl ConfigSchema for StableEnvironmentConfig {
+    fn schema() -> serde_json::Value {
+        object_schema(
+            "A stable environment",
+            serde_json::json!({
+                "workspace": { "type": "string", "description": "Terraform workspace of the environment" },
+                "backend": BackendConfig::schema(),
+                "description": { "type": "string" },
+                "variables": variables_schema("Terraform variables of every module deployed to this environment"),
+            }),
+            &["workspace", "backend"],
+        )
+    }
+}
+
+impl ConfigSchema for BackendConfig {
+    fn schema() -> serde_json::Value {
+        let kinds: Vec<&str> = BackendKind::ALL.iter().map(|kind| kind.name()).collect();
+        object_schema(
+            "Terraform backend holding the states",
+            serde_json::json!({
+                "type": { "type": "string", "enum": kinds },
+                "config": {
+                    "type": "object",
+                    "description": "Backend settings, rendered as typed HCL. key_pattern sets the state key template",
+                    "properties": {
+                        "key_pattern": {
+                            "type": "string",
+                            "description": "Placeholders: {project}, {workspace}, {environment}, {service}, {module}, {branch}, {user}, {region}",
+                        },
+                    },
+                },
+            }),
+            &["type"],
+        )
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -47,12 +156,29 @@ pub struct ResolvedEnvironment {
     pub backend: BackendConfig,
 }
 
+/// Environment to use for a dependency on `service/module`: a `-E service/module:env`
+/// override wins over `-E service:env`, which wins over the configured environment
+pub fn dependency_environment<'a>(
+    environment_overrides: &'a HashMap<String, String>,
+    service: &str,
+    module: &str,
+    configured: &'a str,
+) -> &'a str {
+    environment_overrides
+        .get(&format!("{}/{}", service, module))
+        .or_else(|| environment_overrides.get(service))
+        .map(|env| env.as_str())
+        .unwrap_or(configured)
+}
+
 #[derive(Debug, Clone)]
 pub struct EnvironmentResolver {
     pub current_workspace: String,
     pub project_name: String,
-    pub available_workspaces: Vec<String>,
+    pub inventory: Arc<EnvironmentInventory>,
     pub environment_config: EnvironmentConfig,
+    /// Root of the project; relative backend locations (local state) are resolved against it
+    pub project_root: PathBuf,
 }
 
 impl EnvironmentResolver {
@@ -64,13 +190,19 @@ impl EnvironmentResolver {
         Self {
             current_workspace,
             project_name,
-            available_workspaces: Vec::new(),
+            inventory: Arc::new(EnvironmentInventory::with_workspaces(Vec::new())),
             environment_config,
+            project_root: PathBuf::from("."),
         }
     }
     
-    pub fn with_available_workspaces(mut self, workspaces: Vec<String>) -> Self {
-        self.available_workspaces = workspaces;
+    pub fn with_inventory(mut self, inventory: EnvironmentInventory) -> Self {
+        self.inventory = Arc::new(inventory);
+        self
+    }
+    
+    pub fn with_project_root<P: AsRef<Path>>(mut self, project_root: P) -> Self {
+        self.project_root = project_root.as_ref().to_path_buf();
         self
     }
     
@@ -94,11 +226,14 @@ impl EnvironmentResolver {
     
     fn resolve_stable_environment(&self, env_name: &str) -> Result<ResolvedEnvironment> {
         let stable_env = self.environment_config.stable.get(env_name)
-            .ok_or_else(|| EnvieError::ValidationError(
-                format!("Stable environment '{}' not found. Available: {:?}", 
-                    env_name, 
-                    self.environment_config.stable.keys().collect::<Vec<_>>())
-            ))?;
+            .ok_or_else(|| {
+                let mut available: Vec<_> = self.environment_config.stable.keys().cloned().collect();
+                available.sort();
+                EnvieError::EnvironmentError(
+                    format!("Stable environment '{}' is not defined in environments.stable of workspace.envie. Available: {:?}",
+                        env_name, available)
+                )
+            })?;
         
         Ok(ResolvedEnvironment {
             workspace: stable_env.workspace.clone(),
@@ -116,13 +251,13 @@ impl EnvironmentResolver {
     }
     
     fn resolve_specific_ephemeral(&self, id: &str) -> Result<ResolvedEnvironment> {
-        let workspace = format!("{}-{}", self.project_name, id);
+        let workspace = self.environment_config.ephemeral_workspace_name(&self.project_root, &self.project_name, id)?;
         
-        // Validate workspace exists
-        if !self.available_workspaces.contains(&workspace) {
+        // Validate workspace exists (the current workspace is created by this run)
+        if workspace != self.current_workspace && !self.inventory.contains(&workspace)? {
             return Err(EnvieError::ValidationError(
                 format!("Ephemeral workspace '{}' does not exist. Available: {:?}", 
-                    workspace, self.available_workspaces)
+                    workspace, self.inventory.workspaces()?)
             ));
         }
         
@@ -159,69 +294,157 @@ impl EnvironmentResolver {
         })
     }
     
-    pub fn generate_state_key(&self, resolved_env: &ResolvedEnvironment, service: &str, module: &str) -> String {
-        match &resolved_env.environment_type {
-            EnvironmentType::Ephemeral => {
-                // ephemeral/{workspace}/{service}/{module}/terraform.tfstate
-                format!("ephemeral/{}/{}/{}/terraform.tfstate", 
-                    resolved_env.workspace, service, module)
-            }
-            EnvironmentType::Stable(env_name) => {
-                // Use the key_pattern from the backend config and substitute placeholders
-                let default_pattern = "stable/{environment}/{service}/{module}/terraform.tfstate".to_string();
-                let key_pattern = resolved_env.backend.config.get("key_pattern")
-                    .unwrap_or(&default_pattern);
-                
-                key_pattern
-                    .replace("{environment}", env_name)
-                    .replace("{service}", service)
-                    .replace("{module}", module)
-            }
-        }
+    /// Whether a resolved environment is the ephemeral workspace this run deploys to
+    pub fn is_current(&self, resolved_env: &ResolvedEnvironment) -> bool {
+        matches!(resolved_env.environment_type, EnvironmentType::Ephemeral)
+            && resolved_env.workspace == self.current_workspace
     }
     
-    pub fn generate_backend_config(&self, resolved_env: &ResolvedEnvironment, service: &str, module: &str) -> String {
-        let state_key = self.generate_state_key(resolved_env, service, module);
+    /// Render the environment's `key_pattern` for `service/module`
+    pub fn generate_state_key(&self, resolved_env: &ResolvedEnvironment, service: &str, module: &str) -> Result<String> {
+        let (environment, default_pattern) = match &resolved_env.environment_type {
+            EnvironmentType::Ephemeral if resolved_env.backend.kind()?.supports_workspaces() => ("ephemeral", DEFAULT_EPHEMERAL_KEY_PATTERN),
+            EnvironmentType::Ephemeral => ("ephemeral", DEFAULT_EPHEMERAL_KEY_PATTERN_WITHOUT_WORKSPACES),
+            EnvironmentType::Stable(env_name) => (env_name.as_str(), DEFAULT_STABLE_KEY_PATTERN),
+        };
+        let key_pattern = resolved_env.backend.string("key_pattern").unwrap_or(default_pattern);
+        let template = Template::parse(key_pattern, KEY_PLACEHOLDERS, "key_pattern")?;
         
-        let mut config_items = String::new();
-        for (key, value) in &resolved_env.backend.config {
-            if key == "key" {
-                config_items.push_str(&format!("    {} = \"{}\"\n", key, state_key));
-            } else {
-                config_items.push_str(&format!("    {} = \"{}\"\n", key, value));
-            }
+        let mut context = TemplateContext::new(&self.project_root)
+            .with("project", &self.project_name)
+            .with("workspace", &resolved_env.workspace)
+            .with("environment", environment)
+            .with("service", service)
+            .with("module", module);
+        if let Some(region) = resolved_env.backend.string("region") {
+            context = context.with("region", region);
+        }
+        template.render(&context)
+    }
+    
+    /// Backend settings pointing at the state of `service/module` in a resolved environment
+    pub fn backend_settings(&self, resolved_env: &ResolvedEnvironment, service: &str, module: &str) -> Result<Vec<(String, serde_json::Value)>> {
+        let state_key = self.generate_state_key(resolved_env, service, module)?;
+        resolved_env.backend.settings_for_state(&state_key, &self.project_root)
+    }
+    
+    /// The `terraform { backend ... }` block pointing a module's own state at its key in `resolved_env`
+    pub fn generate_backend_config(&self, resolved_env: &ResolvedEnvironment, service: &str, module: &str) -> Result<Block> {
+        let mut backend = Block::new("backend").label(&resolved_env.backend.backend_type);
+        for (key, value) in self.backend_settings(resolved_env, service, module)? {
+            backend = backend.attribute(&key, value);
         }
         
-        format!(r#"terraform {{
-  backend "{}" {{
-{}
-  }}
-}}
-"#,
-            resolved_env.backend.backend_type,
-            config_items
-        )
+        Ok(Block::new("terraform").block(backend))
     }
 }
 
 impl EnvironmentConfig {
-    pub fn from_file<P: AsRef<std::path::Path>>(path: P) -> Result<Self> {
-        let content = std::fs::read_to_string(path)?;
-        let config: EnvironmentConfig = serde_yaml::from_str(&content)
-            .map_err(|e| EnvieError::ConfigError(format!("Failed to parse environment config: {}", e)))?;
-        Ok(config)
+    /// Load the environment configuration from the workspace.envie (or .envie.yaml) at `root`
+    pub fn load<P: AsRef<Path>>(root: P) -> Result<Self> {
+        let workspace_config = ServiceRegistry::find_workspace_config(root.as_ref())?
+            .ok_or_else(|| EnvieError::ConfigError(format!(
+                "No workspace.envie found in {}. Run 'envie init' first.",
+                root.as_ref().display()
+            )))?;
+        Self::from_workspace(&workspace_config)
     }
-    
-    pub fn from_str(content: &str) -> Result<Self> {
-        let config: EnvironmentConfig = serde_yaml::from_str(content)
-            .map_err(|e| EnvieError::ConfigError(format!("Failed to parse environment config: {}", e)))?;
-        Ok(config)
+
+    /// Build the environment configuration from the `environments` block of a workspace config
+    pub fn from_workspace(workspace_config: &WorkspaceConfig) -> Result<Self> {
+        let environments = workspace_config.environments.as_ref()
+            .ok_or_else(|| EnvieError::ConfigError(
+                "workspace.envie has no 'environments' block. Define at least environments.ephemeral.backend".to_string()
+            ))?;
+
+        Template::parse(&environments.ephemeral.naming_pattern, NAMING_PLACEHOLDERS, "environments.ephemeral.naming_pattern")?;
+        Self::validate_backend("environments.ephemeral", &environments.ephemeral.backend)?;
+        Self::validate_ephemeral_key_pattern(&environments.ephemeral.backend)?;
+        for (name, stable_env) in &environments.stable {
+            Self::validate_backend(&format!("environments.stable.{}", name), &stable_env.backend)?;
+            if stable_env.workspace.trim().is_empty() {
+                return Err(EnvieError::ConfigError(
+                    format!("environments.stable.{} has an empty 'workspace'", name)
+                ));
+            }
+        }
+
+        let default_variables = match workspace_config.defaults.get("variables") {
+            None => HashMap::new(),
+            Some(variables) => serde_json::from_value(variables.clone()).map_err(|_| EnvieError::ConfigError(
+                "defaults.variables of workspace.envie must be a mapping of variable names to values".to_string()
+            ))?,
+        };
+
+        Ok(EnvironmentConfig {
+            project: workspace_config.project.clone(),
+            ephemeral: environments.ephemeral.clone(),
+            stable: environments.stable.clone(),
+            default_variables,
+        })
+    }
+
+    /// Check a backend is complete and its `key_pattern`, if any, is a valid template
+    pub fn validate_backend(location: &str, backend: &BackendConfig) -> Result<()> {
+        backend.validate(location)?;
+        match backend.config.get("key_pattern") {
+            None => Ok(()),
+            Some(serde_json::Value::String(key_pattern)) => {
+                Template::parse(key_pattern, KEY_PLACEHOLDERS, &format!("{}.backend.config.key_pattern", location))?;
+                Ok(())
+            }
+            Some(_) => Err(EnvieError::ConfigError(
+                format!("{}.backend.config.key_pattern must be a string", location)
+            )),
+        }
+    }
+
+    /// Ephemeral environments are selected as workspaces of the backend when it has them; a key
+    /// that differs per workspace would file each one under its own key, where neither
+    /// `terraform workspace list` nor the other environments can find it
+    fn validate_ephemeral_key_pattern(backend: &BackendConfig) -> Result<()> {
+        let kind = backend.kind()?;
+        let Some(key_pattern) = backend.string("key_pattern") else { return Ok(()) };
+        let location = "environments.ephemeral.backend.config.key_pattern";
+        if kind.supports_workspaces() && Template::parse(key_pattern, KEY_PLACEHOLDERS, location)?.uses("workspace") {
+            return Err(EnvieError::ConfigError(format!(
+                "{}: the {} backend keeps each workspace's state apart itself, so the key must not contain {{workspace}}. Use e.g. '{}'",
+                location, kind.name(), DEFAULT_EPHEMERAL_KEY_PATTERN
+            )));
+        }
+        Ok(())
+    }
+
+    /// Project name from workspace.envie, falling back to the name of the root directory
+    pub fn project_name<P: AsRef<Path>>(&self, root: P) -> Result<String> {
+        if let Some(project) = &self.project {
+            return Ok(project.name.clone());
+        }
+
+        std::fs::canonicalize(root.as_ref())?
+            .file_name()
+            .and_then(|name| name.to_str())
+            .map(|name| name.to_string())
+            .ok_or_else(|| EnvieError::ValidationError("Could not determine project name".to_string()))
+    }
+
+    /// Name of the ephemeral workspace for the given environment ID, rendered from `naming_pattern`
+    pub fn ephemeral_workspace_name<P: AsRef<Path>>(&self, root: P, project_name: &str, id: &str) -> Result<String> {
+        let template = Template::parse(&self.ephemeral.naming_pattern, NAMING_PLACEHOLDERS, "environments.ephemeral.naming_pattern")?;
+        let mut context = TemplateContext::new(root)
+            .with("project", project_name)
+            .with("id", id);
+        if let Some(region) = self.ephemeral.backend.string("region") {
+            context = context.with("region", region);
+        }
+        sanitize_workspace_name(&template.render(&context)?)
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::common::test_support::assert_schema_matches_fields;
     use std::collections::HashMap;
 
     #[test]
@@ -233,35 +456,39 @@ mod tests {
                 backend_type: "s3".to_string(),
                 config: {
                     let mut config = HashMap::new();
-                    config.insert("bucket".to_string(), "terraform-state-stable".to_string());
-                    config.insert("region".to_string(), "eu-west-1".to_string());
+                    config.insert("bucket".to_string(), "terraform-state-stable".into());
+                    config.insert("region".to_string(), "eu-west-1".into());
                     config
                 },
             },
             description: "Sandbox environment".to_string(),
+            variables: HashMap::new(),
         });
         
         let environment_config = EnvironmentConfig {
+            project: None,
             ephemeral: EphemeralConfig {
-                naming_pattern: "{repo}-{merge-request}".to_string(),
+                naming_pattern: "{project}-{id}".to_string(),
                 backend: BackendConfig {
                     backend_type: "s3".to_string(),
                     config: {
                         let mut config = HashMap::new();
-                        config.insert("bucket".to_string(), "terraform-state-ephemeral".to_string());
-                        config.insert("region".to_string(), "eu-west-1".to_string());
+                        config.insert("bucket".to_string(), "terraform-state-ephemeral".into());
+                        config.insert("region".to_string(), "eu-west-1".into());
                         config
                     },
                 },
+                variables: HashMap::new(),
             },
             stable: stable_envs,
+            default_variables: HashMap::new(),
         };
         
         let resolver = EnvironmentResolver::new(
             "myapp-123".to_string(),
             "myapp".to_string(),
             environment_config,
-        ).with_available_workspaces(vec!["myapp-123".to_string(), "myapp-456".to_string()]);
+        ).with_inventory(EnvironmentInventory::with_workspaces(vec!["myapp-123".to_string(), "myapp-456".to_string()]));
         
         // Test stable environment resolution
         let stable_result = resolver.resolve_environment("stable.sandbox").unwrap();
@@ -277,19 +504,25 @@ mod tests {
         let specific_ephemeral = resolver.resolve_environment("ephemeral.456").unwrap();
         assert_eq!(specific_ephemeral.workspace, "myapp-456");
         assert!(matches!(specific_ephemeral.environment_type, EnvironmentType::Ephemeral));
+        
+        // Unknown ephemeral workspaces are rejected
+        assert!(resolver.resolve_environment("ephemeral.789").is_err());
     }
     
     #[test]
     fn test_state_key_generation() {
         let environment_config = EnvironmentConfig {
+            project: None,
             ephemeral: EphemeralConfig {
-                naming_pattern: "{repo}-{merge-request}".to_string(),
+                naming_pattern: "{project}-{id}".to_string(),
                 backend: BackendConfig {
                     backend_type: "s3".to_string(),
                     config: HashMap::new(),
                 },
+                variables: HashMap::new(),
             },
             stable: HashMap::new(),
+            default_variables: HashMap::new(),
         };
         
         let resolver = EnvironmentResolver::new(
@@ -307,7 +540,209 @@ mod tests {
             },
         };
         
-        let state_key = resolver.generate_state_key(&ephemeral_env, "api", "lambda");
+        let state_key = resolver.generate_state_key(&ephemeral_env, "api", "lambda").unwrap();
+        assert_eq!(state_key, "ephemeral/api/lambda/terraform.tfstate");
+        
+        let mut backend = ephemeral_env.backend.clone();
+        backend.config.insert("key_pattern".to_string(), "{project}/{environment}/{service}-{module}.tfstate".into());
+        let patterned_env = ResolvedEnvironment { backend, ..ephemeral_env.clone() };
+        let state_key = resolver.generate_state_key(&patterned_env, "api", "lambda").unwrap();
+        assert_eq!(state_key, "myapp/ephemeral/api-lambda.tfstate");
+
+        // Without workspaces, the workspace is part of the key
+        let http_env = ResolvedEnvironment {
+            backend: BackendConfig {
+                backend_type: "http".to_string(),
+                config: HashMap::new(),
+            },
+            ..ephemeral_env
+        };
+        let state_key = resolver.generate_state_key(&http_env, "api", "lambda").unwrap();
         assert_eq!(state_key, "ephemeral/myapp-123/api/lambda/terraform.tfstate");
     }
+
+    #[test]
+    fn test_ephemeral_environments_share_module_state() {
+        let workspace_config = WorkspaceConfig::from_str(r#"
+version: "1.0"
+environments:
+  ephemeral:
+    backend:
+      type: s3
+      config:
+        bucket: terraform-state-ephemeral
+        region: eu-west-1
+"#).unwrap();
+        let environment_config = EnvironmentConfig::from_workspace(&workspace_config).unwrap();
+        let backend_settings = |workspace: &str| {
+            let resolver = EnvironmentResolver::new(workspace.to_string(), "myapp".to_string(), environment_config.clone());
+            let ephemeral = resolver.resolve_environment("ephemeral").unwrap();
+            assert_eq!(ephemeral.workspace, workspace);
+            resolver.backend_settings(&ephemeral, "api", "lambda").unwrap()
+        };
+
+        // Both environments use the same key; the selected workspace keeps them apart
+        let settings = backend_settings("myapp-123");
+        assert_eq!(settings, backend_settings("myapp-456"));
+        assert!(settings.contains(&("key".to_string(), "ephemeral/api/lambda/terraform.tfstate".into())));
+    }
+
+    #[test]
+    fn test_ephemeral_key_pattern_must_not_depend_on_workspace() {
+        let workspace_config = |backend: &str| WorkspaceConfig::from_str(&format!(r#"
+version: "1.0"
+environments:
+  ephemeral:
+    backend:
+      {}
+      config:
+        key_pattern: "ephemeral/{{workspace}}/{{service}}/{{module}}/terraform.tfstate"
+"#, backend)).unwrap();
+
+        let error = EnvironmentConfig::from_workspace(&workspace_config("type: local")).unwrap_err();
+        assert!(error.to_string().contains("the local backend keeps each workspace's state apart itself, so the key must not contain {workspace}"));
+
+        let mut http = workspace_config("type: http");
+        http.environments.as_mut().unwrap().ephemeral.backend.config.insert("address".to_string(), "https://state.example.com".into());
+        assert!(EnvironmentConfig::from_workspace(&http).is_ok());
+    }
+
+    #[test]
+    fn test_backend_config_renders_typed_values() {
+        let workspace_config = WorkspaceConfig::from_str(r#"
+version: "1.0"
+environments:
+  ephemeral:
+    backend:
+      type: s3
+      config:
+        bucket: terraform-state-ephemeral
+        region: eu-west-1
+        encrypt: true
+        max_retries: 5
+        shared_config_files: ["~/.aws/config"]
+        assume_role:
+          role_arn: arn:aws:iam::123456789012:role/terraform
+"#).unwrap();
+
+        let environment_config = EnvironmentConfig::from_workspace(&workspace_config).unwrap();
+        let resolver = EnvironmentResolver::new(
+            "myapp-123".to_string(),
+            "myapp".to_string(),
+            environment_config,
+        );
+        let ephemeral = resolver.resolve_environment("ephemeral").unwrap();
+        let backend = resolver.generate_backend_config(&ephemeral, "api", "lambda").unwrap();
+
+        crate::common::hcl::tests::assert_golden("hcl/backend.tf", &backend.to_string());
+    }
+
+    #[test]
+    fn test_environment_config_from_workspace() {
+        let workspace_config = WorkspaceConfig::from_str(r#"
+version: "1.0"
+project:
+  name: myapp
+environments:
+  ephemeral:
+    backend:
+      type: s3
+      config:
+        bucket: terraform-state-ephemeral
+        region: eu-west-1
+  stable:
+    sandbox:
+      workspace: sandbox
+      backend:
+        type: s3
+        config:
+          bucket: terraform-state-stable
+          region: eu-west-1
+"#).unwrap();
+
+        let environment_config = EnvironmentConfig::from_workspace(&workspace_config).unwrap();
+        assert_eq!(environment_config.ephemeral.naming_pattern, "{project}-{id}");
+        assert_eq!(environment_config.project_name(".").unwrap(), "myapp");
+
+        let resolver = EnvironmentResolver::new(
+            "myapp-123".to_string(),
+            "myapp".to_string(),
+            environment_config,
+        );
+        let sandbox = resolver.resolve_environment("stable.sandbox").unwrap();
+        assert_eq!(sandbox.backend.config["bucket"], "terraform-state-stable");
+
+        let missing = resolver.resolve_environment("stable.production").unwrap_err();
+        assert!(missing.to_string().contains("Available: [\"sandbox\"]"));
+    }
+
+    #[test]
+    fn test_naming_pattern() {
+        let workspace_config = |naming_pattern: &str| WorkspaceConfig::from_str(&format!(r#"
+version: "1.0"
+environments:
+  ephemeral:
+    naming_pattern: "{}"
+    backend:
+      type: local
+"#, naming_pattern)).unwrap();
+
+        let environment_config = EnvironmentConfig::from_workspace(&workspace_config("{project}/mr {id}")).unwrap();
+        assert_eq!(environment_config.ephemeral_workspace_name(".", "myapp", "123").unwrap(), "myapp-mr-123");
+
+        let error = EnvironmentConfig::from_workspace(&workspace_config("{repo}-{id}")).unwrap_err();
+        assert!(error.to_string().contains("environments.ephemeral.naming_pattern: unknown placeholder {repo}"));
+    }
+
+    #[test]
+    fn test_environment_config_requires_environments_block() {
+        let workspace_config = WorkspaceConfig::from_str("version: \"1.0\"\n").unwrap();
+        let error = EnvironmentConfig::from_workspace(&workspace_config).unwrap_err();
+        assert!(error.to_string().contains("environments"));
+
+        let missing_backend = WorkspaceConfig::from_str(r#"
+version: "1.0"
+environments:
+  ephemeral:
+    naming_pattern: "{project}-{id}"
+"#);
+        assert!(missing_backend.unwrap_err().to_string().contains("backend"));
+
+        let incomplete_backend = WorkspaceConfig::from_str(r#"
+version: "1.0"
+environments:
+  ephemeral:
+    backend:
+      type: gcs
+"#).unwrap();
+        let error = EnvironmentConfig::from_workspace(&incomplete_backend).unwrap_err();
+        assert!(error.to_string().contains("environments.ephemeral.backend (gcs) is missing required config: bucket"));
+    }
+
+    #[test]
+    fn test_environment_config_load() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        assert!(EnvironmentConfig::load(temp_dir.path()).is_err());
+
+        std::fs::write(temp_dir.path().join("workspace.envie"), r#"
+version: "1.0"
+environments:
+  ephemeral:
+    backend:
+      type: local
+      config: {}
+"#).unwrap();
+
+        let environment_config = EnvironmentConfig::load(temp_dir.path()).unwrap();
+        assert_eq!(environment_config.ephemeral.backend.backend_type, "local");
+        assert!(environment_config.stable.is_empty());
+    }
+
+    #[test]
+    fn test_schemas_match_fields() {
+        assert_schema_matches_fields::<EnvironmentsConfig>();
+        assert_schema_matches_fields::<EphemeralConfig>();
+        assert_schema_matches_fields::<StableEnvironmentConfig>();
+        assert_schema_matches_fields::<BackendConfig>();
+    }
 }
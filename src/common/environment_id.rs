@@ -0,0 +1,158 @@
+use crate::common::*;
+use regex::Regex;
+use std::fmt;
+use std::path::Path;
+
+/// CI variables holding the number of the merge/pull request a pipeline runs for, by priority
+const MERGE_REQUEST_VARIABLES: &[&str] = &[
+    // GitLab
+    "CI_MERGE_REQUEST_IID",
+    // Bitbucket Pipelines
+    "BITBUCKET_PR_ID",
+    // Jenkins multibranch pipelines
+    "CHANGE_ID",
+];
+
+/// CI variables holding the branch of a pipeline, for checkouts with a detached HEAD
+const BRANCH_VARIABLES: &[&str] = &[
+    "CI_COMMIT_REF_NAME",
+    "GITHUB_HEAD_REF",
+    "GITHUB_REF_NAME",
+    "BITBUCKET_BRANCH",
+    "BRANCH_NAME",
+];
+
+/// Where an environment ID came from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IdSource {
+    /// `--merge-request`
+    Flag,
+    /// A CI variable, by name
+    Variable(String),
+    /// The slug of the checked out git branch
+    GitBranch(String),
+}
+
+impl fmt::Display for IdSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IdSource::Flag => write!(f, "--merge-request"),
+            IdSource::Variable(name) => write!(f, "${}", name),
+            IdSource::GitBranch(branch) => write!(f, "git branch {}", branch),
+        }
+    }
+}
+
+/// The ID of the ephemeral environment a command works on
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EnvironmentId {
+    pub id: String,
+    pub source: IdSource,
+}
+
+impl EnvironmentId {
+    /// The ID given with `--merge-request`, or else the first of:
+    /// 1. the merge/pull request number from CI (`CI_MERGE_REQUEST_IID`, `GITHUB_REF` of a
+    ///    pull request, `BITBUCKET_PR_ID`, `CHANGE_ID`)
+    /// 2. the slug of the git branch checked out at `root`
+    /// 3. the slug of the branch from CI (`CI_COMMIT_REF_NAME`, `GITHUB_HEAD_REF`,
+    ///    `GITHUB_REF_NAME`, `BITBUCKET_BRANCH`, `BRANCH_NAME`)
+    pub fn resolve(flag: Option<&str>, root: &Path) -> Result<Self> {
+        if let Some(id) = flag {
+            return Ok(Self { id: id.to_string(), source: IdSource::Flag });
+        }
+
+        let variable = |name: &str| std::env::var(name).ok().filter(|value| !value.trim().is_empty());
+        Self::infer(variable, || current_git_branch(root)).ok_or_else(|| EnvieError::ValidationError(
+            "Could not determine the environment ID. Pass --merge-request, or run from a git branch or a CI merge request pipeline.".to_string()
+        ))
+    }
+
+    /// e.g. `123 (from $CI_MERGE_REQUEST_IID)`
+    pub fn describe(&self) -> String {
+        format!("{} (from {})", self.id, self.source)
+    }
+
+    fn infer<V, B>(variable: V, git_branch: B) -> Option<Self>
+    where
+        V: Fn(&str) -> Option<String>,
+        B: FnOnce() -> Option<String>,
+    {
+        if let Some(id) = variable(MERGE_REQUEST_VARIABLES[0]) {
+            return Some(Self { id, source: IdSource::Variable(MERGE_REQUEST_VARIABLES[0].to_string()) });
+        }
+
+        // refs/pull/<number>/merge on GitHub Actions pull request events
+        let pull_request = Regex::new(r"^refs/pull/(\d+)/").ok()?;
+        if let Some(caps) = variable("GITHUB_REF").as_deref().and_then(|r| pull_request.captures(r)) {
+            return Some(Self { id: caps[1].to_string(), source: IdSource::Variable("GITHUB_REF".to_string()) });
+        }
+
+        for name in &MERGE_REQUEST_VARIABLES[1..] {
+            if let Some(id) = variable(name) {
+                return Some(Self { id, source: IdSource::Variable(name.to_string()) });
+            }
+        }
+
+        if let Some(branch) = git_branch() {
+            let id = slug(&branch);
+            if !id.is_empty() {
+                return Some(Self { id, source: IdSource::GitBranch(branch) });
+            }
+        }
+
+        BRANCH_VARIABLES.iter().find_map(|name| {
+            let id = slug(&variable(name)?);
+            (!id.is_empty()).then(|| Self { id, source: IdSource::Variable(name.to_string()) })
+        })
+    }
+}
+
+/// Whether Envie runs in a CI pipeline (GitLab, GitHub Actions, Bitbucket Pipelines or Jenkins)
+pub fn running_in_ci() -> bool {
+    ["CI", "CI_PIPELINE_URL", "GITHUB_ACTIONS", "BITBUCKET_BUILD_NUMBER", "JENKINS_URL"]
+        .iter()
+        .any(|name| std::env::var(name).is_ok_and(|value| !value.is_empty() && value != "false"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn infer(variables: &[(&str, &str)], branch: Option<&str>) -> Option<EnvironmentId> {
+        let variables: HashMap<String, String> = variables.iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect();
+        EnvironmentId::infer(|name| variables.get(name).cloned(), || branch.map(|b| b.to_string()))
+    }
+
+    #[test]
+    fn test_infer_priority() {
+        let gitlab = infer(&[("CI_MERGE_REQUEST_IID", "42"), ("GITHUB_REF", "refs/pull/7/merge")], Some("main")).unwrap();
+        assert_eq!(gitlab, EnvironmentId { id: "42".to_string(), source: IdSource::Variable("CI_MERGE_REQUEST_IID".to_string()) });
+
+        let github = infer(&[("GITHUB_REF", "refs/pull/7/merge"), ("CHANGE_ID", "9")], None).unwrap();
+        assert_eq!(github.id, "7");
+        assert_eq!(github.source.to_string(), "$GITHUB_REF");
+
+        // A branch push is not a pull request
+        let jenkins = infer(&[("GITHUB_REF", "refs/heads/main"), ("CHANGE_ID", "9")], None).unwrap();
+        assert_eq!(jenkins.id, "9");
+
+        let branch = infer(&[("CI_COMMIT_REF_NAME", "other")], Some("Feature/Add-Login")).unwrap();
+        assert_eq!(branch.id, "feature-add-login");
+        assert_eq!(branch.source.to_string(), "git branch Feature/Add-Login");
+
+        let detached = infer(&[("CI_COMMIT_REF_NAME", "fix/typo")], None).unwrap();
+        assert_eq!(detached.id, "fix-typo");
+
+        assert!(infer(&[], None).is_none());
+    }
+
+    #[test]
+    fn test_flag_wins() {
+        let id = EnvironmentId::resolve(Some("123"), Path::new(".")).unwrap();
+        assert_eq!(id, EnvironmentId { id: "123".to_string(), source: IdSource::Flag });
+    }
+}
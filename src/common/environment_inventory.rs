@@ -0,0 +1,198 @@
+use crate::common::*;
+use crate::common::environment::BackendConfig;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use uuid::Uuid;
+use walkdir::WalkDir;
+
+/// Inventory of the ephemeral workspaces that actually exist in the backend.
+///
+/// The backend is queried at most once per run, on first use: local backends are read from
+/// the state directory under the project root, every other backend through
+/// `terraform workspace list` against the ephemeral state of each module. The modules do not
+/// need to be initialized, e.g. in a fresh CI checkout.
+#[derive(Debug)]
+pub struct EnvironmentInventory {
+    /// `terraform { backend ... }` of each module's ephemeral state
+    module_backends: Vec<Block>,
+    backend: BackendConfig,
+    project_root: PathBuf,
+    workspaces: OnceLock<Vec<String>>,
+}
+
+impl EnvironmentInventory {
+    pub fn new<P: AsRef<Path>>(module_backends: Vec<Block>, backend: BackendConfig, project_root: P) -> Self {
+        Self {
+            module_backends,
+            backend,
+            project_root: project_root.as_ref().to_path_buf(),
+            workspaces: OnceLock::new(),
+        }
+    }
+
+    /// Inventory of the ephemeral backend for every module in the registry
+    pub fn from_registry<P: AsRef<Path>>(
+        registry: &ServiceRegistry,
+        environment_resolver: &EnvironmentResolver,
+        project_root: P,
+    ) -> Result<Self> {
+        let ephemeral = environment_resolver.resolve_environment("ephemeral")?;
+        let mut keys: Vec<&String> = registry.modules.keys().collect();
+        keys.sort();
+        let module_backends = keys.into_iter()
+            .map(|key| {
+                let (service, module) = split_module_key(key);
+                environment_resolver.generate_backend_config(&ephemeral, service, module)
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Self::new(module_backends, ephemeral.backend, project_root))
+    }
+
+    /// Inventory with a known list of workspaces, without querying any backend
+    pub fn with_workspaces(workspaces: Vec<String>) -> Self {
+        let inventory = Self::new(Vec::new(), BackendConfig {
+            backend_type: "local".to_string(),
+            config: Default::default(),
+        }, ".");
+        let _ = inventory.workspaces.set(workspaces);
+        inventory
+    }
+
+    /// Existing workspaces (excluding `default`), sorted
+    pub fn workspaces(&self) -> Result<&[String]> {
+        if let Some(workspaces) = self.workspaces.get() {
+            return Ok(workspaces);
+        }
+
+        let workspaces = self.query()?;
+        Ok(self.workspaces.get_or_init(|| workspaces))
+    }
+
+    pub fn contains(&self, workspace: &str) -> Result<bool> {
+        Ok(self.workspaces()?.iter().any(|w| w == workspace))
+    }
+
+    fn query(&self) -> Result<Vec<String>> {
+        let kind = self.backend.kind()?;
+        let mut workspaces = if kind == BackendKind::Local {
+            self.local_workspaces()?
+        } else if kind.supports_workspaces() {
+            let mut workspaces = Vec::new();
+            for backend in &self.module_backends {
+                workspaces.extend(backend_workspaces(backend)?);
+            }
+            workspaces
+        } else {
+            // Every environment lives at an address of its own, which cannot be listed
+            Vec::new()
+        };
+
+        workspaces.retain(|workspace| workspace != "default");
+        workspaces.sort();
+        workspaces.dedup();
+        Ok(workspaces)
+    }
+
+    /// Every module keeps its states under its own `workspace_dir` below the state root, as
+    /// `<workspace>/terraform.tfstate`
+    fn local_workspaces(&self) -> Result<Vec<String>> {
+        let state_root = self.project_root.join(
+            self.backend.string("workspace_dir").unwrap_or(LOCAL_STATE_DIR)
+        );
+
+        if !state_root.is_dir() {
+            return Ok(Vec::new());
+        }
+
+        let mut workspaces = Vec::new();
+        for entry in WalkDir::new(&state_root) {
+            let entry = entry.map_err(|e| EnvieError::FileSystemError(e.to_string()))?;
+            if entry.file_type().is_file() && entry.file_name() == "terraform.tfstate" {
+                if let Some(name) = entry.path().parent().and_then(|dir| dir.file_name()).and_then(|name| name.to_str()) {
+                    workspaces.push(name.to_string());
+                }
+            }
+        }
+        Ok(workspaces)
+    }
+}
+
+/// Workspaces of the state a backend block points at. Terraform only lists them from an
+/// initialized directory, so a scratch one holding nothing but the block is initialized instead
+/// of the module.
+fn backend_workspaces(backend: &Block) -> Result<Vec<String>> {
+    let scratch_dir = std::env::temp_dir().join(format!("envie-inventory-{}", Uuid::new_v4()));
+    std::fs::create_dir_all(&scratch_dir)?;
+    std::fs::write(scratch_dir.join("backend.tf"), HclFile::new().block(backend.clone()).to_string())?;
+
+    let terraform_manager = TerraformManager::new(&scratch_dir).with_quiet(true);
+    let workspaces = terraform_manager.init_reconfigure().and_then(|_| terraform_manager.workspace_list());
+    let _ = std::fs::remove_dir_all(&scratch_dir);
+    workspaces
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_local_backend_inventory() {
+        let temp_dir = TempDir::new().unwrap();
+        let state_root = temp_dir.path().join(".envie").join("state").join("ephemeral");
+        let write_state = |path: PathBuf| {
+            fs::create_dir_all(&path).unwrap();
+            fs::write(path.join("terraform.tfstate"), "{}").unwrap();
+        };
+        write_state(state_root.join("network").join("vpc").join("myapp-456"));
+        write_state(state_root.join("api").join("lambda").join("myapp-123"));
+        write_state(state_root.join("api").join("lambda").join("myapp-456"));
+
+        let inventory = EnvironmentInventory::new(
+            Vec::new(),
+            BackendConfig {
+                backend_type: "local".to_string(),
+                config: HashMap::new(),
+            },
+            temp_dir.path(),
+        );
+
+        assert_eq!(inventory.workspaces().unwrap(), &["myapp-123".to_string(), "myapp-456".to_string()]);
+
+        // The result is cached for the rest of the run
+        write_state(state_root.join("api").join("lambda").join("myapp-789"));
+        assert!(!inventory.contains("myapp-789").unwrap());
+    }
+
+    #[test]
+    fn test_backend_is_queried_without_initializing_modules() {
+        if which::which("terraform").is_err() {
+            return;
+        }
+        let temp_dir = TempDir::new().unwrap();
+        let workspace_dir = temp_dir.path().join("states");
+        for workspace in ["myapp-123", "myapp-456"] {
+            fs::create_dir_all(workspace_dir.join(workspace)).unwrap();
+        }
+
+        let backend = Block::new("terraform")
+            .block(Block::new("backend").label("local").attribute("workspace_dir", workspace_dir.to_string_lossy().as_ref()));
+        assert_eq!(backend_workspaces(&backend).unwrap(), ["default", "myapp-123", "myapp-456"]);
+    }
+
+    #[test]
+    fn test_backend_without_workspaces_has_none() {
+        let inventory = EnvironmentInventory::new(
+            vec![Block::new("terraform").block(Block::new("backend").label("http"))],
+            BackendConfig {
+                backend_type: "http".to_string(),
+                config: HashMap::new(),
+            },
+            ".",
+        );
+
+        assert!(inventory.workspaces().unwrap().is_empty());
+    }
+}
@@ -0,0 +1,369 @@
+use crate::common::*;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// What Envie deployed into one ephemeral workspace, kept at `.envie/environments/<workspace>.json`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EnvironmentManifest {
+    pub workspace: String,
+    pub merge_request: String,
+    /// Deployments by service name
+    pub services: BTreeMap<String, ServiceDeployment>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    /// Commit of the last deploy, if run from a git checkout
+    pub git_commit: Option<String>,
+    /// Envie version of the last deploy
+    pub envie_version: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ServiceDeployment {
+    /// `-E` overrides the service was deployed with
+    #[serde(default)]
+    pub environment_overrides: BTreeMap<String, String>,
+    /// Every module of the deployment closure, in dependency order
+    pub modules: Vec<ManifestModule>,
+    pub deployed_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ManifestModule {
+    /// `service/module` key
+    pub module: String,
+    /// Environment reference after overrides (ephemeral, stable.sandbox, ...)
+    pub environment: String,
+    /// Workspace the environment resolved to
+    pub workspace: String,
+    pub status: ModuleStatus,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ModuleStatus {
+    Deployed,
+    /// The apply failed, so the workspace may hold part of the module's resources
+    Failed,
+    /// Not applied because the run stopped or a dependency failed
+    NotDeployed,
+    /// Resolved to another environment and left untouched
+    External,
+}
+
+impl ModuleStatus {
+    /// Whether the module may own resources in the ephemeral workspace
+    pub fn has_resources(&self) -> bool {
+        matches!(self, ModuleStatus::Deployed | ModuleStatus::Failed)
+    }
+}
+
+impl ServiceDeployment {
+    /// The deployment of `plan`, given the status each module reached in this run. A module the
+    /// run did not reach keeps the resources (and status) of the previous deployment.
+    pub fn from_run(
+        plan: &DeploymentPlan,
+        statuses: &HashMap<String, ModuleStatus>,
+        environment_overrides: &HashMap<String, String>,
+        previous: Option<&ServiceDeployment>,
+    ) -> Self {
+        let previous_status = |module: &str| previous
+            .and_then(|deployment| deployment.modules.iter().find(|m| m.module == module))
+            .map(|m| m.status);
+
+        let modules = plan.modules.iter()
+            .map(|planned| {
+                let status = if !planned.deploy {
+                    ModuleStatus::External
+                } else {
+                    match (statuses.get(&planned.key).copied(), previous_status(&planned.key)) {
+                        (Some(ModuleStatus::NotDeployed) | None, Some(earlier)) if earlier.has_resources() => earlier,
+                        (Some(status), _) => status,
+                        (None, _) => ModuleStatus::NotDeployed,
+                    }
+                };
+                ManifestModule {
+                    module: planned.key.clone(),
+                    environment: planned.environment.clone(),
+                    workspace: planned.resolved.workspace.clone(),
+                    status,
+                }
+            })
+            .collect();
+
+        Self {
+            environment_overrides: environment_overrides.iter()
+                .map(|(key, value)| (key.clone(), value.clone()))
+                .collect(),
+            modules,
+            deployed_at: Utc::now(),
+        }
+    }
+}
+
+impl EnvironmentManifest {
+    pub fn new(workspace: &str, merge_request: &str) -> Self {
+        let now = Utc::now();
+        Self {
+            workspace: workspace.to_string(),
+            merge_request: merge_request.to_string(),
+            services: BTreeMap::new(),
+            created_at: now,
+            updated_at: now,
+            git_commit: None,
+            envie_version: env!("CARGO_PKG_VERSION").to_string(),
+        }
+    }
+
+    /// Record the deployment of a service, replacing any earlier deployment of it
+    pub fn record(&mut self, service: &str, deployment: ServiceDeployment, git_commit: Option<String>) {
+        self.services.insert(service.to_string(), deployment);
+        self.updated_at = Utc::now();
+        self.git_commit = git_commit;
+        self.envie_version = env!("CARGO_PKG_VERSION").to_string();
+    }
+
+    /// Services other than `service` whose deployment owns resources of `module`
+    pub fn other_owners(&self, module: &str, service: &str) -> Vec<&str> {
+        self.services.iter()
+            .filter(|(name, _)| name.as_str() != service)
+            .filter(|(_, deployment)| deployment.modules.iter()
+                .any(|m| m.module == module && m.status.has_resources()))
+            .map(|(name, _)| name.as_str())
+            .collect()
+    }
+}
+
+/// Manifests of every ephemeral environment of a project
+#[derive(Debug, Clone)]
+pub struct ManifestStore {
+    root: PathBuf,
+}
+
+impl ManifestStore {
+    pub fn new<P: AsRef<Path>>(project_root: P) -> Self {
+        Self {
+            root: project_root.as_ref().join(".envie").join("environments"),
+        }
+    }
+
+    pub fn path(&self, workspace: &str) -> PathBuf {
+        self.root.join(format!("{}.json", workspace))
+    }
+
+    pub fn load(&self, workspace: &str) -> Result<Option<EnvironmentManifest>> {
+        let path = self.path(workspace);
+        if !path.exists() {
+            return Ok(None);
+        }
+        let content = std::fs::read_to_string(&path)?;
+        let manifest = serde_json::from_str(&content).map_err(|e| EnvieError::ConfigError(
+            format!("Invalid environment manifest {}: {}", path.display(), e)
+        ))?;
+        Ok(Some(manifest))
+    }
+
+    pub fn save(&self, manifest: &EnvironmentManifest) -> Result<()> {
+        std::fs::create_dir_all(&self.root)?;
+        std::fs::write(self.path(&manifest.workspace), serde_json::to_string_pretty(manifest)?)?;
+        Ok(())
+    }
+
+    /// Save the manifest, or remove it once no service is deployed anymore
+    pub fn save_or_remove(&self, manifest: &EnvironmentManifest) -> Result<()> {
+        if manifest.services.is_empty() {
+            let path = self.path(&manifest.workspace);
+            if path.exists() {
+                std::fs::remove_file(path)?;
+            }
+            Ok(())
+        } else {
+            self.save(manifest)
+        }
+    }
+
+    /// The manifest of the only deployed environment; ambiguous when several are deployed
+    pub fn only(&self) -> Result<EnvironmentManifest> {
+        let mut manifests = self.list()?;
+        match manifests.len() {
+            0 => Err(EnvieError::ValidationError(
+                "No deployed environments found. Deploy one first or pass --merge-request.".to_string()
+            )),
+            1 => Ok(manifests.remove(0)),
+            _ => Err(EnvieError::ValidationError(format!(
+                "Several environments are deployed ({}). Pass --merge-request to pick one.",
+                manifests.iter().map(|m| m.merge_request.as_str()).collect::<Vec<_>>().join(", ")
+            ))),
+        }
+    }
+
+    /// Every manifest, sorted by workspace
+    pub fn list(&self) -> Result<Vec<EnvironmentManifest>> {
+        if !self.root.is_dir() {
+            return Ok(Vec::new());
+        }
+
+        let mut workspaces = Vec::new();
+        for entry in std::fs::read_dir(&self.root)? {
+            let path = entry?.path();
+            if path.extension().is_some_and(|ext| ext == "json") {
+                if let Some(workspace) = path.file_stem().and_then(|stem| stem.to_str()) {
+                    workspaces.push(workspace.to_string());
+                }
+            }
+        }
+        workspaces.sort();
+
+        let mut manifests = Vec::new();
+        for workspace in workspaces {
+            if let Some(manifest) = self.load(&workspace)? {
+                manifests.push(manifest);
+            }
+        }
+        Ok(manifests)
+    }
+}
+
+/// Commit checked out at `path`, if it is inside a git repository
+pub fn current_git_commit(path: &Path) -> Option<String> {
+    let output = Command::new("git")
+        .args(["rev-parse", "HEAD"])
+        .current_dir(path)
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let commit = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    (!commit.is_empty()).then_some(commit)
+}
+
+/// Branch checked out at `path`, if it is a git checkout on a branch
+pub fn current_git_branch(path: &Path) -> Option<String> {
+    let output = Command::new("git")
+        .args(["rev-parse", "--abbrev-ref", "HEAD"])
+        .current_dir(path)
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let branch = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    // A detached HEAD reports itself as "HEAD"
+    (!branch.is_empty() && branch != "HEAD").then_some(branch)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn deployment(modules: &[(&str, ModuleStatus)]) -> ServiceDeployment {
+        ServiceDeployment {
+            environment_overrides: BTreeMap::new(),
+            modules: modules.iter()
+                .map(|(module, status)| ManifestModule {
+                    module: module.to_string(),
+                    environment: "ephemeral".to_string(),
+                    workspace: "myapp-123".to_string(),
+                    status: *status,
+                })
+                .collect(),
+            deployed_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_manifest_round_trip_and_removal() {
+        let temp_dir = TempDir::new().unwrap();
+        let store = ManifestStore::new(temp_dir.path());
+        assert!(store.load("myapp-123").unwrap().is_none());
+
+        let mut manifest = EnvironmentManifest::new("myapp-123", "123");
+        manifest.record("api", deployment(&[("api/lambda", ModuleStatus::Deployed)]), Some("abc123".to_string()));
+        store.save_or_remove(&manifest).unwrap();
+
+        assert_eq!(store.path("myapp-123"), temp_dir.path().join(".envie/environments/myapp-123.json"));
+        let loaded = store.load("myapp-123").unwrap().unwrap();
+        assert_eq!(loaded.git_commit.as_deref(), Some("abc123"));
+        assert_eq!(loaded.services["api"].modules[0].status, ModuleStatus::Deployed);
+        assert_eq!(store.list().unwrap().len(), 1);
+        assert_eq!(store.only().unwrap().workspace, "myapp-123");
+
+        store.save(&EnvironmentManifest::new("myapp-456", "456")).unwrap();
+        assert!(store.only().unwrap_err().to_string().contains("123, 456"));
+        store.save_or_remove(&EnvironmentManifest::new("myapp-456", "456")).unwrap();
+
+        manifest.services.clear();
+        store.save_or_remove(&manifest).unwrap();
+        assert!(store.list().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_from_run_keeps_resources_of_modules_not_reached() {
+        let planned = |key: &str, deploy: bool| PlannedModule {
+            key: key.to_string(),
+            service: split_module_key(key).0.to_string(),
+            environment: if deploy { "ephemeral" } else { "stable.sandbox" }.to_string(),
+            resolved: ResolvedEnvironment {
+                workspace: if deploy { "myapp-123" } else { "sandbox" }.to_string(),
+                environment_type: if deploy {
+                    EnvironmentType::Ephemeral
+                } else {
+                    EnvironmentType::Stable("sandbox".to_string())
+                },
+                backend: crate::common::environment::BackendConfig {
+                    backend_type: "local".to_string(),
+                    config: HashMap::new(),
+                },
+            },
+            deploy,
+        };
+        let plan = DeploymentPlan {
+            modules: vec![
+                planned("database/dynamodb", false),
+                planned("api/lambda", true),
+                planned("api/gateway", true),
+            ],
+        };
+        let previous = deployment(&[("api/gateway", ModuleStatus::Deployed)]);
+
+        let mut statuses = HashMap::new();
+        statuses.insert("api/lambda".to_string(), ModuleStatus::Failed);
+        statuses.insert("api/gateway".to_string(), ModuleStatus::NotDeployed);
+        let mut overrides = HashMap::new();
+        overrides.insert("database".to_string(), "stable.sandbox".to_string());
+
+        let deployment = ServiceDeployment::from_run(&plan, &statuses, &overrides, Some(&previous));
+
+        let statuses: Vec<(&str, ModuleStatus)> = deployment.modules.iter()
+            .map(|m| (m.module.as_str(), m.status))
+            .collect();
+        assert_eq!(statuses, vec![
+            ("database/dynamodb", ModuleStatus::External),
+            ("api/lambda", ModuleStatus::Failed),
+            ("api/gateway", ModuleStatus::Deployed),
+        ]);
+        assert_eq!(deployment.modules[0].workspace, "sandbox");
+        assert_eq!(deployment.environment_overrides["database"], "stable.sandbox");
+    }
+
+    #[test]
+    fn test_other_owners() {
+        let mut manifest = EnvironmentManifest::new("myapp-123", "123");
+        manifest.record("api", deployment(&[
+            ("networking/vpc", ModuleStatus::Deployed),
+            ("api/lambda", ModuleStatus::Deployed),
+        ]), None);
+        manifest.record("web", deployment(&[
+            ("networking/vpc", ModuleStatus::Deployed),
+            ("web/site", ModuleStatus::NotDeployed),
+        ]), None);
+
+        assert_eq!(manifest.other_owners("networking/vpc", "api"), vec!["web"]);
+        assert!(manifest.other_owners("api/lambda", "api").is_empty());
+        assert!(manifest.other_owners("web/site", "api").is_empty());
+    }
+}
@@ -0,0 +1,438 @@
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// An HCL expression
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expression {
+    Null,
+    Bool(bool),
+    Number(serde_json::Number),
+    String(String),
+    List(Vec<Expression>),
+    /// Keys are written in sorted order
+    Object(BTreeMap<String, Expression>),
+    /// Written as is, e.g. `data.terraform_remote_state.vpc.outputs.vpc_id`
+    Reference(String),
+    /// A string written as a heredoc. Falls back to a quoted string unless it ends with a newline,
+    /// which a heredoc always does.
+    Heredoc(String),
+}
+
+impl From<&str> for Expression {
+    fn from(value: &str) -> Self {
+        Expression::String(value.to_string())
+    }
+}
+
+impl From<String> for Expression {
+    fn from(value: String) -> Self {
+        Expression::String(value)
+    }
+}
+
+impl From<bool> for Expression {
+    fn from(value: bool) -> Self {
+        Expression::Bool(value)
+    }
+}
+
+impl From<&serde_json::Value> for Expression {
+    fn from(value: &serde_json::Value) -> Self {
+        match value {
+            serde_json::Value::Null => Expression::Null,
+            serde_json::Value::Bool(value) => Expression::Bool(*value),
+            serde_json::Value::Number(value) => Expression::Number(value.clone()),
+            // e.g. a PEM certificate
+            serde_json::Value::String(value) if value.ends_with('\n') && value.trim_end().contains('\n') => {
+                Expression::Heredoc(value.clone())
+            }
+            serde_json::Value::String(value) => Expression::String(value.clone()),
+            serde_json::Value::Array(items) => Expression::List(items.iter().map(Expression::from).collect()),
+            serde_json::Value::Object(entries) => Expression::Object(
+                entries.iter().map(|(key, value)| (key.clone(), Expression::from(value))).collect()
+            ),
+        }
+    }
+}
+
+impl From<serde_json::Value> for Expression {
+    fn from(value: serde_json::Value) -> Self {
+        Expression::from(&value)
+    }
+}
+
+/// Attributes and nested blocks, written in the order they are added
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Body {
+    items: Vec<BodyItem>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum BodyItem {
+    Attribute(String, Expression),
+    Block(Block),
+    BlankLine,
+}
+
+impl Body {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn attribute<V: Into<Expression>>(mut self, name: &str, value: V) -> Self {
+        self.items.push(BodyItem::Attribute(name.to_string(), value.into()));
+        self
+    }
+
+    pub fn block(mut self, block: Block) -> Self {
+        self.items.push(BodyItem::Block(block));
+        self
+    }
+
+    pub fn blank_line(mut self) -> Self {
+        self.items.push(BodyItem::BlankLine);
+        self
+    }
+}
+
+/// A block such as `data "terraform_remote_state" "vpc" { ... }`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Block {
+    block_type: String,
+    labels: Vec<String>,
+    body: Body,
+}
+
+impl Block {
+    pub fn new(block_type: &str) -> Self {
+        Self {
+            block_type: block_type.to_string(),
+            labels: Vec::new(),
+            body: Body::new(),
+        }
+    }
+
+    pub fn label(mut self, label: &str) -> Self {
+        self.labels.push(label.to_string());
+        self
+    }
+
+    pub fn attribute<V: Into<Expression>>(mut self, name: &str, value: V) -> Self {
+        self.body = self.body.attribute(name, value);
+        self
+    }
+
+    pub fn block(mut self, block: Block) -> Self {
+        self.body = self.body.block(block);
+        self
+    }
+
+    pub fn blank_line(mut self) -> Self {
+        self.body = self.body.blank_line();
+        self
+    }
+}
+
+/// A Terraform file: leading `#` comments, then top-level blocks separated by blank lines.
+/// Renders the way `terraform fmt` would write it.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct HclFile {
+    comments: Vec<String>,
+    blocks: Vec<Block>,
+}
+
+impl HclFile {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn comment(mut self, text: &str) -> Self {
+        self.comments.push(text.to_string());
+        self
+    }
+
+    pub fn block(mut self, block: Block) -> Self {
+        self.blocks.push(block);
+        self
+    }
+
+    pub fn blocks<I: IntoIterator<Item = Block>>(mut self, blocks: I) -> Self {
+        self.blocks.extend(blocks);
+        self
+    }
+}
+
+impl fmt::Display for HclFile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for comment in &self.comments {
+            writeln!(f, "# {}", comment)?;
+        }
+
+        let mut lines = Vec::new();
+        for (index, block) in self.blocks.iter().enumerate() {
+            if index > 0 || !self.comments.is_empty() {
+                lines.push(Line::text(0, ""));
+            }
+            write_block(block, 0, &mut lines);
+        }
+        write_lines(f, &lines)
+    }
+}
+
+impl fmt::Display for Block {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut lines = Vec::new();
+        write_block(self, 0, &mut lines);
+        write_lines(f, &lines)
+    }
+}
+
+/// A rendered line. Consecutive single-line attributes have their `=` aligned, as `terraform fmt` does.
+struct Line {
+    indent: usize,
+    /// Name of a single-line attribute (or object key); `text` is then its value
+    key: Option<String>,
+    text: String,
+    /// Heredoc content, written without indentation
+    verbatim: bool,
+}
+
+impl Line {
+    fn text(indent: usize, text: &str) -> Self {
+        Self { indent, key: None, text: text.to_string(), verbatim: false }
+    }
+
+    fn attribute(indent: usize, key: String, value: String) -> Self {
+        Self { indent, key: Some(key), text: value, verbatim: false }
+    }
+}
+
+fn write_lines(f: &mut fmt::Formatter<'_>, lines: &[Line]) -> fmt::Result {
+    let mut start = 0;
+    while start < lines.len() {
+        // Heredoc content belongs to the attribute that opens it and does not break the chain
+        let chain = lines[start..].iter().take_while(|line| line.key.is_some() || line.verbatim).count().max(1);
+        let width = lines[start..start + chain].iter()
+            .filter_map(|line| line.key.as_ref().map(|key| key.chars().count()))
+            .max()
+            .unwrap_or(0);
+
+        for line in &lines[start..start + chain] {
+            let indent = "  ".repeat(line.indent);
+            match &line.key {
+                _ if line.verbatim => writeln!(f, "{}", line.text)?,
+                Some(key) => writeln!(f, "{}{:<width$} = {}", indent, key, line.text, width = width)?,
+                None if line.text.is_empty() => writeln!(f)?,
+                None => writeln!(f, "{}{}", indent, line.text)?,
+            }
+        }
+        start += chain;
+    }
+    Ok(())
+}
+
+fn write_block(block: &Block, indent: usize, lines: &mut Vec<Line>) {
+    let mut header = block.block_type.clone();
+    for label in &block.labels {
+        header.push(' ');
+        header.push_str(&quote(label));
+    }
+
+    if block.body.items.is_empty() {
+        lines.push(Line::text(indent, &format!("{} {{}}", header)));
+        return;
+    }
+
+    lines.push(Line::text(indent, &format!("{} {{", header)));
+    for item in &block.body.items {
+        match item {
+            BodyItem::Attribute(name, value) => write_attribute(name.clone(), value, indent + 1, lines),
+            BodyItem::Block(nested) => write_block(nested, indent + 1, lines),
+            BodyItem::BlankLine => lines.push(Line::text(indent + 1, "")),
+        }
+    }
+    lines.push(Line::text(indent, "}"));
+}
+
+fn write_attribute(key: String, value: &Expression, indent: usize, lines: &mut Vec<Line>) {
+    if let Some(inline) = inline_expression(value) {
+        lines.push(Line::attribute(indent, key, inline));
+        return;
+    }
+
+    match value {
+        Expression::Object(entries) => {
+            lines.push(Line::text(indent, &format!("{} = {{", key)));
+            for (name, value) in entries {
+                write_attribute(object_key(name), value, indent + 1, lines);
+            }
+            lines.push(Line::text(indent, "}"));
+        }
+        Expression::List(items) => {
+            lines.push(Line::text(indent, &format!("{} = [", key)));
+            for item in items {
+                write_list_item(item, indent + 1, lines);
+            }
+            lines.push(Line::text(indent, "]"));
+        }
+        Expression::Heredoc(content) => {
+            let delimiter = heredoc_delimiter(content);
+            lines.push(Line::attribute(indent, key, format!("<<{}", delimiter)));
+            for line in content.lines() {
+                lines.push(Line { verbatim: true, ..Line::text(0, &escape_template(line)) });
+            }
+            lines.push(Line { verbatim: true, ..Line::text(0, &delimiter) });
+        }
+        _ => unreachable!("scalar expressions are always inline"),
+    }
+}
+
+fn write_list_item(item: &Expression, indent: usize, lines: &mut Vec<Line>) {
+    if let Some(inline) = inline_expression(item) {
+        lines.push(Line::text(indent, &format!("{},", inline)));
+        return;
+    }
+
+    match item {
+        Expression::Object(entries) => {
+            lines.push(Line::text(indent, "{"));
+            for (name, value) in entries {
+                write_attribute(object_key(name), value, indent + 1, lines);
+            }
+            lines.push(Line::text(indent, "},"));
+        }
+        Expression::List(items) => {
+            lines.push(Line::text(indent, "["));
+            for item in items {
+                write_list_item(item, indent + 1, lines);
+            }
+            lines.push(Line::text(indent, "],"));
+        }
+        // A heredoc inside a list is written as a quoted string
+        Expression::Heredoc(content) => lines.push(Line::text(indent, &format!("{},", quote(content)))),
+        _ => unreachable!("scalar expressions are always inline"),
+    }
+}
+
+/// The expression on a single line, unless it needs several
+fn inline_expression(value: &Expression) -> Option<String> {
+    match value {
+        Expression::Null => Some("null".to_string()),
+        Expression::Bool(value) => Some(value.to_string()),
+        Expression::Number(value) => Some(value.to_string()),
+        Expression::String(value) => Some(quote(value)),
+        Expression::Reference(reference) => Some(reference.clone()),
+        Expression::Heredoc(content) if !content.ends_with('\n') => Some(quote(content)),
+        Expression::Heredoc(_) => None,
+        Expression::Object(entries) if entries.is_empty() => Some("{}".to_string()),
+        Expression::Object(_) => None,
+        Expression::List(items) => {
+            let items: Option<Vec<String>> = items.iter()
+                .map(|item| match item {
+                    Expression::Object(entries) if !entries.is_empty() => None,
+                    item => inline_expression(item),
+                })
+                .collect();
+            items.map(|items| format!("[{}]", items.join(", ")))
+        }
+    }
+}
+
+/// Quote a string, escaping it so Terraform reads it back verbatim (no interpolation)
+pub fn quote(value: &str) -> String {
+    let mut quoted = String::from("\"");
+    for c in escape_template(value).chars() {
+        match c {
+            '\\' => quoted.push_str("\\\\"),
+            '"' => quoted.push_str("\\\""),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c if c.is_control() => quoted.push_str(&format!("\\u{:04X}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// Double `${` and `%{` so they are not read as template sequences
+fn escape_template(value: &str) -> String {
+    value.replace("${", "$${").replace("%{", "%%{")
+}
+
+fn heredoc_delimiter(content: &str) -> String {
+    let mut delimiter = "EOT".to_string();
+    let mut suffix = 0;
+    while content.lines().any(|line| line.trim() == delimiter) {
+        suffix += 1;
+        delimiter = format!("EOT{}", suffix);
+    }
+    delimiter
+}
+
+fn object_key(key: &str) -> String {
+    if is_identifier(key) { key.to_string() } else { quote(key) }
+}
+
+/// Whether `name` can be written bare, as an attribute name or object key
+pub fn is_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars.next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::path::PathBuf;
+
+    /// Compare with `src/common/testdata/<name>`. Set ENVIE_UPDATE_GOLDEN=1 to rewrite the file instead.
+    pub(crate) fn assert_golden(name: &str, actual: &str) {
+        let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("src/common/testdata").join(name);
+        if std::env::var_os("ENVIE_UPDATE_GOLDEN").is_some() {
+            std::fs::write(&path, actual).unwrap();
+            return;
+        }
+        let expected = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(actual, expected, "output differs from {}", path.display());
+    }
+
+    #[test]
+    fn test_writer_golden() {
+        let file = HclFile::new()
+            .comment("Auto-generated by Envie - DO NOT EDIT")
+            .block(Block::new("terraform")
+                .block(Block::new("backend").label("s3")
+                    .attribute("bucket", "state")
+                    .attribute("encrypt", true)
+                    .attribute("max_retries", json!(5))
+                    .attribute("assume_role", json!({ "role_arn": "arn:aws:iam::1:role/x", "duration": "1h" }))
+                    .attribute("shared_config_files", json!(["~/.aws/config", "~/.aws/other"]))
+                    .attribute("workspace_key_prefix", "env")))
+            .block(Block::new("locals")
+                .attribute("quoted", "say \"hi\" to ${name} and %{ if x } C:\\temp\n")
+                .attribute("empty_map", json!({}))
+                .attribute("empty_list", json!([]))
+                .attribute("nothing", json!(null))
+                .attribute("odd_keys", json!({ "with space": 1, "1st": 2, "ok": 3 }))
+                .attribute("nested", json!([{ "a": 1 }, "b"]))
+                .blank_line()
+                .attribute("script", Expression::Heredoc("echo ${HOME}\nEOT\n".to_string()))
+                .attribute("certificate", json!("-----BEGIN CERTIFICATE-----\nMIIB\n-----END CERTIFICATE-----\n"))
+                .attribute("reference", Expression::Reference("data.terraform_remote_state.vpc.outputs.vpc_id".to_string())))
+            .block(Block::new("data").label("terraform_remote_state").label("empty"));
+
+        assert_golden("hcl/writer.tf", &file.to_string());
+    }
+
+    #[test]
+    fn test_quote_escapes() {
+        assert_eq!(quote("a\"b\\c"), r#""a\"b\\c""#);
+        assert_eq!(quote("${var.x} %{x} $x"), r#""$${var.x} %%{x} $x""#);
+        assert_eq!(quote("bell\u{7}"), r#""bell\u0007""#);
+        assert!(is_identifier("table_name-2"));
+        assert!(!is_identifier("2nd"));
+    }
+}
@@ -6,6 +6,23 @@ pub mod service_discovery;
 pub mod terraform_generator;
 pub mod terraform_scanner;
 pub mod environment;
+pub mod dependency_graph;
+pub mod deployment_plan;
+pub mod environment_inventory;
+pub mod deployment_context;
+pub mod plan_store;
+pub mod run_logs;
+pub mod module_executor;
+pub mod environment_manifest;
+pub mod backend;
+pub mod template;
+pub mod environment_id;
+pub mod config_validation;
+pub mod config_schema;
+pub mod variables;
+pub mod hcl;
+#[cfg(test)]
+pub mod test_support;
 
 pub use error::*;
 pub use terraform::*;
@@ -15,3 +32,18 @@ pub use service_discovery::*;
 pub use terraform_generator::*;
 pub use terraform_scanner::*;
 pub use environment::*;
+pub use dependency_graph::*;
+pub use deployment_plan::*;
+pub use environment_inventory::*;
+pub use deployment_context::*;
+pub use plan_store::*;
+pub use run_logs::*;
+pub use module_executor::*;
+pub use environment_manifest::*;
+pub use backend::*;
+pub use template::*;
+pub use environment_id::*;
+pub use config_validation::*;
+pub use config_schema::*;
+pub use variables::*;
+pub use hcl::{Block, Expression, HclFile};
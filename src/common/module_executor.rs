@@ -0,0 +1,361 @@
+use crate::common::*;
+use std::collections::{HashMap, HashSet};
+use std::panic::AssertUnwindSafe;
+use std::sync::mpsc;
+
+/// What to do with the remaining modules once one of them fails
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FailureMode {
+    /// Start nothing new, let running modules finish
+    FailFast,
+    /// Keep running every module that does not depend on a failed one
+    ContinueOnError,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct ExecutionOptions {
+    /// Maximum number of modules running at the same time
+    pub parallelism: usize,
+    pub failure_mode: FailureMode,
+}
+
+impl ExecutionOptions {
+    pub fn new(parallelism: usize, continue_on_error: bool) -> Self {
+        Self {
+            parallelism: parallelism.max(1),
+            failure_mode: if continue_on_error {
+                FailureMode::ContinueOnError
+            } else {
+                FailureMode::FailFast
+            },
+        }
+    }
+}
+
+impl Default for ExecutionOptions {
+    fn default() -> Self {
+        Self::new(1, false)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum ModuleOutcome<T> {
+    Succeeded(T),
+    Failed(String),
+    /// Not run, with the reason
+    Skipped(String),
+}
+
+/// Outcome of every module of a run, in the order the modules were given
+#[derive(Debug, Clone)]
+pub struct ExecutionSummary<T> {
+    pub results: Vec<(String, ModuleOutcome<T>)>,
+}
+
+impl<T> ExecutionSummary<T> {
+    /// Record a module that was left out of the run, e.g. a dependency in a stable environment
+    pub fn push_skipped(&mut self, module: &str, reason: &str) {
+        self.results.push((module.to_string(), ModuleOutcome::Skipped(reason.to_string())));
+    }
+
+    pub fn succeeded(&self) -> impl Iterator<Item = (&str, &T)> {
+        self.results.iter().filter_map(|(module, outcome)| match outcome {
+            ModuleOutcome::Succeeded(value) => Some((module.as_str(), value)),
+            _ => None,
+        })
+    }
+
+    pub fn failed(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.results.iter().filter_map(|(module, outcome)| match outcome {
+            ModuleOutcome::Failed(error) => Some((module.as_str(), error.as_str())),
+            _ => None,
+        })
+    }
+
+    pub fn skipped(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.results.iter().filter_map(|(module, outcome)| match outcome {
+            ModuleOutcome::Skipped(reason) => Some((module.as_str(), reason.as_str())),
+            _ => None,
+        })
+    }
+
+    pub fn print(&self, output_manager: &OutputManager) {
+        output_manager.print_green(&format!(
+            "Summary: {} succeeded, {} failed, {} skipped",
+            self.succeeded().count(),
+            self.failed().count(),
+            self.skipped().count()
+        ));
+        for (module, _) in self.succeeded() {
+            output_manager.print_green(&format!("  ✓ {}", module));
+        }
+        for (module, error) in self.failed() {
+            output_manager.print_red(&format!("  ✗ {}: {}", module, error));
+        }
+        for (module, reason) in self.skipped() {
+            output_manager.print_blue(&format!("  - {}: {}", module, reason));
+        }
+    }
+
+    /// An error naming the failed modules, if any failed
+    pub fn into_result(self) -> Result<Self> {
+        let failed: Vec<&str> = self.failed().map(|(module, _)| module).collect();
+        if failed.is_empty() {
+            Ok(self)
+        } else {
+            Err(EnvieError::TerraformError(format!(
+                "{} module(s) failed: {}", failed.len(), failed.join(", ")
+            )))
+        }
+    }
+}
+
+/// Runs a set of modules, each as soon as every module it waits for has succeeded, with at most
+/// `parallelism` modules running at once.
+#[derive(Debug, Clone)]
+pub struct ModuleExecutor {
+    /// Modules in topological order
+    modules: Vec<String>,
+    /// Module -> modules that must succeed before it starts
+    waits_for: HashMap<String, Vec<String>>,
+}
+
+impl ModuleExecutor {
+    /// Execute `modules` (in topological order) in dependency order. Only dependencies within
+    /// `modules` are waited for.
+    pub fn new(modules: Vec<String>, graph: &DependencyGraph) -> Self {
+        let members: HashSet<&String> = modules.iter().collect();
+        let waits_for = modules.iter()
+            .map(|module| {
+                let deps = graph.dependencies(module).iter()
+                    .filter(|edge| members.contains(&edge.target))
+                    .map(|edge| edge.target.clone())
+                    .collect();
+                (module.clone(), deps)
+            })
+            .collect();
+        Self { modules, waits_for }
+    }
+
+    /// Modules in the order they are started when running one at a time
+    pub fn order(&self) -> &[String] {
+        &self.modules
+    }
+
+    /// Execute dependents before their dependencies, e.g. to destroy
+    pub fn reversed(self) -> Self {
+        let mut waits_for: HashMap<String, Vec<String>> = self.modules.iter()
+            .map(|module| (module.clone(), Vec::new()))
+            .collect();
+        for module in &self.modules {
+            for dep in &self.waits_for[module] {
+                if let Some(dependents) = waits_for.get_mut(dep) {
+                    dependents.push(module.clone());
+                }
+            }
+        }
+        let mut modules = self.modules;
+        modules.reverse();
+        Self { modules, waits_for }
+    }
+
+    pub fn run<T, F>(&self, options: ExecutionOptions, task: F) -> ExecutionSummary<T>
+    where
+        T: Send,
+        F: Fn(&str) -> Result<T> + Sync,
+    {
+        let mut outcomes: HashMap<String, ModuleOutcome<T>> = HashMap::new();
+        let mut pending: Vec<&String> = self.modules.iter().collect();
+        let mut running = 0;
+        let mut first_failure: Option<String> = None;
+
+        std::thread::scope(|scope| {
+            let (sender, receiver) = mpsc::channel::<(String, Result<T>)>();
+
+            loop {
+                // Skip whatever can no longer run, start whatever is ready, in topological order
+                let mut index = 0;
+                while index < pending.len() {
+                    let module = pending[index];
+                    let deps = &self.waits_for[module];
+
+                    let blocked_by = deps.iter().find(|dep| matches!(
+                        outcomes.get(*dep),
+                        Some(ModuleOutcome::Failed(_)) | Some(ModuleOutcome::Skipped(_))
+                    ));
+                    if let Some(dep) = blocked_by {
+                        let reason = format!("dependency {} did not complete", dep);
+                        outcomes.insert(module.clone(), ModuleOutcome::Skipped(reason));
+                        pending.remove(index);
+                        continue;
+                    }
+                    if let Some(failed) = &first_failure {
+                        if options.failure_mode == FailureMode::FailFast {
+                            let reason = format!("not started after {} failed", failed);
+                            outcomes.insert(module.clone(), ModuleOutcome::Skipped(reason));
+                            pending.remove(index);
+                            continue;
+                        }
+                    }
+
+                    let ready = deps.iter()
+                        .all(|dep| matches!(outcomes.get(dep), Some(ModuleOutcome::Succeeded(_))));
+                    if ready && running < options.parallelism {
+                        pending.remove(index);
+                        running += 1;
+                        let sender = sender.clone();
+                        let task = &task;
+                        scope.spawn(move || {
+                            // Report a panic as a failure so the scheduler never waits forever
+                            let result = std::panic::catch_unwind(AssertUnwindSafe(|| task(module)))
+                                .unwrap_or_else(|_| Err(EnvieError::ProcessError(
+                                    format!("{} panicked", module)
+                                )));
+                            let _ = sender.send((module.clone(), result));
+                        });
+                        continue;
+                    }
+                    index += 1;
+                }
+
+                if running == 0 {
+                    break;
+                }
+
+                let (module, result) = receiver.recv().expect("module task ended without reporting");
+                running -= 1;
+                let outcome = match result {
+                    Ok(value) => ModuleOutcome::Succeeded(value),
+                    Err(error) => {
+                        first_failure.get_or_insert_with(|| module.clone());
+                        ModuleOutcome::Failed(error.to_string())
+                    }
+                };
+                outcomes.insert(module, outcome);
+            }
+        });
+
+        ExecutionSummary {
+            results: self.modules.iter()
+                .filter_map(|module| outcomes.remove(module).map(|outcome| (module.clone(), outcome)))
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::sync::Mutex;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+    use tempfile::TempDir;
+
+    /// vpc <- subnets <- lambda, vpc <- dynamodb
+    fn executor() -> (TempDir, ModuleExecutor) {
+        let temp_dir = TempDir::new().unwrap();
+        let service_dir = temp_dir.path().join("services").join("app");
+        fs::create_dir_all(&service_dir).unwrap();
+        fs::write(service_dir.join(".envie"), r#"
+name: app
+modules:
+  - name: vpc
+  - name: subnets
+    depends: [vpc]
+  - name: dynamodb
+    depends: [vpc]
+  - name: lambda
+    depends: [subnets]
+"#).unwrap();
+
+        let registry = ServiceRegistry::discover_from_path(temp_dir.path()).unwrap();
+        let graph = DependencyGraph::from_registry(&registry).unwrap();
+        let order = graph.order_for_service("app").unwrap();
+        (temp_dir, ModuleExecutor::new(order, &graph))
+    }
+
+    fn position(order: &[String], module: &str) -> usize {
+        order.iter().position(|m| m == module).unwrap()
+    }
+
+    #[test]
+    fn test_runs_independent_modules_concurrently_in_dependency_order() {
+        let (_temp_dir, executor) = executor();
+        let started = Mutex::new(Vec::new());
+        let running = AtomicUsize::new(0);
+        let max_running = AtomicUsize::new(0);
+
+        let summary = executor.run(ExecutionOptions::new(2, false), |module| {
+            started.lock().unwrap().push(module.to_string());
+            let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+            max_running.fetch_max(now, Ordering::SeqCst);
+            std::thread::sleep(Duration::from_millis(50));
+            running.fetch_sub(1, Ordering::SeqCst);
+            Ok(module.len())
+        });
+
+        let started = started.into_inner().unwrap();
+        assert_eq!(started[0], "app/vpc");
+        assert!(position(&started, "app/subnets") < position(&started, "app/lambda"));
+        assert_eq!(max_running.load(Ordering::SeqCst), 2);
+        assert_eq!(summary.succeeded().count(), 4);
+        assert!(summary.into_result().is_ok());
+    }
+
+    #[test]
+    fn test_continue_on_error_skips_only_dependents() {
+        let (_temp_dir, executor) = executor();
+
+        let summary = executor.run(ExecutionOptions::new(1, true), |module| {
+            if module == "app/subnets" {
+                Err(EnvieError::TerraformError("boom".to_string()))
+            } else {
+                Ok(())
+            }
+        });
+
+        let succeeded: Vec<&str> = summary.succeeded().map(|(module, _)| module).collect();
+        assert_eq!(succeeded, vec!["app/vpc", "app/dynamodb"]);
+        assert_eq!(summary.failed().collect::<Vec<_>>(), vec![("app/subnets", "Terraform error: boom")]);
+        assert_eq!(
+            summary.skipped().collect::<Vec<_>>(),
+            vec![("app/lambda", "dependency app/subnets did not complete")]
+        );
+        assert!(summary.into_result().is_err());
+    }
+
+    #[test]
+    fn test_fail_fast_starts_nothing_new() {
+        let (_temp_dir, executor) = executor();
+
+        let summary = executor.run(ExecutionOptions::new(1, false), |module| {
+            if module == "app/vpc" {
+                Err(EnvieError::TerraformError("boom".to_string()))
+            } else {
+                Ok(())
+            }
+        });
+
+        assert_eq!(summary.failed().count(), 1);
+        assert_eq!(summary.skipped().count(), 3);
+        assert_eq!(summary.succeeded().count(), 0);
+    }
+
+    #[test]
+    fn test_reversed_runs_dependents_first() {
+        let (_temp_dir, executor) = executor();
+        let started = Mutex::new(Vec::new());
+
+        executor.reversed().run(ExecutionOptions::default(), |module| {
+            started.lock().unwrap().push(module.to_string());
+            Ok(())
+        });
+
+        let started = started.into_inner().unwrap();
+        assert!(position(&started, "app/lambda") < position(&started, "app/subnets"));
+        assert!(position(&started, "app/subnets") < position(&started, "app/vpc"));
+        assert!(position(&started, "app/dynamodb") < position(&started, "app/vpc"));
+        assert_eq!(started.last().unwrap(), "app/vpc");
+    }
+}
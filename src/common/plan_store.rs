@@ -0,0 +1,177 @@
+use crate::common::*;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+/// Name of the file describing the plans saved for a workspace
+const PLAN_MANIFEST: &str = "plans.json";
+
+/// Location of saved Terraform plans: `.envie/plans/<workspace>/<service>/<module>.tfplan`
+#[derive(Debug, Clone)]
+pub struct PlanStore {
+    root: PathBuf,
+}
+
+/// The plans saved by the last `envie plan` of a workspace, in apply order
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SavedPlanSet {
+    pub workspace: String,
+    pub service: String,
+    /// `-E` overrides the plans were made with, recorded in the manifest once they are applied
+    #[serde(default)]
+    pub environment_overrides: HashMap<String, String>,
+    pub created_at: DateTime<Utc>,
+    pub modules: Vec<SavedPlan>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SavedPlan {
+    /// `service/module` key
+    pub module: String,
+    pub module_path: PathBuf,
+    pub plan_file: PathBuf,
+    /// Fingerprint of the module's files when the plan was made
+    pub fingerprint: String,
+}
+
+impl PlanStore {
+    pub fn new<P: AsRef<Path>>(project_root: P) -> Self {
+        Self {
+            root: project_root.as_ref().join(".envie").join("plans"),
+        }
+    }
+
+    pub fn workspace_dir(&self, workspace: &str) -> PathBuf {
+        self.root.join(workspace)
+    }
+
+    pub fn plan_path(&self, workspace: &str, service: &str, module: &str) -> PathBuf {
+        self.workspace_dir(workspace)
+            .join(service)
+            .join(format!("{}.tfplan", module))
+    }
+
+    /// Remove every plan saved for a workspace
+    pub fn clear(&self, workspace: &str) -> Result<()> {
+        let dir = self.workspace_dir(workspace);
+        if dir.exists() {
+            std::fs::remove_dir_all(dir)?;
+        }
+        Ok(())
+    }
+
+    pub fn save(&self, plan_set: &SavedPlanSet) -> Result<()> {
+        let dir = self.workspace_dir(&plan_set.workspace);
+        std::fs::create_dir_all(&dir)?;
+        std::fs::write(dir.join(PLAN_MANIFEST), serde_json::to_string_pretty(plan_set)?)?;
+        Ok(())
+    }
+
+    pub fn load(&self, workspace: &str) -> Result<SavedPlanSet> {
+        let manifest = self.workspace_dir(workspace).join(PLAN_MANIFEST);
+        if !manifest.exists() {
+            return Err(EnvieError::ValidationError(format!(
+                "No saved plans found for {}. Run 'envie plan' first.", workspace
+            )));
+        }
+        let content = std::fs::read_to_string(manifest)?;
+        Ok(serde_json::from_str(&content)?)
+    }
+}
+
+/// SHA-256 over the relative path and content of every file of a module, including the
+/// generated `.envie-backend.tf` and `.envie-remote-state.tf`. Terraform's working data and local state are ignored.
+pub fn module_fingerprint(module_path: &Path) -> Result<String> {
+    let mut files: Vec<PathBuf> = WalkDir::new(module_path)
+        .into_iter()
+        .filter_entry(|entry| {
+            let name = entry.file_name().to_string_lossy();
+            !(entry.file_type().is_dir() && (name == ".terraform" || name == "terraform.tfstate.d"))
+        })
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().is_file())
+        .filter(|entry| {
+            let name = entry.file_name().to_string_lossy();
+            !name.ends_with(".tfstate") && !name.ends_with(".tfstate.backup")
+        })
+        .map(|entry| entry.path().to_path_buf())
+        .collect();
+    files.sort();
+
+    let mut hasher = Sha256::new();
+    for file in files {
+        let relative = file.strip_prefix(module_path).unwrap_or(&file);
+        hasher.update(relative.to_string_lossy().as_bytes());
+        hasher.update([0u8]);
+        hasher.update(std::fs::read(&file)?);
+        hasher.update([0u8]);
+    }
+
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_plan_path_layout() {
+        let store = PlanStore::new("/project");
+        assert_eq!(
+            store.plan_path("myapp-123", "api", "lambda"),
+            PathBuf::from("/project/.envie/plans/myapp-123/api/lambda.tfplan")
+        );
+    }
+
+    #[test]
+    fn test_save_and_load_plan_set() {
+        let temp_dir = TempDir::new().unwrap();
+        let store = PlanStore::new(temp_dir.path());
+
+        assert!(store.load("myapp-123").is_err());
+
+        store.save(&SavedPlanSet {
+            workspace: "myapp-123".to_string(),
+            service: "api".to_string(),
+            environment_overrides: HashMap::new(),
+            created_at: Utc::now(),
+            modules: vec![SavedPlan {
+                module: "api/lambda".to_string(),
+                module_path: PathBuf::from("services/api/modules/lambda"),
+                plan_file: store.plan_path("myapp-123", "api", "lambda"),
+                fingerprint: "abc".to_string(),
+            }],
+        }).unwrap();
+
+        let loaded = store.load("myapp-123").unwrap();
+        assert_eq!(loaded.service, "api");
+        assert_eq!(loaded.modules[0].module, "api/lambda");
+
+        store.clear("myapp-123").unwrap();
+        assert!(store.load("myapp-123").is_err());
+    }
+
+    #[test]
+    fn test_module_fingerprint_tracks_sources_and_generated_files() {
+        let temp_dir = TempDir::new().unwrap();
+        let module = temp_dir.path();
+        fs::write(module.join("main.tf"), "resource \"null_resource\" \"a\" {}\n").unwrap();
+        fs::write(module.join(".envie-remote-state.tf"), "# generated\n").unwrap();
+
+        let original = module_fingerprint(module).unwrap();
+
+        // Terraform working data does not affect the fingerprint
+        fs::create_dir_all(module.join(".terraform")).unwrap();
+        fs::write(module.join(".terraform").join("environment"), "myapp-123").unwrap();
+        fs::write(module.join("terraform.tfstate"), "{}").unwrap();
+        assert_eq!(module_fingerprint(module).unwrap(), original);
+
+        fs::write(module.join(".envie-remote-state.tf"), "# regenerated\n").unwrap();
+        assert_ne!(module_fingerprint(module).unwrap(), original);
+    }
+}
@@ -0,0 +1,49 @@
+use chrono::Utc;
+use std::path::{Path, PathBuf};
+use uuid::Uuid;
+
+/// Log directory of one Envie run: `.envie/logs/<run-id>/<service>/<module>.log`
+#[derive(Debug, Clone)]
+pub struct RunLogs {
+    dir: PathBuf,
+}
+
+impl RunLogs {
+    pub fn new<P: AsRef<Path>>(project_root: P) -> Self {
+        // Sortable by start time, unique across concurrent runs
+        let run_id = format!(
+            "{}-{}",
+            Utc::now().format("%Y%m%dT%H%M%SZ"),
+            &Uuid::new_v4().simple().to_string()[..8]
+        );
+        Self {
+            dir: project_root.as_ref().join(".envie").join("logs").join(run_id),
+        }
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Log file of a `service/module` key
+    pub fn module_log(&self, module_key: &str) -> PathBuf {
+        self.dir.join(format!("{}.log", module_key))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_module_log_layout() {
+        let logs = RunLogs::new("/project");
+        assert!(logs.dir().starts_with("/project/.envie/logs"));
+        assert_eq!(logs.dir().parent().unwrap(), Path::new("/project/.envie/logs"));
+        assert_eq!(
+            logs.module_log("api/lambda"),
+            logs.dir().join("api").join("lambda.log")
+        );
+        assert_ne!(RunLogs::new("/project").dir(), logs.dir());
+    }
+}
@@ -1,4 +1,6 @@
 use crate::common::Result;
+use crate::common::config_schema::{object_schema, read_config, variables_schema, ConfigKind, ConfigSchema};
+use crate::common::environment::EnvironmentsConfig;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::path::Path;
@@ -15,6 +17,10 @@ pub struct ServiceConfig {
     
     #[serde(default)]
     pub depends: Vec<String>,
+    
+    /// Terraform variables for every module of the service
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub variables: HashMap<String, serde_json::Value>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -29,14 +35,63 @@ pub struct ModuleConfig {
     
     #[serde(default)]
     pub depends: Vec<DependencyReference>,
+    
+    /// Remote states exposed to the module as `local.<name>.<output>`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub remote_states: Vec<RemoteStateReference>,
+    
+    /// Terraform variables of the module, over the service's
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub variables: HashMap<String, serde_json::Value>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(from = "DependencyReferenceSpec")]
 pub struct DependencyReference {
     pub path: String,  // Path like "../database/modules/dynamodb" or "database.dynamodb"
     pub environment: String,  // stable.sandbox, ephemeral, ephemeral.123, or direct workspace
 }
 
+/// Accepts both `depends: [lambda]` and `depends: [{ path: ..., environment: ... }]`
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum DependencyReferenceSpec {
+    Short(String),
+    Full {
+        path: String,
+        #[serde(default = "default_dependency_environment")]
+        environment: String,
+    },
+}
+
+fn default_dependency_environment() -> String {
+    "ephemeral".to_string()
+}
+
+impl From<DependencyReferenceSpec> for DependencyReference {
+    fn from(spec: DependencyReferenceSpec) -> Self {
+        match spec {
+            DependencyReferenceSpec::Short(path) => DependencyReference {
+                path,
+                environment: default_dependency_environment(),
+            },
+            DependencyReferenceSpec::Full { path, environment } => DependencyReference { path, environment },
+        }
+    }
+}
+
+/// A named remote state of another module, limited to the outputs it lists
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RemoteStateReference {
+    /// Name of the data source and of the local holding the outputs
+    pub name: String,
+    /// Module to read, as a dependency path
+    pub source: String,
+    #[serde(default = "default_dependency_environment")]
+    pub environment: String,
+    pub outputs: Vec<String>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct WorkspaceConfig {
     pub version: String,
@@ -44,11 +99,18 @@ pub struct WorkspaceConfig {
     #[serde(default)]
     pub project: Option<ProjectInfo>,
     
+    #[serde(default)]
+    pub environments: Option<EnvironmentsConfig>,
+    
     #[serde(default)]
     pub services: Vec<ServiceDiscovery>,
     
     #[serde(default)]
     pub defaults: HashMap<String, serde_json::Value>,
+    
+    /// Reject unknown keys in every config file instead of ignoring them
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub strict: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -65,12 +127,149 @@ pub struct ServiceDiscovery {
     pub name: Option<String>,
 }
 
+impl ConfigSchema for WorkspaceConfig {
+    fn schema() -> serde_json::Value {
+        object_schema(
+            "Root configuration of an Envie project",
+            serde_json::json!({
+                "version": { "type": "string" },
+                "project": ProjectInfo::schema(),
+                "environments": EnvironmentsConfig::schema(),
+                "services": {
+                    "type": "array",
+                    "description": "Service directories, relative to the project root",
+                    "items": ServiceDiscovery::schema(),
+                },
+                "defaults": {
+                    "type": "object",
+                    "properties": {
+                        "variables": variables_schema("Terraform variables for every module, overridden by every other level"),
+                    },
+                },
+                "strict": {
+                    "type": "boolean",
+                    "description": "Reject unknown keys in every config file instead of ignoring them",
+                    "default": false,
+                },
+            }),
+            &["version"],
+        )
+    }
+}
+
+impl ConfigSchema for ProjectInfo {
+    fn schema() -> serde_json::Value {
+        object_schema(
+            "The project",
+            serde_json::json!({
+                "name": { "type": "string", "description": "Used as {project} in naming patterns" },
+                "description": { "type": "string" },
+            }),
+            &["name"],
+        )
+    }
+}
+
+impl ConfigSchema for ServiceDiscovery {
+    fn schema() -> serde_json::Value {
+        object_schema(
+            "A service",
+            serde_json::json!({
+                "path": { "type": "string", "description": "Directory holding the service's .envie" },
+                "name": { "type": "string" },
+            }),
+            &["path"],
+        )
+    }
+}
+
+impl ConfigSchema for ServiceConfig {
+    fn schema() -> serde_json::Value {
+        object_schema(
+            "A service and its Terraform modules",
+            serde_json::json!({
+                "name": { "type": "string" },
+                "description": { "type": "string" },
+                "modules": { "type": "array", "items": ModuleConfig::schema() },
+                "depends": {
+                    "type": "array",
+                    "description": "Services every module of this one depends on, e.g. ../networking",
+                    "items": { "type": "string" },
+                },
+                "variables": variables_schema("Terraform variables for every module of the service"),
+            }),
+            &["name"],
+        )
+    }
+}
+
+impl ConfigSchema for ModuleConfig {
+    fn schema() -> serde_json::Value {
+        object_schema(
+            "A Terraform module",
+            serde_json::json!({
+                "name": { "type": "string" },
+                "description": { "type": "string" },
+                "path": { "type": "string", "description": "Module directory, relative to the service. Defaults to modules/<name>" },
+                "depends": { "type": "array", "items": DependencyReference::schema() },
+                "remote_states": { "type": "array", "items": RemoteStateReference::schema() },
+                "variables": variables_schema("Terraform variables of the module, over the service's"),
+            }),
+            &["name"],
+        )
+    }
+}
+
+impl ConfigSchema for DependencyReference {
+    fn schema() -> serde_json::Value {
+        serde_json::json!({
+            "description": "A module this one reads outputs from",
+            "oneOf": [
+                { "type": "string", "description": "Module path, deployed to the same ephemeral environment" },
+                object_schema(
+                    "Module path and the environment to read it from",
+                    serde_json::json!({
+                        "path": { "type": "string" },
+                        "environment": {
+                            "type": "string",
+                            "description": "ephemeral, ephemeral.<id> or stable.<name>",
+                            "default": default_dependency_environment(),
+                        },
+                    }),
+                    &["path"],
+                ),
+            ],
+        })
+    }
+}
+
+impl ConfigSchema for RemoteStateReference {
+    fn schema() -> serde_json::Value {
+        object_schema(
+            "The outputs of another module, exposed as local.<name>.<output>",
+            serde_json::json!({
+                "name": { "type": "string", "description": "Name of the data source and the local" },
+                "source": { "type": "string", "description": "Module path, e.g. ../../database/modules/dynamodb" },
+                "environment": {
+                    "type": "string",
+                    "description": "ephemeral, ephemeral.<id> or stable.<name>",
+                    "default": default_dependency_environment(),
+                },
+                "outputs": {
+                    "type": "array",
+                    "description": "Outputs of the source module to expose",
+                    "items": { "type": "string" },
+                },
+            }),
+            &["name", "source", "outputs"],
+        )
+    }
+}
+
 impl ServiceConfig {
-    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
-        let content = std::fs::read_to_string(path)?;
-        let config: ServiceConfig = serde_yaml::from_str(&content)
-            .map_err(|e| crate::common::EnvieError::ConfigError(format!("Failed to parse service config: {}", e)))?;
-        Ok(config)
+    /// Parse a service `.envie`. `strict` comes from workspace.envie.
+    pub fn from_file<P: AsRef<Path>>(path: P, strict: bool) -> Result<Self> {
+        read_config(path.as_ref(), ConfigKind::Service, strict)
     }
     
     pub fn from_str(content: &str) -> Result<Self> {
@@ -81,11 +280,9 @@ impl ServiceConfig {
 }
 
 impl ModuleConfig {
-    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
-        let content = std::fs::read_to_string(path)?;
-        let config: ModuleConfig = serde_yaml::from_str(&content)
-            .map_err(|e| crate::common::EnvieError::ConfigError(format!("Failed to parse module config: {}", e)))?;
-        Ok(config)
+    /// Parse a module `.envie`. `strict` comes from workspace.envie.
+    pub fn from_file<P: AsRef<Path>>(path: P, strict: bool) -> Result<Self> {
+        read_config(path.as_ref(), ConfigKind::Module, strict)
     }
     
     pub fn from_str(content: &str) -> Result<Self> {
@@ -96,10 +293,12 @@ impl ModuleConfig {
 }
 
 impl WorkspaceConfig {
+    /// Parse workspace.envie, rejecting unknown keys when it sets `strict`
     pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
-        let content = std::fs::read_to_string(path)?;
-        let config: WorkspaceConfig = serde_yaml::from_str(&content)
-            .map_err(|e| crate::common::EnvieError::ConfigError(format!("Failed to parse workspace config: {}", e)))?;
+        let config: WorkspaceConfig = read_config(path.as_ref(), ConfigKind::Workspace, false)?;
+        if config.strict {
+            return read_config(path.as_ref(), ConfigKind::Workspace, true);
+        }
         Ok(config)
     }
     
@@ -113,6 +312,8 @@ impl WorkspaceConfig {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::common::test_support::{assert_schema_matches_fields, schema_properties};
+    use std::collections::BTreeSet;
 
     #[test]
     fn test_service_config_parsing() {
@@ -127,7 +328,7 @@ modules:
     remote_states:
       - name: db
         source: ../database/modules/dynamodb
-        workspace: sandbox
+        environment: stable.sandbox
         outputs: [table_name, table_arn]
   
   - name: gateway
@@ -149,6 +350,15 @@ depends:
         assert_eq!(config.depends.len(), 2);
         assert!(config.depends.contains(&"../database".to_string()));
         assert!(config.depends.contains(&"../networking".to_string()));
+
+        let gateway_depends = &config.modules[1].depends;
+        assert_eq!(gateway_depends[0].path, "lambda");
+        assert_eq!(gateway_depends[0].environment, "ephemeral");
+
+        let db = &config.modules[0].remote_states[0];
+        assert_eq!(db.environment, "stable.sandbox");
+        assert_eq!(db.outputs, vec!["table_name", "table_arn"]);
+        assert_eq!(config.modules[1].remote_states[0].environment, "ephemeral");
     }
 
     #[test]
@@ -173,5 +383,59 @@ defaults:
         assert_eq!(config.version, "1.0");
         assert_eq!(config.services.len(), 3);
         assert_eq!(config.defaults.get("region").unwrap(), "eu-west-1");
+        assert!(config.environments.is_none());
+    }
+
+    #[test]
+    fn test_workspace_config_environments_parsing() {
+        let yaml = r#"
+version: "1.0"
+project:
+  name: my-project
+
+environments:
+  ephemeral:
+    naming_pattern: "{project}-{id}"
+    backend:
+      type: s3
+      config:
+        bucket: terraform-state-ephemeral
+        region: eu-west-1
+  stable:
+    sandbox:
+      workspace: sandbox
+      backend:
+        type: s3
+        config:
+          bucket: terraform-state-stable
+          region: eu-west-1
+      description: Shared sandbox
+
+services:
+  - path: services/api
+"#;
+
+        let config: WorkspaceConfig = serde_yaml::from_str(yaml).unwrap();
+        let environments = config.environments.unwrap();
+        assert_eq!(environments.ephemeral.backend.backend_type, "s3");
+        assert_eq!(environments.stable["sandbox"].workspace, "sandbox");
+    }
+
+    #[test]
+    fn test_schemas_match_fields() {
+        assert_schema_matches_fields::<WorkspaceConfig>();
+        assert_schema_matches_fields::<ProjectInfo>();
+        assert_schema_matches_fields::<ServiceDiscovery>();
+        assert_schema_matches_fields::<ServiceConfig>();
+        assert_schema_matches_fields::<ModuleConfig>();
+        assert_schema_matches_fields::<RemoteStateReference>();
+
+        // Dependencies are read through an untagged enum; compare the object form with what is written back
+        let written = serde_json::to_value(DependencyReference {
+            path: "networking/vpc".to_string(),
+            environment: "ephemeral".to_string(),
+        }).unwrap();
+        let written: BTreeSet<&str> = written.as_object().unwrap().keys().map(|key| key.as_str()).collect();
+        assert_eq!(schema_properties(&DependencyReference::schema()["oneOf"][1]), written);
     }
 }
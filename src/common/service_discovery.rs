@@ -20,6 +20,8 @@ pub struct DiscoveredModule {
 pub struct ServiceRegistry {
     pub services: HashMap<String, DiscoveredService>,
     pub modules: HashMap<String, DiscoveredModule>,
+    /// Directory the services were discovered from
+    pub root: PathBuf,
 }
 
 impl ServiceRegistry {
@@ -43,23 +45,24 @@ impl ServiceRegistry {
             Self::auto_discover_services(root_path)?
         };
         
+        let strict = workspace_config.as_ref().is_some_and(|config| config.strict);
         for service_path in service_paths {
-            if let Ok(service) = Self::discover_service(&service_path) {
-                let service_name = service.config.name.clone();
-                services.insert(service_name.clone(), service.clone());
-                
-                // Register modules
-                for module in &service.modules {
-                    let module_name = format!("{}/{}", service_name, module.config.name);
-                    modules.insert(module_name, module.clone());
-                }
+            // A broken service config is an error, not a missing service
+            let service = Self::discover_service(&service_path, strict)?;
+            let service_name = service.config.name.clone();
+            
+            // Register modules
+            for module in &service.modules {
+                let module_name = format!("{}/{}", service_name, module.config.name);
+                modules.insert(module_name, module.clone());
             }
+            services.insert(service_name, service);
         }
         
-        Ok(ServiceRegistry { services, modules })
+        Ok(ServiceRegistry { services, modules, root: root_path.to_path_buf() })
     }
     
-    fn find_workspace_config<P: AsRef<Path>>(root_path: P) -> Result<Option<WorkspaceConfig>> {
+    pub fn find_workspace_config<P: AsRef<Path>>(root_path: P) -> Result<Option<WorkspaceConfig>> {
         let root_path = root_path.as_ref();
         
         // Try workspace.envie first
@@ -77,6 +80,14 @@ impl ServiceRegistry {
         Ok(None)
     }
     
+    /// Nearest directory at or above `start` holding a workspace.envie or .envie.yaml
+    pub fn find_project_root<P: AsRef<Path>>(start: P) -> Option<PathBuf> {
+        start.as_ref()
+            .ancestors()
+            .find(|dir| dir.join("workspace.envie").is_file() || dir.join(".envie.yaml").is_file())
+            .map(|dir| dir.to_path_buf())
+    }
+    
     fn auto_discover_services<P: AsRef<Path>>(root_path: P) -> Result<Vec<PathBuf>> {
         let mut service_paths = Vec::new();
         
@@ -95,7 +106,7 @@ impl ServiceRegistry {
         Ok(service_paths)
     }
     
-    fn discover_service<P: AsRef<Path>>(service_path: P) -> Result<DiscoveredService> {
+    fn discover_service<P: AsRef<Path>>(service_path: P, strict: bool) -> Result<DiscoveredService> {
         let service_path = service_path.as_ref();
         let config_path = service_path.join(".envie");
         
@@ -105,7 +116,7 @@ impl ServiceRegistry {
             ));
         }
         
-        let config = ServiceConfig::from_file(config_path)?;
+        let config = ServiceConfig::from_file(config_path, strict)?;
         let mut modules = Vec::new();
         
         // Discover modules within this service
@@ -119,7 +130,7 @@ impl ServiceRegistry {
             // Look for module-specific .envie file
             let module_envie_path = module_path.join(".envie");
             let module_config = if module_envie_path.exists() {
-                ModuleConfig::from_file(module_envie_path)?
+                ModuleConfig::from_file(module_envie_path, strict)?
             } else {
                 module_config.clone()
             };
@@ -157,112 +168,161 @@ impl ServiceRegistry {
         None
     }
     
-    pub fn find_module_by_path<P: AsRef<Path>>(&self, path: P) -> Option<&DiscoveredModule> {
+    /// The module whose directory is `path`, with its `service/module` key. Paths are compared
+    /// after resolving `.`, `..` and symlinks.
+    pub fn find_module_by_path<P: AsRef<Path>>(&self, path: P) -> Option<(&str, &DiscoveredModule)> {
+        let path = self.canonical_path(path.as_ref());
+        
+        self.modules.iter()
+            .find(|(_, module)| self.canonical_path(&module.path) == path)
+            .map(|(key, module)| (key.as_str(), module))
+    }
+    
+    /// The module whose directory contains `path`, with its `service/module` key. The deepest
+    /// module wins when module directories are nested.
+    pub fn find_module_containing<P: AsRef<Path>>(&self, path: P) -> Option<(&str, &DiscoveredModule)> {
         let path = path.as_ref();
         
-        for module in self.modules.values() {
-            if module.path == path {
-                return Some(module);
-            }
+        self.modules.iter()
+            .filter(|(_, module)| path.starts_with(&module.path))
+            .max_by_key(|(_, module)| module.path.components().count())
+            .map(|(key, module)| (key.as_str(), module))
+    }
+    
+    /// A module by `service/module` key, or by name when it is unique (within `service`, if given)
+    pub fn find_module(&self, reference: &str, service: Option<&str>) -> Result<(&str, &DiscoveredModule)> {
+        if let Some((key, module)) = self.modules.get_key_value(reference) {
+            return Ok((key.as_str(), module));
+        }
+
+        let mut candidates: Vec<(&str, &DiscoveredModule)> = self.modules.iter()
+            .filter(|(key, _)| {
+                let (module_service, module_name) = split_module_key(key);
+                module_name == reference && service.is_none_or(|service| module_service == service)
+            })
+            .map(|(key, module)| (key.as_str(), module))
+            .collect();
+        candidates.sort_by_key(|(key, _)| *key);
+
+        match candidates.len() {
+            1 => Ok(candidates[0]),
+            0 => Err(EnvieError::ValidationError(format!("Module '{}' not found", reference))),
+            _ => Err(EnvieError::ValidationError(format!(
+                "Module name '{}' is ambiguous: {}. Use service/module or --service",
+                reference,
+                candidates.iter().map(|(key, _)| *key).collect::<Vec<_>>().join(", ")
+            ))),
         }
-        
-        None
     }
     
-    pub fn resolve_dependencies(&self, service_name: &str) -> Result<Vec<String>> {
-        let mut visited = std::collections::HashSet::new();
-        let mut recursion_stack = std::collections::HashSet::new();
-        let mut deployment_order = Vec::new();
-        
+    /// Resolve a service-level `depends` entry of `service_name` to the name of the service it
+    /// refers to: a service name, or a path to the service directory relative to the directory
+    /// of `service_name` (`../database`).
+    pub fn resolve_dependency_name(&self, dep_path: &str, service_name: &str) -> Result<String> {
+        let mut considered = Vec::new();
+
+        if !dep_path.contains('/') {
+            if self.services.contains_key(dep_path) {
+                return Ok(dep_path.to_string());
+            }
+            considered.push(dep_path.to_string());
+        }
+
         if let Some(service) = self.services.get(service_name) {
-            self.resolve_service_dependencies_recursive(
-                service,
-                &mut visited,
-                &mut recursion_stack,
-                &mut deployment_order,
-            )?;
-        } else {
-            return Err(EnvieError::ValidationError(
-                format!("Service '{}' not found", service_name)
-            ));
+            let path = self.canonical_path(&service.path.join(dep_path));
+            let found = self.services.iter()
+                .find(|(_, candidate)| self.canonical_path(&candidate.path) == path);
+            if let Some((name, _)) = found {
+                return Ok(name.clone());
+            }
+            considered.push(self.display_path(&path));
         }
-        
-        Ok(deployment_order)
+
+        Err(EnvieError::DependencyError(format!(
+            "Dependency '{}' of service '{}' does not match any discovered service. Considered: {}",
+            dep_path, service_name, considered.join(", ")
+        )))
     }
     
-    fn resolve_service_dependencies_recursive(
-        &self,
-        service: &DiscoveredService,
-        visited: &mut std::collections::HashSet<String>,
-        recursion_stack: &mut std::collections::HashSet<String>,
-        deployment_order: &mut Vec<String>,
-    ) -> Result<()> {
-        // Check for cyclic dependencies
-        if recursion_stack.contains(&service.config.name) {
-            return Err(EnvieError::DependencyError(
-                format!("Cyclic dependency detected involving service {}", service.config.name)
+    /// Resolve a module dependency reference of module `from` (a `service/module` key) to the
+    /// `service/module` key of the module it refers to.
+    ///
+    /// Accepts `service/module` and `service.module` keys, the name of a module of the same
+    /// service, and paths to the module directory. Paths are tried relative to the directory of
+    /// `from`, the directory holding it (`./lambda`, `../../database/modules/dynamodb`) and the
+    /// service directory (`../database/modules/dynamodb`), in that order.
+    pub fn resolve_module_reference(&self, reference: &str, from: &str) -> Result<String> {
+        if reference.trim().is_empty() {
+            return Err(EnvieError::ValidationError(
+                format!("Invalid dependency path: '{}'", reference)
             ));
         }
-        
-        // Skip if already visited
-        if visited.contains(&service.config.name) {
-            return Ok(());
+
+        let (service_name, _) = split_module_key(from);
+        let mut considered = Vec::new();
+
+        let key = if reference.starts_with('.') || reference.starts_with('/') {
+            None
+        } else {
+            match reference.split('/').collect::<Vec<_>>().as_slice() {
+                [name] => Some(match name.split_once('.') {
+                    Some((service, module)) => format!("{}/{}", service, module),
+                    None => format!("{}/{}", service_name, name),
+                }),
+                [service, module] => Some(format!("{}/{}", service, module)),
+                _ => None,
+            }
+        };
+        if let Some(key) = key {
+            if self.modules.contains_key(&key) {
+                return Ok(key);
+            }
+            considered.push(key);
         }
-        
-        // Mark as visited and add to recursion stack
-        visited.insert(service.config.name.clone());
-        recursion_stack.insert(service.config.name.clone());
-        
-        // Resolve service dependencies first
-        for dep_path in &service.config.depends {
-            let dep_name = self.resolve_dependency_name(dep_path, &service.path)?;
-            if let Some(dep_service) = self.services.get(&dep_name) {
-                self.resolve_service_dependencies_recursive(
-                    dep_service,
-                    visited,
-                    recursion_stack,
-                    deployment_order,
-                )?;
+
+        if let Some(module) = self.modules.get(from) {
+            let service_path = self.services.get(service_name).map(|service| service.path.as_path());
+            for base in [Some(module.path.as_path()), module.path.parent(), service_path].into_iter().flatten() {
+                let path = self.canonical_path(&base.join(reference));
+                if let Some((key, _)) = self.find_module_by_path(&path) {
+                    return Ok(key.to_string());
+                }
+                let shown = self.display_path(&path);
+                if !considered.contains(&shown) {
+                    considered.push(shown);
+                }
             }
         }
-        
-        // Remove from recursion stack and add to deployment order
-        recursion_stack.remove(&service.config.name);
-        deployment_order.push(service.config.name.clone());
-        
-        Ok(())
+
+        Err(EnvieError::DependencyError(format!(
+            "Dependency '{}' of {} does not match any discovered module. Considered: {}",
+            reference, from, considered.join(", ")
+        )))
     }
-    
-    fn resolve_dependency_name(&self, dep_path: &str, current_path: &Path) -> Result<String> {
-        if dep_path.starts_with("../") {
-            // For relative paths like "../networking", we need to find the service
-            // by looking at the directory name after resolving the path
-            let resolved_path = current_path.parent()
-                .ok_or_else(|| EnvieError::ValidationError("Invalid relative path".to_string()))?
-                .join(dep_path);
-            
-            // Extract the service name from the path (last component)
-            let service_name = resolved_path.file_name()
-                .and_then(|name| name.to_str())
-                .ok_or_else(|| EnvieError::ValidationError("Invalid service name in path".to_string()))?;
-            
-            // Check if this service exists
-            if self.services.contains_key(service_name) {
-                return Ok(service_name.to_string());
+
+    /// `path` made absolute with `.` and `..` resolved, and symlinks resolved as far as it exists
+    fn canonical_path(&self, path: &Path) -> PathBuf {
+        let absolute = std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf());
+        let normalized = self.normalize_path(&absolute);
+        
+        for existing in normalized.ancestors() {
+            if let Ok(canonical) = existing.canonicalize() {
+                return match normalized.strip_prefix(existing) {
+                    Ok(rest) => canonical.join(rest),
+                    Err(_) => canonical,
+                };
             }
-            
-            Err(EnvieError::ValidationError(
-                format!("Dependency '{}' not found - service '{}' does not exist", dep_path, service_name)
-            ))
-        } else if dep_path.contains("/") {
-            // service/module format
-            Ok(dep_path.to_string())
-        } else {
-            // Just service name
-            Ok(dep_path.to_string())
         }
+        
+        normalized
     }
-    
+
+    /// A path for messages, relative to the root the registry was discovered from
+    fn display_path(&self, path: &Path) -> String {
+        let root = self.canonical_path(&self.root);
+        path.strip_prefix(&root).unwrap_or(path).display().to_string()
+    }
+
     fn normalize_path(&self, path: &Path) -> PathBuf {
         // Use the standard library's path normalization
         let mut normalized = PathBuf::new();
@@ -341,4 +401,85 @@ remote_states:
         assert_eq!(api_service.config.depends.len(), 1);
         assert!(api_service.config.depends.contains(&"../database".to_string()));
     }
+
+    #[test]
+    fn test_find_project_root_and_module_from_subdirectory() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        fs::write(root.join("workspace.envie"), "version: \"1.0\"\nservices:\n  - path: services/api\n").unwrap();
+        let lambda_dir = root.join("services").join("api").join("modules").join("lambda");
+        fs::create_dir_all(lambda_dir.join("src")).unwrap();
+        fs::write(root.join("services").join("api").join(".envie"), "name: api\nmodules:\n  - name: lambda\n").unwrap();
+
+        assert_eq!(ServiceRegistry::find_project_root(lambda_dir.join("src")), Some(root.to_path_buf()));
+        assert_eq!(ServiceRegistry::find_project_root(root), Some(root.to_path_buf()));
+
+        let registry = ServiceRegistry::discover_from_path(root).unwrap();
+        let (key, module) = registry.find_module_containing(lambda_dir.join("src")).unwrap();
+        assert_eq!(key, "api/lambda");
+        assert_eq!(module.path, lambda_dir);
+        assert!(registry.find_module_containing(root.join("services")).is_none());
+
+        assert_eq!(registry.find_module("lambda", None).unwrap().0, "api/lambda");
+        assert_eq!(registry.find_module("api/lambda", None).unwrap().0, "api/lambda");
+        assert!(registry.find_module("lambda", Some("web")).is_err());
+    }
+
+    #[test]
+    fn test_resolve_references_by_path() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        fs::write(root.join("workspace.envie"), "version: \"1.0\"\nservices:\n  - path: services/api\n  - path: services/db-service\n").unwrap();
+        fs::create_dir_all(root.join("services/api")).unwrap();
+        fs::write(root.join("services/api/.envie"), "name: api\nmodules:\n  - name: lambda\n  - name: gateway\n").unwrap();
+        // The directory name differs from the service name and the module path is nested
+        fs::create_dir_all(root.join("services/db-service/modules/db/v2")).unwrap();
+        fs::write(root.join("services/db-service/.envie"), "name: database\nmodules:\n  - name: dynamodb\n    path: modules/db/v2\n").unwrap();
+
+        let registry = ServiceRegistry::discover_from_path(root).unwrap();
+        for reference in [
+            "../../db-service/modules/db/v2",
+            "../../../db-service/modules/db/v2",
+            "../db-service/modules/db/v2",
+            "database/dynamodb",
+            "database.dynamodb",
+        ] {
+            assert_eq!(registry.resolve_module_reference(reference, "api/lambda").unwrap(), "database/dynamodb", "{}", reference);
+        }
+        assert_eq!(registry.resolve_module_reference("gateway", "api/lambda").unwrap(), "api/gateway");
+        assert_eq!(registry.resolve_module_reference("./gateway", "api/lambda").unwrap(), "api/gateway");
+        assert_eq!(registry.find_module_by_path(root.join("services/db-service/modules/./db/v2")).unwrap().0, "database/dynamodb");
+
+        let error = registry.resolve_module_reference("../../db-service/modules/db/v3", "api/lambda").unwrap_err();
+        assert_eq!(error.to_string(), "Dependency resolution error: Dependency '../../db-service/modules/db/v3' of api/lambda does not match any discovered module. \
+            Considered: services/api/db-service/modules/db/v3, services/db-service/modules/db/v3, db-service/modules/db/v3");
+        let error = registry.resolve_module_reference("database.rds", "api/lambda").unwrap_err();
+        assert!(error.to_string().contains("Considered: database/rds, services/api/modules/lambda/database.rds"));
+
+        assert_eq!(registry.resolve_dependency_name("../db-service", "api").unwrap(), "database");
+        assert_eq!(registry.resolve_dependency_name("database", "api").unwrap(), "database");
+        let error = registry.resolve_dependency_name("../database", "api").unwrap_err();
+        assert!(error.to_string().contains("Dependency '../database' of service 'api' does not match any discovered service. Considered: services/database"));
+    }
+
+    #[test]
+    fn test_broken_and_unknown_keys_in_service_configs() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        fs::create_dir_all(root.join("services").join("api")).unwrap();
+        fs::write(root.join("services").join("api").join(".envie"), "name: api\nowner: team-a\n").unwrap();
+
+        // Unknown keys are ignored unless workspace.envie opts into strict parsing
+        fs::write(root.join("workspace.envie"), "version: \"1.0\"\nservices:\n  - path: services/api\n").unwrap();
+        assert!(ServiceRegistry::discover_from_path(root).unwrap().services.contains_key("api"));
+
+        fs::write(root.join("workspace.envie"), "version: \"1.0\"\nstrict: true\nservices:\n  - path: services/api\n").unwrap();
+        let strict = ServiceRegistry::discover_from_path(root).unwrap_err();
+        assert!(strict.to_string().contains("unknown key 'owner'"));
+
+        // A service that fails to parse is reported, not skipped
+        fs::write(root.join("services").join("api").join(".envie"), "name: [api\n").unwrap();
+        let broken = ServiceRegistry::discover_from_path(root).unwrap_err();
+        assert!(broken.to_string().contains("Failed to parse service config"));
+    }
 }
@@ -0,0 +1,205 @@
+use crate::common::*;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// Every placeholder a pattern may use
+pub const PLACEHOLDERS: &[&str] = &[
+    "project", "id", "workspace", "environment", "service", "module", "branch", "user", "region",
+];
+
+/// Placeholders available in `naming_pattern`; the workspace does not exist yet when it is named
+pub const NAMING_PLACEHOLDERS: &[&str] = &["project", "id", "branch", "user", "region"];
+
+/// Placeholders available in `key_pattern`
+pub const KEY_PLACEHOLDERS: &[&str] = &[
+    "project", "workspace", "environment", "service", "module", "branch", "user", "region",
+];
+
+/// A pattern such as `{project}-{id}` or `ephemeral/{workspace}/{service}/{module}/terraform.tfstate`
+#[derive(Debug, Clone)]
+pub struct Template {
+    parts: Vec<TemplatePart>,
+}
+
+#[derive(Debug, Clone)]
+enum TemplatePart {
+    Text(String),
+    Placeholder(String),
+}
+
+impl Template {
+    /// Parse `pattern`, rejecting placeholders outside `allowed`. `location` names the setting in errors.
+    pub fn parse(pattern: &str, allowed: &[&str], location: &str) -> Result<Self> {
+        let mut parts = Vec::new();
+        let mut rest = pattern;
+
+        while let Some(start) = rest.find('{') {
+            if start > 0 {
+                parts.push(TemplatePart::Text(rest[..start].to_string()));
+            }
+            let end = rest[start..].find('}').ok_or_else(|| EnvieError::ConfigError(
+                format!("{}: unclosed '{{' in pattern '{}'", location, pattern)
+            ))? + start;
+
+            let name = &rest[start + 1..end];
+            if !PLACEHOLDERS.contains(&name) {
+                return Err(EnvieError::ConfigError(format!(
+                    "{}: unknown placeholder {{{}}} in pattern '{}'. Available: {}",
+                    location, name, pattern, Self::list(allowed)
+                )));
+            }
+            if !allowed.contains(&name) {
+                return Err(EnvieError::ConfigError(format!(
+                    "{}: placeholder {{{}}} cannot be used here. Available: {}",
+                    location, name, Self::list(allowed)
+                )));
+            }
+            parts.push(TemplatePart::Placeholder(name.to_string()));
+            rest = &rest[end + 1..];
+        }
+        if rest.contains('}') {
+            return Err(EnvieError::ConfigError(
+                format!("{}: unmatched '}}' in pattern '{}'", location, pattern)
+            ));
+        }
+        if !rest.is_empty() {
+            parts.push(TemplatePart::Text(rest.to_string()));
+        }
+
+        Ok(Self { parts })
+    }
+
+    pub fn render(&self, context: &TemplateContext) -> Result<String> {
+        let mut rendered = String::new();
+        for part in &self.parts {
+            match part {
+                TemplatePart::Text(text) => rendered.push_str(text),
+                TemplatePart::Placeholder(name) => rendered.push_str(&context.value(name)?),
+            }
+        }
+        Ok(rendered)
+    }
+
+    /// Whether the pattern contains `{placeholder}`
+    pub fn uses(&self, placeholder: &str) -> bool {
+        self.parts.iter().any(|part| matches!(part, TemplatePart::Placeholder(name) if name == placeholder))
+    }
+
+    fn list(placeholders: &[&str]) -> String {
+        placeholders.iter().map(|name| format!("{{{}}}", name)).collect::<Vec<_>>().join(", ")
+    }
+}
+
+/// Values for the placeholders of a pattern. `{branch}`, `{user}` and `{region}` fall back to the
+/// git checkout and the environment when they are not given.
+#[derive(Debug, Clone)]
+pub struct TemplateContext {
+    root: PathBuf,
+    values: HashMap<&'static str, String>,
+}
+
+impl TemplateContext {
+    pub fn new<P: AsRef<Path>>(root: P) -> Self {
+        Self {
+            root: root.as_ref().to_path_buf(),
+            values: HashMap::new(),
+        }
+    }
+
+    pub fn with(mut self, placeholder: &'static str, value: &str) -> Self {
+        self.values.insert(placeholder, value.to_string());
+        self
+    }
+
+    fn value(&self, placeholder: &str) -> Result<String> {
+        if let Some(value) = self.values.get(placeholder) {
+            return Ok(value.clone());
+        }
+
+        let value = match placeholder {
+            "branch" => current_git_branch(&self.root).map(|branch| slug(&branch)),
+            "user" => std::env::var("USER").or_else(|_| std::env::var("USERNAME")).ok().map(|user| slug(&user)),
+            "region" => std::env::var("AWS_REGION").or_else(|_| std::env::var("AWS_DEFAULT_REGION")).ok(),
+            _ => None,
+        };
+        value.filter(|value| !value.is_empty()).ok_or_else(|| EnvieError::ConfigError(
+            format!("No value for placeholder {{{}}}", placeholder)
+        ))
+    }
+}
+
+/// Lowercase `value`, replacing anything but letters, digits, `-` and `_` with `-`
+pub fn slug(value: &str) -> String {
+    let mut slug = String::new();
+    for c in value.chars() {
+        if c.is_ascii_alphanumeric() || c == '_' {
+            slug.push(c.to_ascii_lowercase());
+        } else if !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+    slug.trim_matches('-').to_string()
+}
+
+/// Make a rendered name usable as a Terraform workspace name (a single URL path segment)
+pub fn sanitize_workspace_name(name: &str) -> Result<String> {
+    let mut sanitized = String::new();
+    for c in name.chars() {
+        if c.is_ascii_alphanumeric() || c == '_' || c == '-' {
+            sanitized.push(c);
+        } else if !sanitized.ends_with('-') {
+            sanitized.push('-');
+        }
+    }
+    let sanitized = sanitized.trim_matches('-').to_string();
+
+    if sanitized.is_empty() {
+        return Err(EnvieError::ValidationError(
+            format!("'{}' does not make a valid workspace name", name)
+        ));
+    }
+    Ok(sanitized)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_template() {
+        let template = Template::parse(
+            "ephemeral/{workspace}/{service}/{module}/terraform.tfstate",
+            KEY_PLACEHOLDERS,
+            "key_pattern",
+        ).unwrap();
+        let context = TemplateContext::new(".")
+            .with("workspace", "myapp-123")
+            .with("service", "api")
+            .with("module", "lambda");
+
+        assert_eq!(template.render(&context).unwrap(), "ephemeral/myapp-123/api/lambda/terraform.tfstate");
+
+        let missing = Template::parse("{project}-{id}", NAMING_PLACEHOLDERS, "naming_pattern").unwrap();
+        assert!(missing.render(&context).unwrap_err().to_string().contains("No value for placeholder {project}"));
+    }
+
+    #[test]
+    fn test_parse_rejects_bad_placeholders() {
+        let unknown = Template::parse("{repo}-{id}", NAMING_PLACEHOLDERS, "environments.ephemeral.naming_pattern").unwrap_err();
+        assert!(unknown.to_string().contains("environments.ephemeral.naming_pattern: unknown placeholder {repo}"));
+
+        let unavailable = Template::parse("{project}-{module}", NAMING_PLACEHOLDERS, "naming_pattern").unwrap_err();
+        assert!(unavailable.to_string().contains("placeholder {module} cannot be used here"));
+
+        assert!(Template::parse("{project", NAMING_PLACEHOLDERS, "naming_pattern").is_err());
+        assert!(Template::parse("project}", NAMING_PLACEHOLDERS, "naming_pattern").is_err());
+    }
+
+    #[test]
+    fn test_sanitize_workspace_name() {
+        assert_eq!(sanitize_workspace_name("myapp-123").unwrap(), "myapp-123");
+        assert_eq!(sanitize_workspace_name("My App/feature x").unwrap(), "My-App-feature-x");
+        assert!(sanitize_workspace_name("//").is_err());
+        assert_eq!(slug("Feature/Add_Login"), "feature-add_login");
+    }
+}
@@ -1,8 +1,14 @@
-use crate::common::Result;
+use crate::common::{EnvieError, Result};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
-use std::path::Path;
-use std::process::Command;
+use std::collections::{HashMap, VecDeque};
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::Mutex;
+
+/// Number of output lines included in the error of a failed Terraform command
+const ERROR_TAIL_LINES: usize = 30;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TerraformOutput {
@@ -11,6 +17,61 @@ pub struct TerraformOutput {
     pub output_type: String,
 }
 
+/// The parts of `terraform show -json <planfile>` Envie reads
+#[derive(Debug, Clone, Deserialize)]
+pub struct TerraformPlanJson {
+    #[serde(default)]
+    pub resource_changes: Vec<ResourceChange>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ResourceChange {
+    pub change: Change,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Change {
+    pub actions: Vec<String>,
+}
+
+/// Resource counts of a plan, counted the way `terraform plan` reports them
+/// (a replacement is one add and one destroy)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PlanSummary {
+    pub add: usize,
+    pub change: usize,
+    pub destroy: usize,
+}
+
+impl PlanSummary {
+    pub fn from_plan(plan: &TerraformPlanJson) -> Self {
+        let mut summary = Self::default();
+        for resource in &plan.resource_changes {
+            for action in &resource.change.actions {
+                match action.as_str() {
+                    "create" => summary.add += 1,
+                    "update" => summary.change += 1,
+                    "delete" => summary.destroy += 1,
+                    _ => {}
+                }
+            }
+        }
+        summary
+    }
+
+    pub fn has_changes(&self) -> bool {
+        self.add + self.change + self.destroy > 0
+    }
+}
+
+impl std::ops::AddAssign for PlanSummary {
+    fn add_assign(&mut self, other: Self) {
+        self.add += other.add;
+        self.change += other.change;
+        self.destroy += other.destroy;
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TerraformState {
     pub service: String,
@@ -20,6 +81,12 @@ pub struct TerraformState {
 pub struct TerraformManager {
     working_directory: std::path::PathBuf,
     verbose: bool,
+    /// Prefix of every streamed output line, usually the `service/module` key
+    label: Option<String>,
+    /// File the full output of every command is appended to
+    log_file: Option<PathBuf>,
+    /// Keep Terraform output off the terminal (it still goes to the log and error tail)
+    quiet: bool,
 }
 
 impl TerraformManager {
@@ -27,6 +94,9 @@ impl TerraformManager {
         Self {
             working_directory: working_directory.as_ref().to_path_buf(),
             verbose: false,
+            label: None,
+            log_file: None,
+            quiet: false,
         }
     }
 
@@ -35,29 +105,45 @@ impl TerraformManager {
         self
     }
 
+    pub fn with_label(mut self, label: &str) -> Self {
+        self.label = Some(label.to_string());
+        self
+    }
+
+    pub fn with_log_file(mut self, log_file: Option<PathBuf>) -> Self {
+        self.log_file = log_file;
+        self
+    }
+
+    pub fn with_quiet(mut self, quiet: bool) -> Self {
+        self.quiet = quiet;
+        self
+    }
+
     pub fn init(&self) -> Result<()> {
         self.run_command("init", &[], false)
     }
 
+    /// Initialize against the backend in the configuration, ignoring the one used last time
+    pub fn init_reconfigure(&self) -> Result<()> {
+        self.run_command("init", &["-reconfigure"], false)
+    }
+
     pub fn init_with_upgrade(&self) -> Result<()> {
         self.run_command("init", &["-upgrade"], false)
     }
 
     pub fn workspace_list(&self) -> Result<Vec<String>> {
         let output = self.run_command_capture("workspace", &["list"], false)?;
+        // The selected workspace is marked with a leading "* "
         let workspaces: Vec<String> = output
             .lines()
-            .map(|line| line.trim().to_string())
+            .map(|line| line.trim().trim_start_matches("* ").to_string())
             .filter(|line| !line.is_empty())
             .collect();
         Ok(workspaces)
     }
 
-    pub fn workspace_show(&self) -> Result<String> {
-        let output = self.run_command_capture("workspace", &["show"], false)?;
-        Ok(output.trim().to_string())
-    }
-
     pub fn workspace_select(&self, workspace: &str) -> Result<()> {
         self.run_command("workspace", &["select", workspace], false)
     }
@@ -70,6 +156,26 @@ impl TerraformManager {
         self.run_command("workspace", &["delete", workspace], false)
     }
 
+    pub fn select_or_create_workspace(&self, workspace: &str) -> Result<()> {
+        if self.workspace_list()?.iter().any(|w| w == workspace) {
+            self.workspace_select(workspace)
+        } else {
+            self.workspace_new(workspace)
+        }
+    }
+
+    pub fn plan(&self, plan_file: &Path) -> Result<()> {
+        let out_arg = format!("-out={}", plan_file.display());
+        self.run_command("plan", &["-input=false", &out_arg], false)
+    }
+
+    pub fn show_plan_json(&self, plan_file: &Path) -> Result<TerraformPlanJson> {
+        let plan_file = plan_file.to_string_lossy();
+        let output = self.run_command_capture("show", &["-json", &plan_file], false)?;
+        let parsed: TerraformPlanJson = serde_json::from_str(&output)?;
+        Ok(parsed)
+    }
+
     pub fn apply(&self, vars: &[(&str, &str)]) -> Result<()> {
         let mut args = vec!["-auto-approve", "-input=false"];
         let mut var_args = Vec::new();
@@ -84,6 +190,21 @@ impl TerraformManager {
         self.run_command("apply", &args, false)
     }
 
+    /// Apply a saved plan file exactly as it was planned
+    pub fn apply_plan(&self, plan_file: &Path) -> Result<()> {
+        let plan_file = plan_file.to_string_lossy();
+        self.run_command("apply", &["-input=false", &plan_file], false)
+            .map_err(|error| match error {
+                EnvieError::TerraformError(message) if message.contains("Saved plan is stale") => {
+                    EnvieError::ValidationError(format!(
+                        "Saved plan {} is stale: the state changed since it was planned. Run 'envie plan' again.",
+                        plan_file
+                    ))
+                }
+                other => other,
+            })
+    }
+
     pub fn apply_with_output(&self, vars: &[(&str, &str)], output_file: &str) -> Result<()> {
         let mut args = vec!["-auto-approve", "-input=false"];
         let mut var_args = Vec::new();
@@ -119,13 +240,60 @@ impl TerraformManager {
         Ok(parsed)
     }
 
-    pub fn output_value(&self, key: &str) -> Result<serde_json::Value> {
-        let output = self.run_command_capture("output", &["-json", key], false)?;
-        let parsed: serde_json::Value = serde_json::from_str(&output)?;
-        Ok(parsed)
+    /// Run a command, streaming its stdout and stderr line by line as it runs
+    fn run_command(&self, command: &str, args: &[&str], quiet: bool) -> Result<()> {
+        let mut cmd = self.command(command, args);
+        cmd.stdout(Stdio::piped());
+        cmd.stderr(Stdio::piped());
+
+        let output = CommandOutput::new(
+            self.label.as_deref(),
+            quiet || self.quiet,
+            self.log_file.as_deref(),
+        )?;
+        output.log(&format!("$ terraform {} {}", command, args.join(" ")));
+
+        let mut child = cmd.spawn().map_err(|e| EnvieError::ProcessError(
+            format!("Failed to execute terraform {}: {}", command, e)
+        ))?;
+        let stdout = child.stdout.take();
+        let stderr = child.stderr.take();
+
+        let status = std::thread::scope(|scope| {
+            if let Some(stdout) = stdout {
+                let output = &output;
+                scope.spawn(move || output.stream(stdout, false));
+            }
+            if let Some(stderr) = stderr {
+                let output = &output;
+                scope.spawn(move || output.stream(stderr, true));
+            }
+            child.wait()
+        }).map_err(|e| EnvieError::ProcessError(
+            format!("Failed to execute terraform {}: {}", command, e)
+        ))?;
+
+        if status.success() {
+            Ok(())
+        } else {
+            Err(EnvieError::TerraformError(output.failure_message(command, &status.to_string())))
+        }
     }
 
-    fn run_command(&self, command: &str, args: &[&str], _quiet: bool) -> Result<()> {
+    /// Run a command and return its stdout; stderr is only reported on failure
+    fn run_command_capture(&self, command: &str, args: &[&str], _quiet: bool) -> Result<String> {
+        let output = self.command(command, args).output()?;
+
+        if output.status.success() {
+            Ok(String::from_utf8_lossy(&output.stdout).to_string())
+        } else {
+            let captured = CommandOutput::new(self.label.as_deref(), true, None)?;
+            captured.stream(output.stderr.as_slice(), true);
+            Err(EnvieError::TerraformError(captured.failure_message(command, &output.status.to_string())))
+        }
+    }
+
+    fn command(&self, command: &str, args: &[&str]) -> Command {
         let mut cmd = Command::new("terraform");
         cmd.arg(command);
         cmd.args(args);
@@ -135,51 +303,101 @@ impl TerraformManager {
         cmd.env("GODEBUG", "asyncpreemptoff=1");
 
         if self.verbose {
-            println!(">> Running: terraform {} {}", command, args.join(" "));
+            // Quiet callers may be printing JSON on stdout, so the echo goes to stderr
+            let echo = format!(">> Running: terraform {} {}", command, args.join(" "));
+            if self.quiet {
+                eprintln!("{}", echo);
+            } else {
+                println!("{}", echo);
+            }
         }
 
-        let output = cmd.output();
+        cmd
+    }
+}
+
+/// Destination of the output of one Terraform command: the terminal (prefixed with the module
+/// label), an optional log file and the last lines kept for the error message.
+struct CommandOutput {
+    prefix: String,
+    quiet: bool,
+    log_path: Option<PathBuf>,
+    log: Option<Mutex<File>>,
+    tail: Mutex<VecDeque<String>>,
+}
 
-        match output {
-            Ok(output) => {
-                if output.status.success() {
-                    Ok(())
-                } else {
-                    let stderr = String::from_utf8_lossy(&output.stderr);
-                    Err(crate::common::EnvieError::TerraformError(
-                        format!("terraform {} failed: {}", command, stderr)
-                    ))
+impl CommandOutput {
+    fn new(label: Option<&str>, quiet: bool, log_path: Option<&Path>) -> Result<Self> {
+        let log = match log_path {
+            Some(path) => {
+                if let Some(parent) = path.parent() {
+                    std::fs::create_dir_all(parent)?;
                 }
+                let file = std::fs::OpenOptions::new().create(true).append(true).open(path)?;
+                Some(Mutex::new(file))
             }
-            Err(e) => Err(crate::common::EnvieError::ProcessError(
-                format!("Failed to execute terraform {}: {}", command, e)
-            )),
+            None => None,
+        };
+
+        Ok(Self {
+            prefix: label.map(|label| format!("[{}] ", label)).unwrap_or_default(),
+            quiet,
+            log_path: log_path.map(|path| path.to_path_buf()),
+            log,
+            tail: Mutex::new(VecDeque::with_capacity(ERROR_TAIL_LINES)),
+        })
+    }
+
+    fn stream<R: Read>(&self, reader: R, is_stderr: bool) {
+        let mut reader = BufReader::new(reader);
+        let mut buffer = Vec::new();
+        while matches!(reader.read_until(b'\n', &mut buffer), Ok(n) if n > 0) {
+            let line = String::from_utf8_lossy(&buffer);
+            self.line(line.trim_end_matches(['\r', '\n']), is_stderr);
+            buffer.clear();
         }
     }
 
-    fn run_command_capture(&self, command: &str, args: &[&str], _quiet: bool) -> Result<String> {
-        let mut cmd = Command::new("terraform");
-        cmd.arg(command);
-        cmd.args(args);
-        cmd.current_dir(&self.working_directory);
-        
-        // Set GODEBUG environment variable as in the original scripts
-        cmd.env("GODEBUG", "asyncpreemptoff=1");
+    fn line(&self, line: &str, is_stderr: bool) {
+        if !self.quiet {
+            if is_stderr {
+                eprintln!("{}{}", self.prefix, line);
+            } else {
+                println!("{}{}", self.prefix, line);
+            }
+        }
 
-        if self.verbose {
-            println!(">> Running: terraform {} {}", command, args.join(" "));
+        self.log(line);
+
+        let mut tail = self.tail.lock().unwrap();
+        if tail.len() == ERROR_TAIL_LINES {
+            tail.pop_front();
         }
+        tail.push_back(line.to_string());
+    }
 
-        let output = cmd.output()?;
+    fn log(&self, line: &str) {
+        if let Some(log) = &self.log {
+            // A log that cannot be written must not fail the Terraform run itself
+            let _ = writeln!(log.lock().unwrap(), "{}", line);
+        }
+    }
 
-        if output.status.success() {
-            Ok(String::from_utf8_lossy(&output.stdout).to_string())
-        } else {
-            let stderr = String::from_utf8_lossy(&output.stderr);
-            Err(crate::common::EnvieError::TerraformError(
-                format!("terraform {} failed: {}", command, stderr)
-            ))
+    fn failure_message(&self, command: &str, status: &str) -> String {
+        let tail = self.tail.lock().unwrap();
+        let mut message = format!("terraform {} failed", command);
+        if !self.prefix.is_empty() {
+            message.push_str(&format!(" for {}", self.prefix.trim_end()));
+        }
+        message.push_str(&format!(" ({})", status));
+
+        if !tail.is_empty() {
+            message.push_str(&format!(":\n{}", tail.iter().map(String::as_str).collect::<Vec<_>>().join("\n")));
         }
+        if let Some(path) = &self.log_path {
+            message.push_str(&format!("\nFull log: {}", path.display()));
+        }
+        message
     }
 }
 
@@ -196,6 +414,49 @@ mod tests {
         assert!(!manager.verbose);
     }
 
+    #[test]
+    fn test_plan_summary_from_show_json() {
+        let plan: TerraformPlanJson = serde_json::from_str(r#"{
+            "format_version": "1.2",
+            "resource_changes": [
+                {"address": "aws_s3_bucket.a", "change": {"actions": ["create"]}},
+                {"address": "aws_s3_bucket.b", "change": {"actions": ["update"]}},
+                {"address": "aws_s3_bucket.c", "change": {"actions": ["delete", "create"]}},
+                {"address": "aws_s3_bucket.d", "change": {"actions": ["no-op"]}},
+                {"address": "data.aws_caller_identity.e", "change": {"actions": ["read"]}}
+            ]
+        }"#).unwrap();
+
+        let summary = PlanSummary::from_plan(&plan);
+        assert_eq!(summary, PlanSummary { add: 2, change: 1, destroy: 1 });
+        assert!(summary.has_changes());
+
+        let empty: TerraformPlanJson = serde_json::from_str(r#"{"format_version": "1.2"}"#).unwrap();
+        assert!(!PlanSummary::from_plan(&empty).has_changes());
+    }
+
+    #[test]
+    fn test_command_output_keeps_tail_and_log() {
+        let temp_dir = TempDir::new().unwrap();
+        let log_path = temp_dir.path().join("logs").join("api").join("lambda.log");
+        let output = CommandOutput::new(Some("api/lambda"), true, Some(&log_path)).unwrap();
+
+        let lines: String = (1..=40).map(|i| format!("line {}\n", i)).collect();
+        output.stream(lines.as_bytes(), false);
+        output.stream("Error: boom\r\n".as_bytes(), true);
+
+        let log = std::fs::read_to_string(&log_path).unwrap();
+        assert!(log.starts_with("line 1\n"));
+        assert!(log.ends_with("Error: boom\n"));
+
+        let message = output.failure_message("apply", "exit status: 1");
+        assert!(message.starts_with("terraform apply failed for [api/lambda] (exit status: 1):\n"));
+        assert!(!message.contains("line 11\n"));
+        assert!(message.contains("line 12\n"));
+        assert!(message.contains("Error: boom"));
+        assert!(message.ends_with(&format!("Full log: {}", log_path.display())));
+    }
+
     #[test]
     fn test_terraform_manager_with_verbose() {
         let temp_dir = TempDir::new().unwrap();
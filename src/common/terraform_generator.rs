@@ -2,240 +2,330 @@ use crate::common::*;
 use std::collections::HashMap;
 use std::path::Path;
 
-#[derive(Debug, Clone)]
-pub struct TerraformGenerator {
-    pub backend_config: BackendConfig,
-}
+/// Terraform loads `*.auto.tfvars.json` files of the working directory by itself
+pub const VARIABLES_FILE: &str = ".envie.auto.tfvars.json";
 
-#[derive(Debug, Clone)]
-pub struct BackendConfig {
-    pub backend_type: String,
-    pub config: HashMap<String, String>,
+/// A `remote_states` entry with its source resolved to a `service/module` key
+#[derive(Debug, Clone, PartialEq)]
+pub struct DeclaredRemoteState {
+    pub name: String,
+    pub source: String,
+    pub environment: String,
+    pub outputs: Vec<String>,
 }
 
-impl Default for BackendConfig {
-    fn default() -> Self {
-        let mut config = HashMap::new();
-        config.insert("bucket".to_string(), "terraform-state-bucket".to_string());
-        config.insert("region".to_string(), "eu-west-1".to_string());
-        config.insert("key".to_string(), "terraform.tfstate".to_string());
-        
-        Self {
-            backend_type: "s3".to_string(),
-            config,
-        }
-    }
-}
+#[derive(Debug, Clone, Default)]
+pub struct TerraformGenerator;
 
 impl TerraformGenerator {
     pub fn new() -> Self {
-        Self {
-            backend_config: BackendConfig::default(),
-        }
+        Self
     }
     
-    pub fn with_backend(mut self, backend: BackendConfig) -> Self {
-        self.backend_config = backend;
-        self
+    /// Resolve the `depends` of a module against the registry
+    pub fn resolve_dependencies(
+        &self,
+        registry: &ServiceRegistry,
+        service_name: &str,
+        module_config: &ModuleConfig,
+    ) -> Result<Vec<DependencyEdge>> {
+        let from = format!("{}/{}", service_name, module_config.name);
+        module_config.depends.iter()
+            .map(|dependency| Ok(DependencyEdge {
+                target: registry.resolve_module_reference(&dependency.path, &from)?,
+                environment: dependency.environment.clone(),
+            }))
+            .collect()
     }
-    
+
+    /// A `terraform_remote_state` data source for each module dependency, named `service_module`
     pub fn generate_remote_state_data_sources(
         &self,
         module_path: &Path,
-        dependencies: &[DependencyReference],
+        dependencies: &[DependencyEdge],
         environment_resolver: &EnvironmentResolver,
         environment_overrides: &std::collections::HashMap<String, String>,
-        service_name: &str,
-        module_name: &str,
-    ) -> Result<String> {
-        let mut generated = String::new();
+    ) -> Result<Vec<Block>> {
+        let mut generated = Vec::new();
         
-        generated.push_str("# Auto-generated by Envie - DO NOT EDIT\n");
-        generated.push_str("# This file is automatically generated and will be overwritten\n\n");
-        
-        // First, scan existing Terraform files to see what data sources are already used
-        let scanner = TerraformScanner::new()?;
-        let existing_deps = scanner.scan_directory(module_path)?;
-        
-        // Create a set of existing data source names to avoid duplicates
-        let existing_names: std::collections::HashSet<String> = existing_deps
-            .iter()
-            .map(|dep| dep.data_source_name.clone())
-            .collect();
+        // Data sources the module already declares itself
+        let hand_written = self.hand_written_data_sources(module_path)?;
         
         for dependency in dependencies {
             // Check for CLI override for this dependency
-            let (source_service, source_module) = self.extract_service_module_from_source(&dependency.path)?;
-            let environment_to_use = if let Some(override_env) = environment_overrides.get(&source_service) {
-                override_env.clone()
-            } else {
-                dependency.environment.clone()
-            };
+            let (source_service, source_module) = split_module_key(&dependency.target);
+            let environment_to_use = dependency_environment(
+                environment_overrides,
+                source_service,
+                source_module,
+                &dependency.environment,
+            );
             
-            let resolved_env = environment_resolver.resolve_environment(&environment_to_use)?;
+            let resolved_env = environment_resolver.resolve_environment(environment_to_use)?;
             
             // Generate a more descriptive name: service_module
-            let data_source_name = if source_service == "current" {
-                format!("{}_{}", service_name, source_module)
-            } else {
-                format!("{}_{}", source_service, source_module)
-            };
+            let data_source_name = format!("{}_{}", source_service, source_module);
             
-            // Skip if this data source already exists
-            if existing_names.contains(&data_source_name) {
-                continue;
+            generated.extend(self.remote_state_block(
+                &data_source_name,
+                &hand_written,
+                environment_resolver,
+                &resolved_env,
+                source_service,
+                source_module,
+            )?);
+        }
+        
+        Ok(generated)
+    }
+
+    /// Resolve the `remote_states` of a module against the registry. Fails if a source module
+    /// does not declare one of the listed outputs.
+    pub fn resolve_remote_states(
+        &self,
+        registry: &ServiceRegistry,
+        service_name: &str,
+        module_config: &ModuleConfig,
+    ) -> Result<Vec<DeclaredRemoteState>> {
+        let scanner = TerraformScanner::new()?;
+        let mut declared: Vec<DeclaredRemoteState> = Vec::new();
+
+        for remote in &module_config.remote_states {
+            let context = format!("remote state '{}' of {}/{}", remote.name, service_name, module_config.name);
+            if !hcl::is_identifier(&remote.name) {
+                return Err(EnvieError::ValidationError(format!(
+                    "Name of {} must start with a letter or _ and contain only letters, digits, _ and -",
+                    context
+                )));
             }
-            
-            generated.push_str(&format!(
-                r#"data "terraform_remote_state" "{}" {{
-  backend = "{}"
-  workspace = "{}"
-  
-  config = {{
-"#,
-                data_source_name,
-                resolved_env.backend.backend_type,
-                resolved_env.workspace
-            ));
-            
-            let state_key = environment_resolver.generate_state_key(&resolved_env, &source_service, &source_module);
-            for (key, value) in &resolved_env.backend.config {
-                if key == "key_pattern" {
-                    generated.push_str(&format!("    key = \"{}\"\n", state_key));
-                } else {
-                    generated.push_str(&format!("    {} = \"{}\"\n", key, value));
-                }
+            if declared.iter().any(|other| other.name == remote.name) {
+                return Err(EnvieError::ValidationError(format!("{} is declared more than once", context)));
             }
-            
-            generated.push_str("  }\n}\n\n");
+
+            let source = registry.resolve_module_reference(&remote.source, &format!("{}/{}", service_name, module_config.name))?;
+            let available = scanner.scan_outputs(&registry.modules[&source].path)?;
+            if let Some(missing) = remote.outputs.iter().find(|output| !available.contains(*output)) {
+                return Err(EnvieError::ValidationError(format!(
+                    "{} lists output '{}', but {} does not declare it. Declared outputs: {}",
+                    context,
+                    missing,
+                    source,
+                    available.iter().cloned().collect::<Vec<_>>().join(", ")
+                )));
+            }
+
+            declared.push(DeclaredRemoteState {
+                name: remote.name.clone(),
+                source,
+                environment: remote.environment.clone(),
+                outputs: remote.outputs.clone(),
+            });
         }
-        
+
+        Ok(declared)
+    }
+
+    /// A data source for each declared remote state and a `locals` block exposing only the
+    /// listed outputs, e.g. `local.database.table_name`
+    pub fn generate_declared_remote_states(
+        &self,
+        module_path: &Path,
+        remote_states: &[DeclaredRemoteState],
+        environment_resolver: &EnvironmentResolver,
+        environment_overrides: &HashMap<String, String>,
+    ) -> Result<Vec<Block>> {
+        if remote_states.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let hand_written = self.hand_written_data_sources(module_path)?;
+        let mut generated = Vec::new();
+        let mut locals = Block::new("locals");
+
+        for remote in remote_states {
+            let (source_service, source_module) = split_module_key(&remote.source);
+            let environment = dependency_environment(
+                environment_overrides,
+                source_service,
+                source_module,
+                &remote.environment,
+            );
+            let resolved_env = environment_resolver.resolve_environment(environment)?;
+            generated.extend(self.remote_state_block(
+                &remote.name,
+                &hand_written,
+                environment_resolver,
+                &resolved_env,
+                source_service,
+                source_module,
+            )?);
+
+            let outputs = remote.outputs.iter()
+                .map(|output| (
+                    output.clone(),
+                    Expression::Reference(format!("data.terraform_remote_state.{}.outputs.{}", remote.name, output)),
+                ))
+                .collect();
+            locals = locals.attribute(&remote.name, Expression::Object(outputs));
+        }
+        generated.push(locals);
+
         Ok(generated)
     }
-    
-    fn extract_service_module_from_source(&self, source: &str) -> Result<(String, String)> {
-        // Convert source path to service/module
-        // e.g., "../database/modules/dynamodb" -> ("database", "dynamodb")
-        // e.g., "./lambda" -> (current_service, "lambda")
-        // e.g., "../../database/modules/dynamodb" -> ("database", "dynamodb")
-        
-        let normalized_source = source
-            .replace("../", "")
-            .replace("./", "")
-            .replace("//", "/");
-        
-        let parts: Vec<&str> = normalized_source.split('/').collect();
-        
-        if parts.len() >= 2 {
-            let service = parts[0].to_string();
-            let module = parts[parts.len() - 1].to_string();
-            Ok((service, module))
-        } else if parts.len() == 1 {
-            // Local module reference
-            Ok(("current".to_string(), parts[0].to_string()))
-        } else {
-            Err(EnvieError::ValidationError(
-                format!("Invalid source path: {}", source)
-            ))
+
+    /// `terraform_remote_state` data sources in the module's own Terraform files, by name
+    fn hand_written_data_sources(&self, module_path: &Path) -> Result<HashMap<String, TerraformDependency>> {
+        Ok(TerraformScanner::new()?.scan_directory(module_path)?
+            .into_iter()
+            .map(|dependency| (dependency.data_source_name.clone(), dependency))
+            .collect())
+    }
+
+    /// The data source reading `source_service/source_module` in `resolved_env`, or `None` when
+    /// the module declares the same one itself. A hand-written data source of the same name
+    /// that reads another backend or state is an error.
+    fn remote_state_block(
+        &self,
+        data_source_name: &str,
+        hand_written: &HashMap<String, TerraformDependency>,
+        environment_resolver: &EnvironmentResolver,
+        resolved_env: &ResolvedEnvironment,
+        source_service: &str,
+        source_module: &str,
+    ) -> Result<Option<Block>> {
+        let backend_kind = resolved_env.backend.kind()?;
+        let settings = environment_resolver.backend_settings(resolved_env, source_service, source_module)?;
+
+        if let Some(existing) = hand_written.get(data_source_name) {
+            let state_field = backend_kind.state_field();
+            let location = settings.iter()
+                .find(|(key, _)| key == state_field)
+                .and_then(|(_, value)| value.as_str())
+                .unwrap_or_default();
+            let existing_location = existing.backend_config.get(state_field).map(|value| value.as_str()).unwrap_or_default();
+            if existing.backend_type == backend_kind.name() && existing_location == location {
+                return Ok(None);
+            }
+            return Err(EnvieError::ValidationError(format!(
+                "{} declares data \"terraform_remote_state\" \"{}\" with backend \"{}\" and {} \"{}\", but Envie generates it for {}/{} with backend \"{}\" and {} \"{}\". Remove or rename the hand-written data source",
+                existing.file.display(),
+                data_source_name,
+                existing.backend_type,
+                state_field,
+                existing_location,
+                source_service,
+                source_module,
+                backend_kind.name(),
+                state_field,
+                location
+            )));
         }
+
+        let mut block = Block::new("data")
+            .label("terraform_remote_state")
+            .label(data_source_name)
+            .attribute("backend", backend_kind.name());
+        // Backends without workspaces keep each environment at its own address instead
+        if backend_kind.supports_workspaces() {
+            block = block.attribute("workspace", resolved_env.workspace.as_str());
+        }
+
+        let config = settings.into_iter()
+            .map(|(key, value)| (key, Expression::from(value)))
+            .collect();
+        Ok(Some(block.blank_line().attribute("config", Expression::Object(config))))
     }
     
-    fn generate_state_key(&self, source: &str, workspace: &str) -> Result<String> {
-        // Convert source path to state key
-        // e.g., "../database/modules/dynamodb" -> "database/dynamodb/terraform.tfstate"
-        let normalized_source = source
-            .replace("../", "")
-            .replace("./", "")
-            .replace("//", "/");
-        
-        Ok(format!("{}/terraform.tfstate", normalized_source))
+    fn generated_file() -> HclFile {
+        HclFile::new()
+            .comment("Auto-generated by Envie - DO NOT EDIT")
+            .comment("This file is automatically generated and will be overwritten")
+    }
+
+    /// Write `.envie-backend.tf`, pointing the module's own state at its key in `resolved_env`.
+    /// Fails if the module declares a backend of its own.
+    pub fn write_backend_file(
+        &self,
+        module_path: &Path,
+        environment_resolver: &EnvironmentResolver,
+        resolved_env: &ResolvedEnvironment,
+        service_name: &str,
+        module_name: &str,
+    ) -> Result<()> {
+        let scanner = TerraformScanner::new()?;
+        if let Some(declared) = scanner.scan_backend_blocks(module_path)?.first() {
+            return Err(EnvieError::ValidationError(format!(
+                "{}/{} declares its own backend \"{}\" in {}:{}. Remove it: Envie writes the backend configuration to {}",
+                service_name,
+                module_name,
+                declared.backend_type,
+                declared.file.display(),
+                declared.line,
+                BACKEND_FILE
+            )));
+        }
+
+        let content = Self::generated_file()
+            .block(environment_resolver.generate_backend_config(resolved_env, service_name, module_name)?);
+
+        std::fs::write(module_path.join(BACKEND_FILE), content.to_string())?;
+        Ok(())
     }
     
+    /// The values of the module's variables as a tfvars JSON document
+    pub fn generate_module_variables(&self, variables: &ModuleVariables) -> Result<String> {
+        let values: serde_json::Map<String, serde_json::Value> = variables.variables.iter()
+            .map(|(name, variable)| (name.clone(), variable.value.clone()))
+            .collect();
+        let mut content = serde_json::to_string_pretty(&values)?;
+        content.push('\n');
+        Ok(content)
+    }
+
+    /// Write `.envie.auto.tfvars.json`, or remove it when the module gets no variables
+    pub fn write_variables_file(&self, module_path: &Path, variables: &ModuleVariables) -> Result<()> {
+        let path = module_path.join(VARIABLES_FILE);
+        if variables.is_empty() {
+            if path.exists() {
+                std::fs::remove_file(path)?;
+            }
+            return Ok(());
+        }
+
+        std::fs::write(path, self.generate_module_variables(variables)?)?;
+        Ok(())
+    }
     
     pub fn write_generated_files(
         &self,
         module_path: &Path,
-        dependencies: &[DependencyReference],
-        module_config: &ModuleConfig,
+        dependencies: &[DependencyEdge],
+        remote_states: &[DeclaredRemoteState],
         environment_resolver: &EnvironmentResolver,
         environment_overrides: &std::collections::HashMap<String, String>,
-        service_name: &str,
-        module_name: &str,
     ) -> Result<()> {
         // Generate and write remote state data sources
-        let remote_state_content = self.generate_remote_state_data_sources(
+        let data_sources = self.generate_remote_state_data_sources(
             module_path,
             dependencies,
             environment_resolver,
             environment_overrides,
-            service_name,
-            module_name,
         )?;
+        let declared = self.generate_declared_remote_states(
+            module_path,
+            remote_states,
+            environment_resolver,
+            environment_overrides,
+        )?;
+        let content = Self::generated_file().blocks(data_sources).blocks(declared);
         
-        let remote_state_file = module_path.join(".envie-remote-state.tf");
-        std::fs::write(remote_state_file, remote_state_content)?;
+        let remote_state_file = module_path.join(REMOTE_STATE_FILE);
+        std::fs::write(remote_state_file, content.to_string())?;
         
         Ok(())
     }
 }
 
-#[derive(Debug, Clone)]
-pub struct WorkspaceResolver {
-    pub current_workspace: String,
-    pub environment_overrides: HashMap<String, String>,
-    pub service_registry: ServiceRegistry,
-}
-
-impl WorkspaceResolver {
-    pub fn new(current_workspace: String, service_registry: ServiceRegistry) -> Self {
-        Self {
-            current_workspace,
-            environment_overrides: HashMap::new(),
-            service_registry,
-        }
-    }
-    
-    pub fn with_environment_overrides(mut self, overrides: HashMap<String, String>) -> Self {
-        self.environment_overrides = overrides;
-        self
-    }
-    
-    pub fn resolve_workspace(&self, source: &str, explicit_workspace: Option<&str>) -> Result<String> {
-        // If explicitly specified, use that
-        if let Some(workspace) = explicit_workspace {
-            return Ok(workspace.to_string());
-        }
-        
-        // Check for environment overrides
-        if let Some(workspace) = self.environment_overrides.get(source) {
-            return Ok(workspace.clone());
-        }
-        
-        // Try to resolve based on dependency graph
-        if let Some(workspace) = self.resolve_from_dependency_graph(source)? {
-            return Ok(workspace);
-        }
-        
-        // Default to current workspace
-        Ok(self.current_workspace.clone())
-    }
-    
-    fn resolve_from_dependency_graph(&self, source: &str) -> Result<Option<String>> {
-        // This is a simplified implementation
-        // In a real implementation, you'd traverse the dependency graph
-        // to determine which workspace the source should be in
-        
-        // For now, if it's a relative path (../), assume it's in a different workspace
-        if source.starts_with("../") {
-            // This would need more sophisticated logic to determine
-            // which workspace the dependency is actually in
-            return Ok(Some("sandbox".to_string())); // Placeholder
-        }
-        
-        Ok(None)
-    }
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -246,34 +336,113 @@ mod tests {
     fn test_remote_state_generation() {
         let generator = TerraformGenerator::new();
         let temp_dir = TempDir::new().unwrap();
-        let module_path = temp_dir.path();
-        
-        let remote_states = vec![
-            RemoteStateReference {
-                name: "database".to_string(),
-                source: "../database/modules/dynamodb".to_string(),
-                workspace: Some("sandbox".to_string()),
-                outputs: vec!["table_name".to_string(), "table_arn".to_string()],
-            }
-        ];
-        
-        let workspace_resolver = WorkspaceResolver::new(
-            "myapp-123".to_string(),
-            ServiceRegistry {
-                services: HashMap::new(),
-                modules: HashMap::new(),
-            }
-        );
-        
-        let generated = generator.generate_remote_state_data_sources(
-            module_path,
+        let root = temp_dir.path();
+        fs::write(root.join("workspace.envie"), r#"
+version: "1.0"
+project:
+  name: myapp
+services:
+  - path: services/api
+  - path: services/database
+environments:
+  ephemeral:
+    backend:
+      type: local
+  stable:
+    sandbox:
+      workspace: sandbox
+      backend:
+        type: s3
+        config:
+          bucket: terraform-state-stable
+          region: eu-west-1
+"#).unwrap();
+        fs::create_dir_all(root.join("services/database/modules/dynamodb")).unwrap();
+        fs::write(root.join("services/database/.envie"), "name: database\nmodules:\n  - name: dynamodb\n").unwrap();
+        fs::write(
+            root.join("services/database/modules/dynamodb/outputs.tf"),
+            "output \"table_name\" {\n  value = \"users\"\n}\n\noutput \"table_arn\" {\n  value = \"arn\"\n}\n",
+        ).unwrap();
+        fs::create_dir_all(root.join("services/api/modules/lambda")).unwrap();
+        fs::write(root.join("services/api/.envie"), r#"
+name: api
+modules:
+  - name: lambda
+    remote_states:
+      - name: database
+        source: ../../database/modules/dynamodb
+        environment: stable.sandbox
+        outputs: [table_name]
+"#).unwrap();
+
+        let context = DeploymentContext::load(root, "123").unwrap();
+        let module = context.module("api/lambda").unwrap();
+        let remote_states = generator.resolve_remote_states(&context.registry, "api", &module.config).unwrap();
+        assert_eq!(remote_states, vec![DeclaredRemoteState {
+            name: "database".to_string(),
+            source: "database/dynamodb".to_string(),
+            environment: "stable.sandbox".to_string(),
+            outputs: vec!["table_name".to_string()],
+        }]);
+
+        let generated = generator.generate_declared_remote_states(
+            &module.path,
             &remote_states,
-            &workspace_resolver,
+            &context.environment_resolver,
+            &HashMap::new(),
         ).unwrap();
-        
-        assert!(generated.contains("data \"terraform_remote_state\" \"database\""));
-        assert!(generated.contains("workspace = \"sandbox\""));
-        assert!(generated.contains("key = \"database/modules/dynamodb/terraform.tfstate\""));
+        crate::common::hcl::tests::assert_golden("hcl/remote_state.tf", &HclFile::new().blocks(generated).to_string());
+
+        // -E overrides apply to declared remote states too
+        let overridden = generator.generate_declared_remote_states(
+            &module.path,
+            &remote_states,
+            &context.environment_resolver,
+            &HashMap::from([("database".to_string(), "ephemeral".to_string())]),
+        ).unwrap();
+        assert!(overridden[0].to_string().contains("workspace = \"myapp-123\""));
+
+        // Regenerating ignores the previously generated file
+        for _ in 0..2 {
+            generator.write_generated_files(&module.path, &[], &remote_states, &context.environment_resolver, &HashMap::new()).unwrap();
+        }
+        let written = fs::read_to_string(module.path.join(REMOTE_STATE_FILE)).unwrap();
+        assert!(written.contains("data \"terraform_remote_state\" \"database\""));
+
+        // A hand-written data source reading the same state is left to the module
+        fs::write(module.path.join("data.tf"), r#"
+data "terraform_remote_state" "database" {
+  backend = "s3"
+  config = {
+    bucket = "terraform-state-stable"
+    key    = "stable/sandbox/database/dynamodb/terraform.tfstate" # shared table
+  }
+}
+"#).unwrap();
+        let generated = generator.generate_declared_remote_states(&module.path, &remote_states, &context.environment_resolver, &HashMap::new()).unwrap();
+        assert_eq!(generated.len(), 1);
+        assert_eq!(generated[0].to_string().lines().next(), Some("locals {"));
+
+        // One of the same name reading another state is a conflict
+        fs::write(module.path.join("data.tf"), r#"
+data "terraform_remote_state" "database" {
+  backend = "s3"
+  config = {
+    key = "legacy/database.tfstate"
+  }
+}
+"#).unwrap();
+        let error = generator.generate_declared_remote_states(&module.path, &remote_states, &context.environment_resolver, &HashMap::new()).unwrap_err();
+        assert!(error.to_string().contains(
+            "declares data \"terraform_remote_state\" \"database\" with backend \"s3\" and key \"legacy/database.tfstate\", but Envie generates it for database/dynamodb with backend \"s3\" and key \"stable/sandbox/database/dynamodb/terraform.tfstate\""
+        ));
+
+        let mut missing = module.config.clone();
+        missing.remote_states[0].outputs.push("stream_arn".to_string());
+        let error = generator.resolve_remote_states(&context.registry, "api", &missing).unwrap_err();
+        assert!(error.to_string().contains(
+            "remote state 'database' of api/lambda lists output 'stream_arn', but database/dynamodb does not declare it. Declared outputs: table_arn, table_name"
+        ));
     }
     
     #[test]
@@ -285,20 +454,17 @@ mod tests {
         variables.insert("timeout".to_string(), serde_json::Value::Number(serde_json::Number::from(30)));
         variables.insert("memory".to_string(), serde_json::Value::Number(serde_json::Number::from(512)));
         
-        let module_config = ModuleConfig {
-            name: "lambda".to_string(),
-            description: "Lambda function".to_string(),
-            path: "modules/lambda".to_string(),
-            depends: vec![],
-            remote_states: vec![],
-            variables,
-        };
+        let module_variables = ModuleVariables::default()
+            .with(&variables, VariableSource::Module("api/lambda".to_string()));
         
-        let generated = generator.generate_module_variables(&module_config).unwrap();
+        let generated = generator.generate_module_variables(&module_variables).unwrap();
         
-        assert!(generated.contains("variable \"runtime\""));
-        assert!(generated.contains("default = \"nodejs18.x\""));
-        assert!(generated.contains("variable \"timeout\""));
-        assert!(generated.contains("default = 30"));
+        assert_eq!(generated, "{\n  \"memory\": 512,\n  \"runtime\": \"nodejs18.x\",\n  \"timeout\": 30\n}\n");
+
+        let temp_dir = TempDir::new().unwrap();
+        generator.write_variables_file(temp_dir.path(), &module_variables).unwrap();
+        assert!(temp_dir.path().join(VARIABLES_FILE).exists());
+        generator.write_variables_file(temp_dir.path(), &ModuleVariables::default()).unwrap();
+        assert!(!temp_dir.path().join(VARIABLES_FILE).exists());
     }
 }
@@ -1,13 +1,33 @@
 use crate::common::*;
-use std::path::Path;
-use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::collections::{BTreeSet, HashSet};
 use regex::Regex;
 
+/// File Envie writes the backend configuration of a module to
+pub const BACKEND_FILE: &str = ".envie-backend.tf";
+
+/// File Envie writes the remote state data sources of a module to
+pub const REMOTE_STATE_FILE: &str = ".envie-remote-state.tf";
+
+/// Files Envie writes into module directories. Scans skip them: they are not the user's.
+pub const MANAGED_FILES: &[&str] = &[BACKEND_FILE, REMOTE_STATE_FILE];
+
 #[derive(Debug, Clone)]
 pub struct TerraformDependency {
     pub data_source_name: String,
     pub backend_type: String,
+    /// Attributes of the `config` object, with string quotes removed
     pub backend_config: std::collections::HashMap<String, String>,
+    /// File the data source is declared in; empty when scanning content
+    pub file: PathBuf,
+}
+
+/// A `backend` (or `cloud`) block a module declares in its own Terraform files
+#[derive(Debug, Clone, PartialEq)]
+pub struct BackendDeclaration {
+    pub file: PathBuf,
+    pub line: usize,
+    pub backend_type: String,
 }
 
 #[derive(Debug, Clone)]
@@ -15,6 +35,8 @@ pub struct TerraformScanner {
     // Cache for compiled regex patterns
     data_source_pattern: Regex,
     backend_pattern: Regex,
+    backend_block_pattern: Regex,
+    output_block_pattern: Regex,
 }
 
 impl TerraformScanner {
@@ -22,140 +44,213 @@ impl TerraformScanner {
         Ok(Self {
             data_source_pattern: Regex::new(r#"data\s+"terraform_remote_state"\s+"([^"]+)""#)?,
             backend_pattern: Regex::new(r#"backend\s*=\s*"([^"]+)""#)?,
+            backend_block_pattern: Regex::new(r#"^(?:backend\s+"([^"]+)"|cloud)\s*\{"#)?,
+            output_block_pattern: Regex::new(r#"^output\s+"([^"]+)"\s*\{"#)?,
         })
     }
 
     /// Scan a Terraform file and extract all terraform_remote_state data sources
     pub fn scan_file<P: AsRef<Path>>(&self, file_path: P) -> Result<Vec<TerraformDependency>> {
-        let content = std::fs::read_to_string(file_path)?;
-        self.scan_content(&content)
+        let content = std::fs::read_to_string(file_path.as_ref())?;
+        let mut dependencies = self.scan_content(&content)?;
+        for dependency in &mut dependencies {
+            dependency.file = file_path.as_ref().to_path_buf();
+        }
+        Ok(dependencies)
     }
 
     /// Scan Terraform content and extract data sources
     pub fn scan_content(&self, content: &str) -> Result<Vec<TerraformDependency>> {
         let mut dependencies = Vec::new();
-        let mut current_data_source: Option<String> = None;
-        let mut current_backend: Option<String> = None;
-        let mut current_config: std::collections::HashMap<String, String> = std::collections::HashMap::new();
-        let mut in_data_source = false;
-        let mut in_backend_config = false;
+        let mut current: Option<TerraformDependency> = None;
+        // Brace depth within the current data source, and the depth of its `config` object
+        let mut depth = 0;
+        let mut config_depth = None;
 
         for line in content.lines() {
-            let line = line.trim();
+            let line = strip_comment(line).trim();
 
             // Check for data source start
-            if let Some(caps) = self.data_source_pattern.captures(line) {
-                if let Some(prev) = current_data_source.take() {
-                    // Save previous data source
-                    dependencies.push(TerraformDependency {
-                        data_source_name: prev,
-                        backend_type: current_backend.unwrap_or_else(|| "s3".to_string()),
-                        backend_config: current_config.clone(),
+            if current.is_none() {
+                if let Some(caps) = self.data_source_pattern.captures(line) {
+                    current = Some(TerraformDependency {
+                        data_source_name: caps[1].to_string(),
+                        backend_type: "s3".to_string(),
+                        backend_config: std::collections::HashMap::new(),
+                        file: PathBuf::new(),
                     });
+                    depth = brace_change(line);
+                    config_depth = None;
+                    if depth <= 0 {
+                        dependencies.extend(current.take());
+                    }
                 }
-                current_data_source = Some(caps[1].to_string());
-                current_backend = None;
-                current_config.clear();
-                in_data_source = true;
-                in_backend_config = false;
                 continue;
             }
+            let Some(dependency) = current.as_mut() else { continue };
 
-            if in_data_source {
-                // Check for backend type
+            if depth == 1 {
                 if let Some(caps) = self.backend_pattern.captures(line) {
-                    current_backend = Some(caps[1].to_string());
-                    in_backend_config = true;
-                    continue;
-                }
-
-                // Check for config block
-                if line.contains("config") && line.contains("{") {
-                    in_backend_config = true;
-                    continue;
+                    dependency.backend_type = caps[1].to_string();
+                } else if line.starts_with("config") && line.ends_with('{') {
+                    config_depth = Some(depth + 1);
                 }
-
-                // Parse config values
-                if in_backend_config && line.contains("=") {
-                    if let Some((key, value)) = self.parse_config_line(line) {
-                        current_config.insert(key, value);
-                    }
-                    continue;
+            } else if config_depth == Some(depth) {
+                if let Some((key, value)) = self.parse_config_line(line) {
+                    dependency.backend_config.insert(key, value);
                 }
+            }
 
-                // Check for end of data source
-                if line == "}" && !in_backend_config {
-                    if let Some(data_source_name) = current_data_source.take() {
-                        dependencies.push(TerraformDependency {
-                            data_source_name,
-                            backend_type: current_backend.unwrap_or_else(|| "s3".to_string()),
-                            backend_config: current_config.clone(),
-                        });
-                    }
-                    in_data_source = false;
-                    in_backend_config = false;
-                    current_backend = None;
-                    current_config.clear();
-                }
+            depth += brace_change(line);
+            if config_depth.is_some_and(|config_depth| depth < config_depth) {
+                config_depth = None;
+            }
+            if depth <= 0 {
+                dependencies.extend(current.take());
             }
         }
 
         // Handle case where file ends without closing brace
-        if let Some(data_source_name) = current_data_source.take() {
-            dependencies.push(TerraformDependency {
-                data_source_name,
-                backend_type: current_backend.unwrap_or_else(|| "s3".to_string()),
-                backend_config: current_config.clone(),
-            });
-        }
+        dependencies.extend(current);
 
         Ok(dependencies)
     }
 
-    /// Scan all Terraform files in a directory
+    /// Scan the Terraform files of a directory, other than the ones Envie writes
     pub fn scan_directory<P: AsRef<Path>>(&self, dir_path: P) -> Result<Vec<TerraformDependency>> {
         let mut all_dependencies = Vec::new();
         
-        for entry in std::fs::read_dir(dir_path)? {
-            let entry = entry?;
-            let path = entry.path();
-            
-            if path.is_file() && path.extension().map_or(false, |ext| ext == "tf") {
-                let deps = self.scan_file(&path)?;
-                all_dependencies.extend(deps);
-            }
+        for path in terraform_files(dir_path.as_ref())? {
+            let deps = self.scan_file(&path)?;
+            all_dependencies.extend(deps);
         }
         
         Ok(all_dependencies)
     }
 
+    /// Backend blocks declared in the Terraform files of a module, other than the one Envie writes
+    pub fn scan_backend_blocks<P: AsRef<Path>>(&self, dir_path: P) -> Result<Vec<BackendDeclaration>> {
+        let mut declarations = Vec::new();
+
+        for file in terraform_files(dir_path.as_ref())? {
+            let content = std::fs::read_to_string(&file)?;
+            for (index, line) in content.lines().enumerate() {
+                if let Some(caps) = self.backend_block_pattern.captures(line.trim()) {
+                    declarations.push(BackendDeclaration {
+                        file: file.clone(),
+                        line: index + 1,
+                        backend_type: caps.get(1).map_or("cloud", |m| m.as_str()).to_string(),
+                    });
+                }
+            }
+        }
+
+        Ok(declarations)
+    }
+
+    /// Names of the outputs the Terraform files of a module declare
+    pub fn scan_outputs<P: AsRef<Path>>(&self, dir_path: P) -> Result<BTreeSet<String>> {
+        let mut outputs = BTreeSet::new();
+
+        for entry in std::fs::read_dir(dir_path)? {
+            let path = entry?.path();
+            if path.is_file() && path.extension().is_some_and(|ext| ext == "tf") {
+                let content = std::fs::read_to_string(&path)?;
+                outputs.extend(content.lines()
+                    .filter_map(|line| self.output_block_pattern.captures(line.trim()))
+                    .map(|caps| caps[1].to_string()));
+            }
+        }
+
+        Ok(outputs)
+    }
+
     /// Parse a config line like 'bucket = "my-bucket"'
     fn parse_config_line(&self, line: &str) -> Option<(String, String)> {
-        let parts: Vec<&str> = line.split('=').collect();
-        if parts.len() == 2 {
-            let key = parts[0].trim().to_string();
-            let value = parts[1].trim().trim_matches('"').to_string();
-            Some((key, value))
-        } else {
-            None
+        let (key, value) = line.split_once('=')?;
+        let key = key.trim().trim_matches('"').to_string();
+        let value = value.trim().trim_matches('"').to_string();
+        (!key.is_empty()).then_some((key, value))
+    }
+
+    /// Outputs of `data.terraform_remote_state.<data_source_name>` the module's own Terraform
+    /// files read; empty when the module directory does not exist
+    pub fn scan_used_outputs<P: AsRef<Path>>(&self, dir_path: P, data_source_name: &str) -> Result<BTreeSet<String>> {
+        self.scan_references(dir_path.as_ref(), &format!("data.terraform_remote_state.{}.outputs", data_source_name))
+    }
+
+    /// Attributes of `local.<local_name>` the module's own Terraform files read, e.g. the
+    /// outputs of a `remote_states` entry; empty when the module directory does not exist
+    pub fn scan_used_locals<P: AsRef<Path>>(&self, dir_path: P, local_name: &str) -> Result<BTreeSet<String>> {
+        self.scan_references(dir_path.as_ref(), &format!("local.{}", local_name))
+    }
+
+    fn scan_references(&self, dir_path: &Path, prefix: &str) -> Result<BTreeSet<String>> {
+        let mut attributes = BTreeSet::new();
+        if !dir_path.is_dir() {
+            return Ok(attributes);
         }
+
+        for path in terraform_files(dir_path)? {
+            let content = std::fs::read_to_string(&path)?;
+            attributes.extend(extract_references(&content, prefix));
+        }
+
+        Ok(attributes)
     }
+}
 
-    /// Extract used outputs from Terraform content
-    pub fn extract_used_outputs(&self, content: &str, data_source_name: &str) -> HashSet<String> {
-        let mut used_outputs = HashSet::new();
-        let pattern = format!(r#"data\.terraform_remote_state\.{}\.outputs\.(\w+)"#, data_source_name);
-        
-        if let Ok(regex) = Regex::new(&pattern) {
-            for caps in regex.captures_iter(content) {
-                if let Some(output) = caps.get(1) {
-                    used_outputs.insert(output.as_str().to_string());
-                }
-            }
+/// Names of the attributes read as `<prefix>.<name>` in `content`
+fn extract_references(content: &str, prefix: &str) -> HashSet<String> {
+    let pattern = format!(r"\b{}\.([A-Za-z_][A-Za-z0-9_-]*)", regex::escape(prefix));
+    match Regex::new(&pattern) {
+        Ok(regex) => regex.captures_iter(content).map(|caps| caps[1].to_string()).collect(),
+        Err(_) => HashSet::new(),
+    }
+}
+
+/// `.tf` files of a module directory, sorted, without the ones Envie writes
+fn terraform_files(dir_path: &Path) -> Result<Vec<PathBuf>> {
+    let mut files: Vec<PathBuf> = std::fs::read_dir(dir_path)?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.is_file() && path.extension().is_some_and(|ext| ext == "tf"))
+        .filter(|path| path.file_name().is_some_and(|name| !MANAGED_FILES.iter().any(|managed| name == *managed)))
+        .collect();
+    files.sort();
+    Ok(files)
+}
+
+/// The line without a trailing `#` or `//` comment; comment markers inside strings are kept
+fn strip_comment(line: &str) -> &str {
+    let mut in_string = false;
+    let mut chars = line.char_indices().peekable();
+    while let Some((index, c)) = chars.next() {
+        match c {
+            '\\' if in_string => { chars.next(); }
+            '"' => in_string = !in_string,
+            '#' if !in_string => return &line[..index],
+            '/' if !in_string && chars.peek().is_some_and(|(_, next)| *next == '/') => return &line[..index],
+            _ => {}
+        }
+    }
+    line
+}
+
+/// Net number of braces a line opens, ignoring the ones in strings and comments
+fn brace_change(line: &str) -> i32 {
+    let mut change = 0;
+    let mut in_string = false;
+    let mut chars = strip_comment(line).chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' if in_string => { chars.next(); }
+            '"' => in_string = !in_string,
+            '{' if !in_string => change += 1,
+            '}' if !in_string => change -= 1,
+            _ => {}
         }
-        
-        used_outputs
     }
+    change
 }
 
 impl Default for TerraformScanner {
@@ -163,3 +258,78 @@ impl Default for TerraformScanner {
         Self::new().expect("Failed to create TerraformScanner")
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_scan_backend_blocks() {
+        let temp_dir = TempDir::new().unwrap();
+        fs::write(temp_dir.path().join("main.tf"), r#"
+terraform {
+  required_version = ">= 1.5"
+  backend "s3" {
+    bucket = "hardcoded"
+  }
+}
+
+data "terraform_remote_state" "vpc" {
+  backend = "s3"
+}
+"#).unwrap();
+        fs::write(temp_dir.path().join(BACKEND_FILE), "terraform {\n  backend \"local\" {\n  }\n}\n").unwrap();
+
+        let declarations = TerraformScanner::new().unwrap().scan_backend_blocks(temp_dir.path()).unwrap();
+        assert_eq!(declarations, vec![BackendDeclaration {
+            file: temp_dir.path().join("main.tf"),
+            line: 4,
+            backend_type: "s3".to_string(),
+        }]);
+    }
+
+    #[test]
+    fn test_scan_directory() {
+        let temp_dir = TempDir::new().unwrap();
+        fs::write(temp_dir.path().join("data.tf"), r#"
+data "terraform_remote_state" "vpc" {
+  backend   = "gcs"
+  workspace = "sandbox"
+
+  config = {
+    bucket = "state" # "{"
+    prefix = "networking/vpc" // moved from "legacy"
+  }
+}
+
+data "terraform_remote_state" "empty" {}
+
+locals {
+  backend = "ignored"
+}
+"#).unwrap();
+        fs::write(temp_dir.path().join(REMOTE_STATE_FILE), "data \"terraform_remote_state\" \"generated\" {\n  backend = \"local\"\n}\n").unwrap();
+
+        let dependencies = TerraformScanner::new().unwrap().scan_directory(temp_dir.path()).unwrap();
+        assert_eq!(dependencies.len(), 2);
+        assert_eq!(dependencies[0].data_source_name, "vpc");
+        assert_eq!(dependencies[0].backend_type, "gcs");
+        assert_eq!(dependencies[0].backend_config["bucket"], "state");
+        assert_eq!(dependencies[0].backend_config["prefix"], "networking/vpc");
+        assert_eq!(dependencies[0].file, temp_dir.path().join("data.tf"));
+        assert_eq!(dependencies[1].data_source_name, "empty");
+        assert_eq!(dependencies[1].backend_type, "s3");
+    }
+
+    #[test]
+    fn test_scan_outputs() {
+        let temp_dir = TempDir::new().unwrap();
+        fs::write(temp_dir.path().join("outputs.tf"), "output \"table_name\" {\n  value = aws_dynamodb_table.this.name\n}\n\noutput \"table_arn\"{\n  value = aws_dynamodb_table.this.arn\n}\n").unwrap();
+        fs::write(temp_dir.path().join("main.tf"), "# output \"commented\" {}\nresource \"aws_dynamodb_table\" \"this\" {}\n").unwrap();
+
+        let outputs = TerraformScanner::new().unwrap().scan_outputs(temp_dir.path()).unwrap();
+        assert_eq!(outputs.into_iter().collect::<Vec<_>>(), vec!["table_arn", "table_name"]);
+    }
+}
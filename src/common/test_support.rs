@@ -0,0 +1,73 @@
+//! Fixture helpers shared by the test modules
+
+use crate::common::ConfigSchema;
+use serde::de::{DeserializeOwned, Visitor};
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::Path;
+
+/// Write `content` to `path` under `root`, creating the parent directories
+pub fn write_file(root: &Path, path: &str, content: &str) {
+    let path = root.join(path);
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::write(path, content).unwrap();
+}
+
+/// Write the `.envie` of the service in `services/<name>`
+pub fn write_service(root: &Path, name: &str, config: &str) {
+    write_file(root, &format!("services/{}/.envie", name), config);
+}
+
+/// Keys a `#[derive(Deserialize)]` struct accepts, after renames
+pub fn serde_fields<T: DeserializeOwned>() -> BTreeSet<&'static str> {
+    /// Records the field list serde hands to `deserialize_struct`, then gives up
+    struct FieldRecorder<'a>(&'a mut BTreeSet<&'static str>);
+
+    impl<'de> serde::Deserializer<'de> for FieldRecorder<'_> {
+        type Error = serde::de::value::Error;
+
+        fn deserialize_any<V: Visitor<'de>>(self, _visitor: V) -> std::result::Result<V::Value, Self::Error> {
+            Err(serde::de::Error::custom("not a struct"))
+        }
+
+        fn deserialize_struct<V: Visitor<'de>>(
+            self,
+            _name: &'static str,
+            fields: &'static [&'static str],
+            _visitor: V,
+        ) -> std::result::Result<V::Value, Self::Error> {
+            self.0.extend(fields);
+            Err(serde::de::Error::custom("fields recorded"))
+        }
+
+        serde::forward_to_deserialize_any! {
+            bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string bytes byte_buf
+            option unit unit_struct newtype_struct seq tuple tuple_struct map enum identifier ignored_any
+        }
+    }
+
+    let mut fields = BTreeSet::new();
+    let _ = T::deserialize(FieldRecorder(&mut fields));
+    fields
+}
+
+/// Keys declared by an object schema
+pub fn schema_properties(schema: &serde_json::Value) -> BTreeSet<&str> {
+    schema["properties"].as_object()
+        .map(|properties| properties.keys().map(|key| key.as_str()).collect())
+        .unwrap_or_default()
+}
+
+/// Fail when the hand-written schema of `T` and its serde fields disagree, so a field added to
+/// the struct cannot be rejected in strict mode or missing from `envie schema`
+pub fn assert_schema_matches_fields<T: DeserializeOwned + ConfigSchema>() {
+    let schema = T::schema();
+    let fields = serde_fields::<T>();
+    assert!(!fields.is_empty(), "{} is not deserialized as a struct", std::any::type_name::<T>());
+    assert_eq!(schema_properties(&schema), fields, "schema properties of {}", std::any::type_name::<T>());
+
+    let required: BTreeSet<&str> = schema["required"].as_array()
+        .map(|required| required.iter().filter_map(|key| key.as_str()).collect())
+        .unwrap_or_default();
+    assert!(required.is_subset(&fields), "required keys of {} that are not fields: {:?}", std::any::type_name::<T>(), required.difference(&fields).collect::<Vec<_>>());
+}
@@ -0,0 +1,13 @@
+terraform {
+  backend "s3" {
+    assume_role = {
+      role_arn = "arn:aws:iam::123456789012:role/terraform"
+    }
+    bucket              = "terraform-state-ephemeral"
+    encrypt             = true
+    key                 = "ephemeral/api/lambda/terraform.tfstate"
+    max_retries         = 5
+    region              = "eu-west-1"
+    shared_config_files = ["~/.aws/config"]
+  }
+}
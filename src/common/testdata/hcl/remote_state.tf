@@ -0,0 +1,16 @@
+data "terraform_remote_state" "database" {
+  backend   = "s3"
+  workspace = "sandbox"
+
+  config = {
+    bucket = "terraform-state-stable"
+    key    = "stable/sandbox/database/dynamodb/terraform.tfstate"
+    region = "eu-west-1"
+  }
+}
+
+locals {
+  database = {
+    table_name = data.terraform_remote_state.database.outputs.table_name
+  }
+}
@@ -0,0 +1,46 @@
+# Auto-generated by Envie - DO NOT EDIT
+
+terraform {
+  backend "s3" {
+    bucket      = "state"
+    encrypt     = true
+    max_retries = 5
+    assume_role = {
+      duration = "1h"
+      role_arn = "arn:aws:iam::1:role/x"
+    }
+    shared_config_files  = ["~/.aws/config", "~/.aws/other"]
+    workspace_key_prefix = "env"
+  }
+}
+
+locals {
+  quoted     = "say \"hi\" to $${name} and %%{ if x } C:\\temp\n"
+  empty_map  = {}
+  empty_list = []
+  nothing    = null
+  odd_keys = {
+    "1st"        = 2
+    ok           = 3
+    "with space" = 1
+  }
+  nested = [
+    {
+      a = 1
+    },
+    "b",
+  ]
+
+  script      = <<EOT1
+echo $${HOME}
+EOT
+EOT1
+  certificate = <<EOT
+-----BEGIN CERTIFICATE-----
+MIIB
+-----END CERTIFICATE-----
+EOT
+  reference   = data.terraform_remote_state.vpc.outputs.vpc_id
+}
+
+data "terraform_remote_state" "empty" {}
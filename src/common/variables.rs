@@ -0,0 +1,162 @@
+use crate::common::*;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+
+/// Where the effective value of a module variable was set
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VariableSource {
+    /// `defaults.variables` of workspace.envie
+    Defaults,
+    /// `variables` of the service, by name
+    Service(String),
+    /// `variables` of the module, by `service/module` key
+    Module(String),
+    /// `variables` of the environment the module is deployed to, e.g. `stable.sandbox`
+    Environment(String),
+}
+
+impl fmt::Display for VariableSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VariableSource::Defaults => write!(f, "workspace.envie defaults.variables"),
+            VariableSource::Service(name) => write!(f, "service {}", name),
+            VariableSource::Module(key) => write!(f, "module {}", key),
+            VariableSource::Environment(environment) => write!(f, "workspace.envie environments.{}.variables", environment),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ModuleVariable {
+    pub value: serde_json::Value,
+    pub source: VariableSource,
+}
+
+/// The Terraform variables Envie passes to a module, by name
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ModuleVariables {
+    pub variables: BTreeMap<String, ModuleVariable>,
+}
+
+impl ModuleVariables {
+    /// Merge the `variables` of every level, each one overriding the ones before it:
+    /// 1. `defaults.variables` of workspace.envie
+    /// 2. the environment the module is deployed to (`environments.ephemeral.variables` or
+    ///    `environments.stable.<name>.variables`)
+    /// 3. the service
+    /// 4. the module
+    ///
+    /// Values are replaced as a whole; objects are not merged key by key.
+    pub fn resolve(
+        environment_config: &EnvironmentConfig,
+        environment: &EnvironmentType,
+        service: &DiscoveredService,
+        module: &DiscoveredModule,
+    ) -> Self {
+        let variables = Self::default().with(&environment_config.default_variables, VariableSource::Defaults);
+
+        let variables = match environment {
+            EnvironmentType::Ephemeral => variables.with(
+                &environment_config.ephemeral.variables,
+                VariableSource::Environment("ephemeral".to_string()),
+            ),
+            EnvironmentType::Stable(name) => match environment_config.stable.get(name) {
+                Some(stable_env) => variables.with(&stable_env.variables, VariableSource::Environment(format!("stable.{}", name))),
+                None => variables,
+            },
+        };
+
+        variables
+            .with(&service.config.variables, VariableSource::Service(service.config.name.clone()))
+            .with(&module.config.variables, VariableSource::Module(format!("{}/{}", service.config.name, module.config.name)))
+    }
+
+    /// Override with the values of one level
+    pub fn with(mut self, values: &HashMap<String, serde_json::Value>, source: VariableSource) -> Self {
+        for (name, value) in values {
+            self.variables.insert(name.clone(), ModuleVariable {
+                value: value.clone(),
+                source: source.clone(),
+            });
+        }
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.variables.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_variable_precedence() {
+        let workspace = WorkspaceConfig::from_str(r#"
+version: "1.0"
+defaults:
+  variables:
+    region: eu-west-1
+    memory: 128
+    log_level: info
+environments:
+  ephemeral:
+    backend:
+      type: local
+    variables:
+      log_level: debug
+      memory: 1024
+  stable:
+    sandbox:
+      workspace: sandbox
+      backend:
+        type: local
+"#).unwrap();
+        let environment_config = EnvironmentConfig::from_workspace(&workspace).unwrap();
+
+        let service_config = ServiceConfig::from_str(r#"
+name: api
+variables:
+  memory: 256
+  runtime: nodejs18.x
+modules:
+  - name: lambda
+    variables:
+      memory: 512
+"#).unwrap();
+        let module = DiscoveredModule {
+            path: PathBuf::from("services/api/modules/lambda"),
+            config: service_config.modules[0].clone(),
+        };
+        let service = DiscoveredService {
+            path: PathBuf::from("services/api"),
+            config: service_config,
+            modules: vec![module.clone()],
+        };
+
+        let ephemeral = ModuleVariables::resolve(&environment_config, &EnvironmentType::Ephemeral, &service, &module);
+        let effective: Vec<(&str, String, String)> = ephemeral.variables.iter()
+            .map(|(name, variable)| (name.as_str(), variable.value.to_string(), variable.source.to_string()))
+            .collect();
+        assert_eq!(effective, vec![
+            ("log_level", "\"debug\"".to_string(), "workspace.envie environments.ephemeral.variables".to_string()),
+            ("memory", "512".to_string(), "module api/lambda".to_string()),
+            ("region", "\"eu-west-1\"".to_string(), "workspace.envie defaults.variables".to_string()),
+            ("runtime", "\"nodejs18.x\"".to_string(), "service api".to_string()),
+        ]);
+
+        let stable = ModuleVariables::resolve(&environment_config, &EnvironmentType::Stable("sandbox".to_string()), &service, &module);
+        assert_eq!(stable.variables["log_level"].source, VariableSource::Defaults);
+
+        // The service and its modules override the environment
+        let service_level = ModuleVariables::resolve(
+            &environment_config,
+            &EnvironmentType::Ephemeral,
+            &service,
+            &DiscoveredModule { config: ModuleConfig { variables: HashMap::new(), ..module.config.clone() }, ..module.clone() },
+        );
+        assert_eq!(service_level.variables["memory"].source, VariableSource::Service("api".to_string()));
+    }
+}
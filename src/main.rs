@@ -19,8 +19,17 @@ async fn main() {
     // Parse command line arguments
     let cli = Cli::parse();
 
-    // Create command handler
-    let handler = CommandHandler::new();
+    // Create command handler, rooted at the project containing the start directory
+    let handler = match cli.chdir {
+        Some(directory) => match CommandHandler::for_directory(&directory) {
+            Ok(handler) => handler,
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                std::process::exit(1);
+            }
+        },
+        None => CommandHandler::new(),
+    };
 
     // Handle the command
     if let Err(e) = handler.handle_command(cli.command).await {